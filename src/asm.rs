@@ -0,0 +1,800 @@
+//! Assembler
+//!
+//! Turns assembly source with the mnemonics of Cowgod's Chip-8 Technical Reference into a ROM,
+//! i.e. the output of [`disasm`](crate::disasm) in [`Syntax::Cowgod`](crate::disasm::Syntax::Cowgod).
+//!
+//! Each line has an optional `label:`, an optional mnemonic with comma separated operands
+//! and an optional `; comment`. Mnemonics and register names are case-insensitive.
+//!
+//! - `NAME equ expr` defines a constant
+//! - `db expr, ...` emits bytes
+//! - `dw expr, ...` emits big-endian words
+//!
+//! Expressions consist of numbers (`12`, `0x0C`, `#0C`, `0b1100`), labels, constants,
+//! `+`, `-`, `*`, `/` and parentheses.
+
+use crate::errors::{Chip8Error, ParseErrorKind};
+use crate::instructions::{Addr, Instruction, VRegister};
+use crate::vm::PROGRAM_START;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Maximum nesting of constants defined by other constants
+const MAX_SYMBOL_DEPTH: usize = 32;
+
+/// Error at a column, the line is added by the caller
+struct Located {
+    column: usize,
+    kind: ParseErrorKind,
+}
+
+type LocatedResult<T> = core::result::Result<T, Located>;
+
+fn err<T>(column: usize, kind: ParseErrorKind) -> LocatedResult<T> {
+    Err(Located { column, kind })
+}
+
+#[derive(Debug, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(i64),
+    Comma,
+    Colon,
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn tokenize(text: &str) -> LocatedResult<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let column = idx + 1;
+        let punctuation = match chars[idx] {
+            ';' => break,
+            c if c.is_whitespace() => {
+                idx += 1;
+                continue;
+            }
+            ',' => Some(TokenKind::Comma),
+            ':' => Some(TokenKind::Colon),
+            '[' => Some(TokenKind::LeftBracket),
+            ']' => Some(TokenKind::RightBracket),
+            '(' => Some(TokenKind::LeftParen),
+            ')' => Some(TokenKind::RightParen),
+            '+' => Some(TokenKind::Plus),
+            '-' => Some(TokenKind::Minus),
+            '*' => Some(TokenKind::Star),
+            '/' => Some(TokenKind::Slash),
+            _ => None,
+        };
+        if let Some(kind) = punctuation {
+            tokens.push(Token { kind, column });
+            idx += 1;
+            continue;
+        }
+
+        let word_len = chars[idx..]
+            .iter()
+            .skip(1)
+            .take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '.')
+            .count()
+            + 1;
+        let word: String = chars[idx..idx + word_len].iter().collect();
+        let kind = match chars[idx] {
+            '#' => TokenKind::Number(parse_number(&word[1..], 16, column)?),
+            c if c.is_ascii_digit() => {
+                let (digits, radix) = if let Some(hex) = strip_prefix_ignore_case(&word, "0x") {
+                    (hex, 16)
+                } else if let Some(bin) = strip_prefix_ignore_case(&word, "0b") {
+                    (bin, 2)
+                } else {
+                    (word.as_str(), 10)
+                };
+                TokenKind::Number(parse_number(digits, radix, column)?)
+            }
+            c if c.is_alphabetic() || c == '_' || c == '.' => TokenKind::Ident(word),
+            c => return err(column, ParseErrorKind::UnexpectedCharacter(c)),
+        };
+        tokens.push(Token { kind, column });
+        idx += word_len;
+    }
+
+    Ok(tokens)
+}
+
+fn strip_prefix_ignore_case<'a>(word: &'a str, prefix: &str) -> Option<&'a str> {
+    match word.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => Some(&word[prefix.len()..]),
+        _ => None,
+    }
+}
+
+fn parse_number(digits: &str, radix: u32, column: usize) -> LocatedResult<i64> {
+    match i64::from_str_radix(digits, radix) {
+        Ok(value) if !digits.starts_with('+') => Ok(value),
+        _ => err(column, ParseErrorKind::UnexpectedToken),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Symbol(String, usize),
+    Neg(Box<Expr>, usize),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>, usize),
+}
+
+#[derive(Debug)]
+enum Symbol {
+    Label(i64),
+    Constant(Expr),
+}
+
+type Symbols = BTreeMap<String, Symbol>;
+
+fn eval(expr: &Expr, symbols: &Symbols, depth: usize) -> LocatedResult<i64> {
+    match expr {
+        Expr::Number(value) => Ok(*value),
+        Expr::Symbol(name, column) => match symbols.get(name) {
+            Some(Symbol::Label(value)) => Ok(*value),
+            Some(Symbol::Constant(_)) if depth >= MAX_SYMBOL_DEPTH => {
+                err(*column, ParseErrorKind::RecursiveSymbol(name.clone()))
+            }
+            Some(Symbol::Constant(expr)) => {
+                eval(expr, symbols, depth + 1).map_err(|located| match located.kind {
+                    // report recursion where it started
+                    ParseErrorKind::RecursiveSymbol(_) => Located {
+                        column: *column,
+                        kind: ParseErrorKind::RecursiveSymbol(name.clone()),
+                    },
+                    _ => located,
+                })
+            }
+            None => err(*column, ParseErrorKind::UndefinedSymbol(name.clone())),
+        },
+        Expr::Neg(expr, column) => {
+            let value = eval(expr, symbols, depth)?;
+            value
+                .checked_neg()
+                .map_or_else(|| err(*column, ParseErrorKind::ValueOutOfRange(value)), Ok)
+        }
+        Expr::Binary(op, lhs, rhs, column) => {
+            let lhs = eval(lhs, symbols, depth)?;
+            let rhs = eval(rhs, symbols, depth)?;
+            let value = match op {
+                BinaryOp::Add => lhs.checked_add(rhs),
+                BinaryOp::Sub => lhs.checked_sub(rhs),
+                BinaryOp::Mul => lhs.checked_mul(rhs),
+                BinaryOp::Div if rhs == 0 => return err(*column, ParseErrorKind::DivisionByZero),
+                BinaryOp::Div => lhs.checked_div(rhs),
+            };
+            value.map_or_else(|| err(*column, ParseErrorKind::ValueOutOfRange(lhs)), Ok)
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Operand {
+    Register(VRegister),
+    I,
+    IndirectI,
+    DelayTimer,
+    SoundTimer,
+    Key,
+    Font,
+    BigFont,
+    Bcd,
+    Flags,
+    Long(Expr),
+    Expr(Expr),
+}
+
+/// Parser of the tokens of a single line
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    end_column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a TokenKind> {
+        self.tokens.get(self.pos).map(|token| &token.kind)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end_column, |token| token.column)
+    }
+
+    fn next(&mut self) -> LocatedResult<&'a Token> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token)
+            }
+            None => err(self.end_column, ParseErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> LocatedResult<()> {
+        let token = self.next()?;
+        if token.kind == *kind {
+            Ok(())
+        } else {
+            err(token.column, ParseErrorKind::UnexpectedToken)
+        }
+    }
+
+    fn finish(&self) -> LocatedResult<()> {
+        match self.tokens.get(self.pos) {
+            Some(token) => err(token.column, ParseErrorKind::UnexpectedToken),
+            None => Ok(()),
+        }
+    }
+
+    fn ident(&mut self) -> LocatedResult<&'a str> {
+        let token = self.next()?;
+        match &token.kind {
+            TokenKind::Ident(name) => Ok(name),
+            _ => err(token.column, ParseErrorKind::UnexpectedToken),
+        }
+    }
+
+    fn expression(&mut self) -> LocatedResult<Expr> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(TokenKind::Plus) => BinaryOp::Add,
+                Some(TokenKind::Minus) => BinaryOp::Sub,
+                _ => return Ok(lhs),
+            };
+            let column = self.next()?.column;
+            let rhs = self.term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), column);
+        }
+    }
+
+    fn term(&mut self) -> LocatedResult<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(TokenKind::Star) => BinaryOp::Mul,
+                Some(TokenKind::Slash) => BinaryOp::Div,
+                _ => return Ok(lhs),
+            };
+            let column = self.next()?.column;
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), column);
+        }
+    }
+
+    fn unary(&mut self) -> LocatedResult<Expr> {
+        if self.peek() == Some(&TokenKind::Minus) {
+            let column = self.next()?.column;
+            return Ok(Expr::Neg(Box::new(self.unary()?), column));
+        }
+
+        let token = self.next()?;
+        match &token.kind {
+            TokenKind::Number(value) => Ok(Expr::Number(*value)),
+            TokenKind::Ident(name) => Ok(Expr::Symbol(name.clone(), token.column)),
+            TokenKind::LeftParen => {
+                let expr = self.expression()?;
+                self.expect(&TokenKind::RightParen)?;
+                Ok(expr)
+            }
+            _ => err(token.column, ParseErrorKind::UnexpectedToken),
+        }
+    }
+
+    /// Parses comma separated expressions up to the end of the line
+    fn expressions(&mut self) -> LocatedResult<Vec<(Expr, usize)>> {
+        let mut exprs = Vec::new();
+        loop {
+            let column = self.column();
+            exprs.push((self.expression()?, column));
+            if self.peek() != Some(&TokenKind::Comma) {
+                self.finish()?;
+                return Ok(exprs);
+            }
+            self.next()?;
+        }
+    }
+
+    fn operand(&mut self) -> LocatedResult<Operand> {
+        if self.peek() == Some(&TokenKind::LeftBracket) {
+            self.next()?;
+            let column = self.column();
+            if !self.ident()?.eq_ignore_ascii_case("i") {
+                return err(column, ParseErrorKind::UnexpectedToken);
+            }
+            self.expect(&TokenKind::RightBracket)?;
+            return Ok(Operand::IndirectI);
+        }
+
+        if let Some(TokenKind::Ident(name)) = self.peek() {
+            let keyword = match name.to_ascii_lowercase().as_str() {
+                "i" => Some(Operand::I),
+                "dt" => Some(Operand::DelayTimer),
+                "st" => Some(Operand::SoundTimer),
+                "k" => Some(Operand::Key),
+                "f" => Some(Operand::Font),
+                "hf" => Some(Operand::BigFont),
+                "b" => Some(Operand::Bcd),
+                "r" => Some(Operand::Flags),
+                "long" => {
+                    self.next()?;
+                    return Ok(Operand::Long(self.expression()?));
+                }
+                register => parse_register(register).map(Operand::Register),
+            };
+            if let Some(operand) = keyword {
+                self.next()?;
+                return Ok(operand);
+            }
+        }
+
+        Ok(Operand::Expr(self.expression()?))
+    }
+
+    /// Parses comma separated operands up to the end of the line
+    fn operands(&mut self) -> LocatedResult<Vec<(Operand, usize)>> {
+        let mut operands = Vec::new();
+        if self.peek().is_none() {
+            return Ok(operands);
+        }
+        loop {
+            let column = self.column();
+            operands.push((self.operand()?, column));
+            if self.peek() != Some(&TokenKind::Comma) {
+                self.finish()?;
+                return Ok(operands);
+            }
+            self.next()?;
+        }
+    }
+}
+
+/// Parses `v0` .. `vf`
+fn parse_register(name: &str) -> Option<VRegister> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v'), Some(digit), None) => {
+            let idx = digit.to_digit(16)?;
+            VRegister::try_from(idx as u8).ok()
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+enum StatementKind {
+    Instruction {
+        mnemonic: String,
+        column: usize,
+        operands: Vec<(Operand, usize)>,
+    },
+    Bytes(Vec<(Expr, usize)>),
+    Words(Vec<(Expr, usize)>),
+}
+
+#[derive(Debug)]
+struct Statement {
+    line: usize,
+    kind: StatementKind,
+}
+
+/// Assembles the `source` into a ROM loaded at `0x200`
+///
+/// # Errors
+///
+/// Will return [`Chip8Error::Parse`] with the line and column of the first error in `source`.
+pub fn assemble(source: &str) -> crate::errors::Result<Vec<u8>> {
+    assemble_at(source, Addr::from(PROGRAM_START))
+}
+
+/// Assembles the `source` into a ROM loaded at `start`
+///
+/// # Errors
+///
+/// Will return [`Chip8Error::Parse`] with the line and column of the first error in `source`.
+pub fn assemble_at(source: &str, start: Addr) -> crate::errors::Result<Vec<u8>> {
+    let mut symbols = Symbols::new();
+    let mut statements = Vec::new();
+
+    // first pass parses all lines and defines all symbols
+    let mut addr = i64::from(u16::from(start));
+    for (idx, text) in source.lines().enumerate() {
+        let line = idx + 1;
+        let located = |Located { column, kind }| Chip8Error::Parse { line, column, kind };
+
+        let tokens = tokenize(text).map_err(located)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            end_column: text.chars().count() + 1,
+        };
+
+        if let [Token {
+            kind: TokenKind::Ident(name),
+            column,
+        }, Token {
+            kind: TokenKind::Colon,
+            ..
+        }, ..] = &tokens[..]
+        {
+            define(&mut symbols, name, Symbol::Label(addr), *column).map_err(located)?;
+            parser.pos = 2;
+        }
+        if parser.peek().is_none() {
+            continue;
+        }
+
+        let column = parser.column();
+        let mnemonic = parser.ident().map_err(located)?;
+        if let Some(TokenKind::Ident(keyword)) = parser.peek() {
+            if keyword.eq_ignore_ascii_case("equ") {
+                parser.next().map_err(located)?;
+                let expr = parser.expression().map_err(located)?;
+                parser.finish().map_err(located)?;
+                define(&mut symbols, mnemonic, Symbol::Constant(expr), column).map_err(located)?;
+                continue;
+            }
+        }
+
+        let kind = match mnemonic.to_ascii_lowercase().as_str() {
+            "db" => {
+                let exprs = parser.expressions().map_err(located)?;
+                addr += exprs.len() as i64;
+                StatementKind::Bytes(exprs)
+            }
+            "dw" => {
+                let exprs = parser.expressions().map_err(located)?;
+                addr += 2 * exprs.len() as i64;
+                StatementKind::Words(exprs)
+            }
+            _ => {
+                let operands = parser.operands().map_err(located)?;
+                let long = operands
+                    .iter()
+                    .any(|(operand, _)| matches!(operand, Operand::Long(_)));
+                addr += if long { 4 } else { 2 };
+                StatementKind::Instruction {
+                    mnemonic: mnemonic.into(),
+                    column,
+                    operands,
+                }
+            }
+        };
+        statements.push(Statement { line, kind });
+    }
+
+    // second pass evaluates all expressions and encodes all instructions
+    let mut rom = Vec::new();
+    for statement in &statements {
+        let line = statement.line;
+        let located = |Located { column, kind }| Chip8Error::Parse { line, column, kind };
+
+        match &statement.kind {
+            StatementKind::Bytes(exprs) => {
+                for (expr, column) in exprs {
+                    rom.push(
+                        to_byte(eval(expr, &symbols, 0).map_err(located)?, *column)
+                            .map_err(located)?,
+                    );
+                }
+            }
+            StatementKind::Words(exprs) => {
+                for (expr, column) in exprs {
+                    let value = eval(expr, &symbols, 0).map_err(located)?;
+                    let word = to_ranged(value, -0x8000, 0xFFFF, *column).map_err(located)? as u16;
+                    rom.extend_from_slice(&word.to_be_bytes());
+                }
+            }
+            StatementKind::Instruction {
+                mnemonic,
+                column,
+                operands,
+            } => {
+                let instruction = encode(mnemonic, *column, operands, &symbols).map_err(located)?;
+                rom.extend(instruction.to_bytes());
+            }
+        }
+    }
+
+    Ok(rom)
+}
+
+fn define(symbols: &mut Symbols, name: &str, symbol: Symbol, column: usize) -> LocatedResult<()> {
+    if symbols.contains_key(name) {
+        return err(column, ParseErrorKind::DuplicateSymbol(name.into()));
+    }
+    symbols.insert(name.into(), symbol);
+    Ok(())
+}
+
+fn to_ranged(value: i64, min: i64, max: i64, column: usize) -> LocatedResult<i64> {
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        err(column, ParseErrorKind::ValueOutOfRange(value))
+    }
+}
+
+/// Converts to a byte, negative values are two's complement
+fn to_byte(value: i64, column: usize) -> LocatedResult<u8> {
+    Ok(to_ranged(value, -0x80, 0xFF, column)? as u8)
+}
+
+fn encode(
+    mnemonic: &str,
+    column: usize,
+    operands: &[(Operand, usize)],
+    symbols: &Symbols,
+) -> LocatedResult<Instruction> {
+    use Instruction::*;
+    use Operand::{
+        Bcd, BigFont, DelayTimer, Expr as E, Flags, Font, IndirectI, Key, Long, Register as R,
+        SoundTimer, I,
+    };
+
+    let addr = |expr: &Expr, column: usize| -> LocatedResult<Addr> {
+        let value = to_ranged(eval(expr, symbols, 0)?, 0, 0xFFF, column)?;
+        Ok(Addr::from(value as u16))
+    };
+    let byte = |expr: &Expr, column: usize| to_byte(eval(expr, symbols, 0)?, column);
+    let nibble = |expr: &Expr, column: usize| -> LocatedResult<crate::instructions::Nibble> {
+        Ok((to_ranged(eval(expr, symbols, 0)?, 0, 0xF, column)? as u8).into())
+    };
+
+    let instruction = match (mnemonic.to_ascii_lowercase().as_str(), operands) {
+        ("sys", [(E(e), c)]) => Sys(addr(e, *c)?),
+        ("cls", []) => Clear,
+        ("ret", []) => Return,
+        ("jp", [(E(e), c)]) => Jump(addr(e, *c)?),
+        ("jp", [(R(VRegister::V0), _), (E(e), c)]) => LongJump(addr(e, *c)?),
+        ("call", [(E(e), c)]) => Call(addr(e, *c)?),
+        ("se", [(R(x), _), (E(e), c)]) => SkipEqualOperand(*x, byte(e, *c)?),
+        ("se", [(R(x), _), (R(y), _)]) => SkipEqual(*x, *y),
+        ("sne", [(R(x), _), (E(e), c)]) => SkipNotEqualOperand(*x, byte(e, *c)?),
+        ("sne", [(R(x), _), (R(y), _)]) => SkipNotEqual(*x, *y),
+        ("ld", [(R(x), _), (E(e), c)]) => LoadOperand(*x, byte(e, *c)?),
+        ("ld", [(R(x), _), (R(y), _)]) => Load(*x, *y),
+        ("ld", [(I, _), (E(e), c)]) => LoadI(addr(e, *c)?),
+        ("ld", [(I, _), (Long(e), c)]) => {
+            let value = to_ranged(eval(e, symbols, 0)?, 0, 0xFFFF, *c)?;
+            LoadILong(Addr::long(value as u16))
+        }
+        ("ld", [(R(x), _), (DelayTimer, _)]) => LoadRegisterDelayTimer(*x),
+        ("ld", [(R(x), _), (Key, _)]) => LoadKey(*x),
+        ("ld", [(DelayTimer, _), (R(x), _)]) => LoadDelayTimerRegister(*x),
+        ("ld", [(SoundTimer, _), (R(x), _)]) => LoadSoundTimerRegister(*x),
+        ("ld", [(Font, _), (R(x), _)]) => LoadSprite(*x),
+        ("ld", [(Bcd, _), (R(x), _)]) => LoadBinaryCodedDecimal(*x),
+        ("ld", [(IndirectI, _), (R(x), _)]) => LoadMemoryRegisters(*x),
+        ("ld", [(R(x), _), (IndirectI, _)]) => LoadRegistersMemory(*x),
+        ("ld", [(BigFont, _), (R(x), _)]) => LoadBigSprite(*x),
+        ("ld", [(Flags, _), (R(x), _)]) => StoreFlags(*x),
+        ("ld", [(R(x), _), (Flags, _)]) => LoadFlags(*x),
+        ("add", [(R(x), _), (E(e), c)]) => AddOperand(*x, byte(e, *c)?),
+        ("add", [(R(x), _), (R(y), _)]) => Add(*x, *y),
+        ("add", [(I, _), (R(x), _)]) => AddI(*x),
+        ("or", [(R(x), _), (R(y), _)]) => Or(*x, *y),
+        ("and", [(R(x), _), (R(y), _)]) => And(*x, *y),
+        ("xor", [(R(x), _), (R(y), _)]) => XOr(*x, *y),
+        ("sub", [(R(x), _), (R(y), _)]) => Sub(*x, *y),
+        ("subn", [(R(x), _), (R(y), _)]) => SubNegated(*x, *y),
+        ("shr", [(R(x), _)]) => ShiftRight(*x, *x),
+        ("shr", [(R(x), _), (R(y), _)]) => ShiftRight(*x, *y),
+        ("shl", [(R(x), _)]) => ShiftLeft(*x, *x),
+        ("shl", [(R(x), _), (R(y), _)]) => ShiftLeft(*x, *y),
+        ("rnd", [(R(x), _), (E(e), c)]) => Random(*x, byte(e, *c)?),
+        ("drw", [(R(x), _), (R(y), _), (E(e), c)]) => Draw(*x, *y, nibble(e, *c)?),
+        ("skp", [(R(x), _)]) => SkipKeyPressed(*x),
+        ("sknp", [(R(x), _)]) => SkipKeyNotPressed(*x),
+        ("scd", [(E(e), c)]) => ScrollDown(nibble(e, *c)?),
+        ("scr", []) => ScrollRight,
+        ("scl", []) => ScrollLeft,
+        ("exit", []) => Exit,
+        ("low", []) => LowResolution,
+        ("high", []) => HighResolution,
+        ("save", [(R(x), _), (R(y), _)]) => SaveRange(*x, *y),
+        ("load", [(R(x), _), (R(y), _)]) => LoadRange(*x, *y),
+        ("plane", [(E(e), c)]) => SelectPlanes(nibble(e, *c)?),
+        ("audio", []) => LoadAudio,
+        ("pitch", [(R(x), _)]) => Pitch(*x),
+        (
+            "sys" | "cls" | "ret" | "jp" | "call" | "se" | "sne" | "ld" | "add" | "or" | "and"
+            | "xor" | "sub" | "subn" | "shr" | "shl" | "rnd" | "drw" | "skp" | "sknp" | "scd"
+            | "scr" | "scl" | "exit" | "low" | "high" | "save" | "load" | "plane" | "audio"
+            | "pitch",
+            _,
+        ) => return err(column, ParseErrorKind::InvalidOperands),
+        _ => return err(column, ParseErrorKind::UnknownMnemonic(mnemonic.into())),
+    };
+
+    Ok(instruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn assemble_instructions() -> crate::errors::Result<()> {
+        let rom = assemble(
+            "CLS\n\
+             ld v0, 0x12 ; comment\n\
+             LD [I], VF\n\
+             DRW V0, V1, 5\n\
+             LD I, long 0xABCD\n",
+        )?;
+
+        assert_eq!(
+            rom,
+            [0x00, 0xE0, 0x60, 0x12, 0xFF, 0x55, 0xD0, 0x15, 0xF0, 0x00, 0xAB, 0xCD]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn assemble_labels_constants_data() -> crate::errors::Result<()> {
+        let rom = assemble(
+            "; forward references are resolved in the second pass\n\
+             start: JP end\n\
+             SPEED equ (OFFSET + 2) * 4\n\
+             OFFSET equ 1\n\
+             data:\n\
+                 db SPEED, -1, #0F, 0b101\n\
+                 dw data - start\n\
+             end: LD I, data\n",
+        )?;
+
+        assert_eq!(
+            rom,
+            [0x12, 0x08, 0x0C, 0xFF, 0x0F, 0x05, 0x00, 0x02, 0xA2, 0x02]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn assemble_at_start() -> crate::errors::Result<()> {
+        let rom = assemble_at("loop: JP loop", 0x600.into())?;
+
+        assert_eq!(rom, [0x16, 0x00]);
+        Ok(())
+    }
+
+    #[test]
+    fn assemble_errors() {
+        assert_eq!(
+            assemble("CLS\n  FOO V0"),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 3,
+                kind: ParseErrorKind::UnknownMnemonic("FOO".into())
+            })
+        );
+        assert_eq!(
+            assemble("JP nowhere"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 4,
+                kind: ParseErrorKind::UndefinedSymbol("nowhere".into())
+            })
+        );
+        assert_eq!(
+            assemble("LD V0, 256"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 8,
+                kind: ParseErrorKind::ValueOutOfRange(256)
+            })
+        );
+        assert_eq!(
+            assemble("CLS V0"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 1,
+                kind: ParseErrorKind::InvalidOperands
+            })
+        );
+        assert_eq!(
+            assemble("LD V0,"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 7,
+                kind: ParseErrorKind::UnexpectedEnd
+            })
+        );
+        assert_eq!(
+            assemble("a: CLS\na: CLS"),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 1,
+                kind: ParseErrorKind::DuplicateSymbol("a".into())
+            })
+        );
+        assert_eq!(
+            assemble("A equ B\nB equ A\ndb A"),
+            Err(Chip8Error::Parse {
+                line: 3,
+                column: 4,
+                kind: ParseErrorKind::RecursiveSymbol("A".into())
+            })
+        );
+        assert_eq!(
+            assemble("db 1 / 0"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 6,
+                kind: ParseErrorKind::DivisionByZero
+            })
+        );
+        assert_eq!(
+            assemble("db 1 ?"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 6,
+                kind: ParseErrorKind::UnexpectedCharacter('?')
+            })
+        );
+        assert_eq!(
+            assemble("db 0é"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 4,
+                kind: ParseErrorKind::UnexpectedToken
+            })
+        );
+    }
+
+    #[test]
+    fn assemble_disassemble_roundtrip() -> crate::errors::Result<()> {
+        let rom = [
+            0x00, 0xE0, 0x22, 0x0A, 0x3A, 0x01, 0xF0, 0x00, 0xAB, 0xCD, 0x12, 0x02, 0xFF, 0x01,
+            0xB2, 0x02, 0x6A, 0x01, 0x8A, 0xB6, 0xD0, 0x10, 0x00, 0xEE,
+        ];
+        let listing = crate::disasm::disassemble(&rom);
+
+        assert_eq!(assemble(&listing.to_string())?, rom);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn instruction_display_assemble(bits in u16::MIN..=u16::MAX) {
+            if let Ok(instruction) = Instruction::decode(bits) {
+                let rom = assemble(&instruction.to_string()).unwrap();
+                prop_assert_eq!(rom, bits.to_be_bytes());
+            }
+        }
+    }
+}
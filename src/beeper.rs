@@ -0,0 +1,77 @@
+//! Beeper for the sound timer
+
+use alloc::vec::Vec;
+
+/// Beeper the VM controls with its sound timer
+///
+/// Implement this to drive real or simulated sound.
+/// The `tick` is the number of timer ticks since the VM was created.
+pub trait Beeper {
+    /// Called when the sound timer becomes active, i.e. goes from `0` to a non-zero value
+    fn start_beeping(&mut self, tick: u64);
+
+    /// Called when the sound timer becomes inactive, i.e. goes from a non-zero value to `0`
+    fn stop_beeping(&mut self, tick: u64);
+}
+
+/// Beeper that does nothing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NoBeeper;
+
+impl Beeper for NoBeeper {
+    fn start_beeping(&mut self, _tick: u64) {}
+
+    fn stop_beeping(&mut self, _tick: u64) {}
+}
+
+/// Change of the beeper state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BeepEvent {
+    /// Started beeping at the given tick
+    Start(u64),
+    /// Stopped beeping at the given tick
+    Stop(u64),
+}
+
+/// Beeper that records all changes of its state
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordingBeeper {
+    events: Vec<BeepEvent>,
+}
+
+impl RecordingBeeper {
+    /// Creates a new instance without any events
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns all recorded events in order
+    pub fn events(&self) -> &[BeepEvent] {
+        &self.events
+    }
+}
+
+impl Beeper for RecordingBeeper {
+    fn start_beeping(&mut self, tick: u64) {
+        self.events.push(BeepEvent::Start(tick));
+    }
+
+    fn stop_beeping(&mut self, tick: u64) {
+        self.events.push(BeepEvent::Stop(tick));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordingbeeper_events() {
+        let mut beeper = RecordingBeeper::new();
+
+        beeper.start_beeping(1);
+        beeper.stop_beeping(3);
+
+        assert_eq!(beeper.events(), [BeepEvent::Start(1), BeepEvent::Stop(3)]);
+    }
+}
@@ -0,0 +1,847 @@
+//! Debug Adapter Protocol
+//!
+//! A [`DapServer`] lets editors debug a [`VM`] through a [`Debugger`] using the
+//! [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/specification).
+//!
+//! Supported requests are `initialize`, `launch`, `configurationDone`, `setInstructionBreakpoints`,
+//! `threads`, `stackTrace`, `scopes`, `variables`, `readMemory`, `writeMemory`,
+//! `continue`, `next`, `stepIn`, `stepOut`, `pause` and `disconnect`.
+//!
+//! `launch` takes the ROM either as file path `program` or as array of bytes `rom`,
+//! and stops on entry if `stopOnEntry` is `true`.
+//! There is a single thread with id `1`, the top stack frame is the program counter,
+//! the others are the return addresses on the call stack.
+//! The scopes are "Registers" with `V0`..`VF`, `I` and `PC`, "Timers" and "Stack".
+//! `I` and `PC` have memory references for `readMemory` and `writeMemory`,
+//! which are hexadecimal addresses like `0x0200`.
+//!
+//! Requests are handled one after another, so `continue` runs until the debugger stops,
+//! at the latest after [`Config::step_limit`](crate::debug::Config::step_limit) instructions.
+//!
+//! # Features
+//!
+//! This is supported on **crate feature `dap`** only, which adds a dependency on `serde_json`.
+
+use crate::beeper::{Beeper, NoBeeper};
+use crate::debug::{Breakpoint, Debugger, StopReason};
+use crate::display::{Display, Screen};
+use crate::instructions::{Addr, Instruction, VRegister};
+use crate::memory::{Bus, Memory};
+use crate::vm::VM;
+use core::convert::TryFrom;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+/// Reference of the "Registers" scope
+const REGISTERS_REFERENCE: u64 = 1;
+
+/// Reference of the "Timers" scope
+const TIMERS_REFERENCE: u64 = 2;
+
+/// Reference of the "Stack" scope
+const STACK_REFERENCE: u64 = 3;
+
+/// Id of the only thread
+const THREAD_ID: u64 = 1;
+
+/// Maximum `Content-Length` of a message, enough to write the whole XO-CHIP memory
+const MAX_CONTENT_LENGTH: usize = 1 << 20;
+
+/// Creates a VM with the given ROM for a `launch` request
+pub type Launcher<R, S, B, M = Memory> = fn(&[u8]) -> crate::errors::Result<VM<R, S, B, M>>;
+
+/// Server of the Debug Adapter Protocol for a [`Debugger`]
+pub struct DapServer<
+    R: crate::random::RandomSource,
+    S: Screen = Display,
+    B: Beeper = NoBeeper,
+    M: Bus = Memory,
+> {
+    launch: Launcher<R, S, B, M>,
+    debugger: Option<Debugger<R, S, B, M>>,
+    breakpoints: Vec<Addr>,
+    stop_on_entry: bool,
+    configured: bool,
+    seq: u64,
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+#[cfg(feature = "rand")]
+impl DapServer<rand::rngs::ThreadRng> {
+    /// Creates a new instance launching ROMs in [`VM::default`]
+    #[must_use]
+    pub fn with_default_vm() -> Self {
+        Self::new(|rom| {
+            let mut vm = VM::default();
+            vm.load_rom(rom)?;
+            Ok(vm)
+        })
+    }
+}
+
+impl<R, S, B, M> DapServer<R, S, B, M>
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    /// Creates a new instance which creates a VM with the ROM for each `launch` request
+    #[must_use]
+    pub fn new(launch: Launcher<R, S, B, M>) -> Self {
+        Self {
+            launch,
+            debugger: None,
+            breakpoints: Vec::new(),
+            stop_on_entry: false,
+            configured: false,
+            seq: 0,
+        }
+    }
+
+    /// Returns the debugger of the launched VM
+    #[must_use]
+    pub fn debugger(&self) -> Option<&Debugger<R, S, B, M>> {
+        self.debugger.as_ref()
+    }
+
+    /// Serves a client on standard input and output until it disconnects
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from reading or writing.
+    pub fn serve_stdio(&mut self) -> io::Result<()> {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        self.serve(stdin.lock(), stdout.lock())
+    }
+
+    /// Serves a client sending messages to `input` and receiving from `output` until it disconnects
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from reading or writing, or [`io::ErrorKind::InvalidData`] for malformed messages
+    /// and messages larger than 1 MiB.
+    pub fn serve<I: BufRead, O: Write>(&mut self, mut input: I, mut output: O) -> io::Result<()> {
+        while let Some(request) = read_message(&mut input)? {
+            let messages = self.handle(&request);
+            for message in &messages {
+                write_message(&mut output, message)?;
+            }
+            if request["command"] == "disconnect" {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a single `request`, returns the response followed by any events
+    pub fn handle(&mut self, request: &Value) -> Vec<Value> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let args = &request["arguments"];
+        let mut events = Vec::new();
+        let result = match command {
+            "initialize" => {
+                events.push(("initialized", Value::Null));
+                Ok(json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsReadMemoryRequest": true,
+                    "supportsWriteMemoryRequest": true,
+                }))
+            }
+            "launch" => {
+                let result = self.launch(args);
+                if result.is_ok() && self.configured {
+                    events.extend(self.start());
+                }
+                result
+            }
+            "configurationDone" => {
+                self.configured = true;
+                if self.debugger.is_some() {
+                    events.extend(self.start());
+                }
+                Ok(Value::Null)
+            }
+            "setInstructionBreakpoints" => self.set_breakpoints(args),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+                    { "name": "Timers", "variablesReference": TIMERS_REFERENCE, "expensive": false },
+                    { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+                ]
+            })),
+            "variables" => self.variables(args),
+            "readMemory" => self.read_memory(args),
+            "writeMemory" => self.write_memory(args),
+            "continue" => {
+                events.extend(self.resume(Debugger::run));
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" => {
+                events.extend(self.resume(Debugger::step_over));
+                Ok(Value::Null)
+            }
+            "stepIn" => {
+                events.extend(self.resume(Debugger::step_into));
+                Ok(Value::Null)
+            }
+            "stepOut" => {
+                events.extend(self.resume(Debugger::step_out));
+                Ok(Value::Null)
+            }
+            "pause" => {
+                events.push(("stopped", stopped_body("pause", None)));
+                Ok(Value::Null)
+            }
+            "disconnect" => {
+                self.debugger = None;
+                self.configured = false;
+                Ok(Value::Null)
+            }
+            _ => Err(format!("unsupported command {:?}", command)),
+        };
+
+        let mut response = json!({
+            "seq": self.next_seq(),
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(Value::Null) => {}
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = message.into(),
+        }
+
+        let mut messages = vec![response];
+        for (event, body) in events {
+            let mut message = json!({
+                "seq": self.next_seq(),
+                "type": "event",
+                "event": event,
+            });
+            if !body.is_null() {
+                message["body"] = body;
+            }
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn launched(&mut self) -> Result<&mut Debugger<R, S, B, M>, String> {
+        self.debugger
+            .as_mut()
+            .ok_or_else(|| "no program launched".into())
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<Value, String> {
+        let rom = if let Some(program) = args["program"].as_str() {
+            std::fs::read(program).map_err(|err| format!("can not read {}: {}", program, err))?
+        } else if let Some(bytes) = args["rom"].as_array() {
+            bytes
+                .iter()
+                .map(|byte| {
+                    byte.as_u64()
+                        .and_then(|byte| u8::try_from(byte).ok())
+                        .ok_or_else(|| "invalid ROM byte".to_string())
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            return Err("missing program".into());
+        };
+        let vm = (self.launch)(&rom).map_err(|err| err.to_string())?;
+        let mut debugger = Debugger::new(vm);
+        for addr in &self.breakpoints {
+            debugger.add_breakpoint(Breakpoint::new(*addr));
+        }
+        self.debugger = Some(debugger);
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+        Ok(Value::Null)
+    }
+
+    /// Starts the launched program once the client is configured, returns the events
+    fn start(&mut self) -> Vec<(&'static str, Value)> {
+        if self.stop_on_entry {
+            vec![("stopped", stopped_body("entry", None))]
+        } else {
+            self.resume(Debugger::run)
+        }
+    }
+
+    /// Replaces all breakpoints, which are kept for programs launched later
+    fn set_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        self.breakpoints.clear();
+        let mut verified = Vec::new();
+        for breakpoint in args["breakpoints"].as_array().into_iter().flatten() {
+            let addr = breakpoint["instructionReference"]
+                .as_str()
+                .and_then(parse_reference)
+                .and_then(|addr| {
+                    let offset = breakpoint["offset"].as_i64().unwrap_or(0);
+                    let addr = i64::from(addr).checked_add(offset)?;
+                    u16::try_from(addr).ok()
+                });
+            match addr {
+                Some(addr) => {
+                    self.breakpoints.push(Addr::long(addr));
+                    verified.push(json!({
+                        "verified": true,
+                        "instructionReference": reference(addr),
+                    }));
+                }
+                None => verified.push(json!({
+                    "verified": false,
+                    "message": "invalid instruction reference",
+                })),
+            }
+        }
+
+        if let Some(debugger) = self.debugger.as_mut() {
+            for breakpoint in debugger.breakpoints().to_vec() {
+                debugger.remove_breakpoints(breakpoint.addr);
+            }
+            for addr in &self.breakpoints {
+                debugger.add_breakpoint(Breakpoint::new(*addr));
+            }
+        }
+        Ok(json!({ "breakpoints": verified }))
+    }
+
+    fn stack_trace(&mut self) -> Result<Value, String> {
+        let vm = self.launched()?.vm();
+        let addrs = core::iter::once(vm.pc()).chain(vm.stack().iter().rev().copied());
+        let frames: Vec<Value> = addrs
+            .enumerate()
+            .map(|(id, addr)| {
+                let name = instruction_at(vm, addr)
+                    .map_or_else(|| reference(addr), |instruction| instruction.to_string());
+                json!({
+                    "id": id,
+                    "name": name,
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": reference(addr),
+                })
+            })
+            .collect();
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    fn variables(&mut self, args: &Value) -> Result<Value, String> {
+        let vm = self.launched()?.vm();
+        let variables: Vec<Value> = match args["variablesReference"].as_u64() {
+            Some(REGISTERS_REFERENCE) => (0..16u8)
+                .filter_map(|vx| VRegister::try_from(vx).ok())
+                .map(|vx| variable(&vx.to_string(), format!("{:#04X}", vm.register(vx)), None))
+                .chain([
+                    variable("I", format!("{:#06X}", vm.i()), Some(vm.i())),
+                    variable("PC", format!("{:#06X}", vm.pc()), Some(vm.pc())),
+                ])
+                .collect(),
+            Some(TIMERS_REFERENCE) => vec![
+                variable("DT", vm.delay_timer().to_string(), None),
+                variable("ST", vm.sound_timer().to_string(), None),
+            ],
+            Some(STACK_REFERENCE) => vm
+                .stack()
+                .iter()
+                .enumerate()
+                .map(|(idx, addr)| variable(&format!("[{}]", idx), reference(*addr), Some(*addr)))
+                .collect(),
+            _ => return Err("unknown variables reference".into()),
+        };
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Returns the memory range of `memoryReference` + `offset` with `len` bytes, clamped to memory
+    fn memory_range(&mut self, args: &Value, len: usize) -> Result<(usize, usize), String> {
+        let base = args["memoryReference"]
+            .as_str()
+            .and_then(parse_reference)
+            .ok_or("invalid memory reference")?;
+        let start = i64::from(base)
+            .checked_add(args["offset"].as_i64().unwrap_or(0))
+            .ok_or("invalid offset")?;
+        let size = self.launched()?.vm().memory().size();
+        let start = usize::try_from(start).map_err(|_| "address before memory")?;
+        Ok((start.min(size), start.saturating_add(len).min(size)))
+    }
+
+    fn read_memory(&mut self, args: &Value) -> Result<Value, String> {
+        let count = args["count"].as_u64().unwrap_or(0) as usize;
+        let (start, end) = self.memory_range(args, count)?;
+        let memory = self.launched()?.vm().memory();
+        let bytes: Vec<u8> = (start..end)
+            .map(|addr| memory.read(Addr::long(addr as u16)))
+            .collect();
+        Ok(json!({
+            "address": reference(start as u16),
+            "data": base64_encode(&bytes),
+            "unreadableBytes": count - bytes.len(),
+        }))
+    }
+
+    fn write_memory(&mut self, args: &Value) -> Result<Value, String> {
+        let data = args["data"]
+            .as_str()
+            .and_then(base64_decode)
+            .ok_or("invalid data")?;
+        let (start, end) = self.memory_range(args, data.len())?;
+        if end - start != data.len() {
+            return Err("write beyond memory".into());
+        }
+        let memory = self.launched()?.vm_mut().memory_mut();
+        for (addr, byte) in (start..end).zip(data.iter().copied()) {
+            memory
+                .write(Addr::long(addr as u16), byte)
+                .map_err(|err| err.to_string())?;
+        }
+        Ok(json!({ "bytesWritten": data.len() }))
+    }
+
+    /// Runs the debugger with `command` and returns the events for why it stopped
+    fn resume(
+        &mut self,
+        command: fn(&mut Debugger<R, S, B, M>) -> crate::errors::Result<StopReason>,
+    ) -> Vec<(&'static str, Value)> {
+        let debugger = match self.debugger.as_mut() {
+            Some(debugger) => debugger,
+            None => return Vec::new(),
+        };
+        let event = match command(debugger) {
+            Ok(StopReason::Step) => ("stopped", stopped_body("step", None)),
+            Ok(StopReason::Breakpoint(_)) => {
+                ("stopped", stopped_body("instruction breakpoint", None))
+            }
+            Ok(StopReason::Watchpoint { .. }) => ("stopped", stopped_body("data breakpoint", None)),
+            Ok(StopReason::Instruction(instruction)) => (
+                "stopped",
+                stopped_body("breakpoint", Some(instruction.to_string())),
+            ),
+            Ok(StopReason::WaitingForKey) => (
+                "stopped",
+                stopped_body("pause", Some("waiting for key".into())),
+            ),
+            Ok(StopReason::StepLimit) => ("stopped", stopped_body("pause", None)),
+            Ok(StopReason::Halted) => ("terminated", Value::Null),
+            Err(err) => ("stopped", stopped_body("exception", Some(err.to_string()))),
+        };
+        vec![event]
+    }
+}
+
+fn stopped_body(reason: &str, text: Option<String>) -> Value {
+    let mut body = json!({
+        "reason": reason,
+        "threadId": THREAD_ID,
+        "allThreadsStopped": true,
+    });
+    if let Some(text) = text {
+        body["text"] = text.into();
+    }
+    body
+}
+
+fn variable(name: &str, value: String, memory: Option<u16>) -> Value {
+    let mut variable = json!({
+        "name": name,
+        "value": value,
+        "variablesReference": 0,
+    });
+    if let Some(addr) = memory {
+        variable["memoryReference"] = reference(addr).into();
+    }
+    variable
+}
+
+/// Returns the instruction or memory reference of `addr`
+fn reference(addr: u16) -> String {
+    format!("{:#06X}", addr)
+}
+
+fn parse_reference(reference: &str) -> Option<u16> {
+    let hex = reference
+        .strip_prefix("0x")
+        .or_else(|| reference.strip_prefix("0X"))?;
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// Decodes the instruction at `addr` without the second word of XO-CHIP long instructions
+fn instruction_at<R, S, B, M>(vm: &VM<R, S, B, M>, addr: u16) -> Option<Instruction>
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    let memory = vm.memory();
+    let high = memory.read(memory.checked_addr(addr).ok()?);
+    let low = memory.read(memory.checked_addr(addr.checked_add(1)?).ok()?);
+    Instruction::decode(u16::from_be_bytes([high, low])).ok()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (idx, byte)| {
+            bits | u32::from(*byte) << (16 - 8 * idx)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(BASE64[(bits >> (18 - 6 * idx) & 0x3F) as usize].into());
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut bits = 0u32;
+        for (idx, c) in chunk.iter().enumerate() {
+            let value = BASE64.iter().position(|b| b == c)? as u32;
+            bits |= value << (18 - 6 * idx);
+        }
+        for idx in 0..chunk.len() - 1 {
+            bytes.push((bits >> (16 - 8 * idx)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a message with its `Content-Length` header, `None` at the end of `input`
+fn read_message<I: BufRead>(input: &mut I) -> io::Result<Option<Value>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            len = Some(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid_data("invalid Content-Length"))?,
+            );
+        }
+    }
+    let len = len.ok_or_else(|| invalid_data("missing Content-Length"))?;
+    if len > MAX_CONTENT_LENGTH {
+        return Err(invalid_data("Content-Length too large"));
+    }
+    let mut content = vec![0; len];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| invalid_data(&err.to_string()))
+}
+
+fn write_message<O: Write>(output: &mut O, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{MappedBus, WriteProtection};
+    use crate::random::XorShift;
+
+    type TestServer = DapServer<XorShift>;
+
+    fn test_server() -> TestServer {
+        DapServer::new(|rom| {
+            let rng = XorShift::default();
+            let mut vm = VM::new(rng, |_, _| Ok(()));
+            vm.load_rom(rom)?;
+            Ok(vm)
+        })
+    }
+
+    /// Sends the requests of the transcript and compares each reply with the expected messages
+    fn assert_transcript(server: &mut TestServer, transcript: &[(Value, Vec<Value>)]) {
+        for (request, expected) in transcript {
+            assert_eq!(&server.handle(request), expected, "request {}", request);
+        }
+    }
+
+    #[test]
+    fn dap_launch_break_and_step() {
+        let mut server = test_server();
+        // LD V0, 0x12; LD I, 0x300; CALL 0x208; JP 0x206; LD [I], V0; RET
+        let rom = [
+            0x60, 0x12, 0xA3, 0x00, 0x22, 0x08, 0x12, 0x06, 0xF0, 0x55, 0x00, 0xEE,
+        ];
+
+        assert_transcript(
+            &mut server,
+            &[
+                (
+                    json!({ "seq": 1, "type": "request", "command": "initialize", "arguments": {} }),
+                    vec![
+                        json!({
+                            "seq": 1, "type": "response", "request_seq": 1, "command": "initialize", "success": true,
+                            "body": {
+                                "supportsConfigurationDoneRequest": true,
+                                "supportsInstructionBreakpoints": true,
+                                "supportsReadMemoryRequest": true,
+                                "supportsWriteMemoryRequest": true,
+                            }
+                        }),
+                        json!({ "seq": 2, "type": "event", "event": "initialized" }),
+                    ],
+                ),
+                (
+                    json!({ "seq": 2, "type": "request", "command": "launch", "arguments": { "rom": rom } }),
+                    vec![
+                        json!({ "seq": 3, "type": "response", "request_seq": 2, "command": "launch", "success": true }),
+                    ],
+                ),
+                (
+                    json!({
+                        "seq": 3, "type": "request", "command": "setInstructionBreakpoints",
+                        "arguments": { "breakpoints": [{ "instructionReference": "0x0204" }] }
+                    }),
+                    vec![json!({
+                        "seq": 4, "type": "response", "request_seq": 3, "command": "setInstructionBreakpoints", "success": true,
+                        "body": { "breakpoints": [{ "verified": true, "instructionReference": "0x0204" }] }
+                    })],
+                ),
+                (
+                    json!({ "seq": 4, "type": "request", "command": "configurationDone" }),
+                    vec![
+                        json!({ "seq": 5, "type": "response", "request_seq": 4, "command": "configurationDone", "success": true }),
+                        json!({
+                            "seq": 6, "type": "event", "event": "stopped",
+                            "body": { "reason": "instruction breakpoint", "threadId": 1, "allThreadsStopped": true }
+                        }),
+                    ],
+                ),
+                (
+                    json!({ "seq": 5, "type": "request", "command": "stepIn" }),
+                    vec![
+                        json!({ "seq": 7, "type": "response", "request_seq": 5, "command": "stepIn", "success": true }),
+                        json!({
+                            "seq": 8, "type": "event", "event": "stopped",
+                            "body": { "reason": "step", "threadId": 1, "allThreadsStopped": true }
+                        }),
+                    ],
+                ),
+                (
+                    json!({ "seq": 6, "type": "request", "command": "stackTrace", "arguments": { "threadId": 1 } }),
+                    vec![json!({
+                        "seq": 9, "type": "response", "request_seq": 6, "command": "stackTrace", "success": true,
+                        "body": {
+                            "stackFrames": [
+                                { "id": 0, "name": "LD [I], V0", "line": 0, "column": 0, "instructionPointerReference": "0x0208" },
+                                { "id": 1, "name": "JP 0x206", "line": 0, "column": 0, "instructionPointerReference": "0x0206" },
+                            ],
+                            "totalFrames": 2,
+                        }
+                    })],
+                ),
+                (
+                    json!({ "seq": 7, "type": "request", "command": "stepOut" }),
+                    vec![
+                        json!({ "seq": 10, "type": "response", "request_seq": 7, "command": "stepOut", "success": true }),
+                        json!({
+                            "seq": 11, "type": "event", "event": "stopped",
+                            "body": { "reason": "step", "threadId": 1, "allThreadsStopped": true }
+                        }),
+                    ],
+                ),
+                (
+                    json!({
+                        "seq": 8, "type": "request", "command": "readMemory",
+                        "arguments": { "memoryReference": "0x0300", "offset": -1, "count": 2 }
+                    }),
+                    vec![json!({
+                        "seq": 12, "type": "response", "request_seq": 8, "command": "readMemory", "success": true,
+                        "body": { "address": "0x02FF", "data": "ABI=", "unreadableBytes": 0 }
+                    })],
+                ),
+                (
+                    json!({ "seq": 9, "type": "request", "command": "disconnect" }),
+                    vec![
+                        json!({ "seq": 13, "type": "response", "request_seq": 9, "command": "disconnect", "success": true }),
+                    ],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn dap_configuration_before_launch() {
+        let mut server = test_server();
+        // LD V0, 0x12; JP 0x202
+        let rom = [0x60, 0x12, 0x12, 0x02];
+
+        let messages = server.handle(&json!({
+            "seq": 1, "command": "setInstructionBreakpoints",
+            "arguments": { "breakpoints": [{ "instructionReference": "0x0202" }] }
+        }));
+        assert_eq!(messages[0]["success"], true);
+        let messages = server.handle(&json!({ "seq": 2, "command": "configurationDone" }));
+        assert_eq!(messages.len(), 1);
+
+        let messages =
+            server.handle(&json!({ "seq": 3, "command": "launch", "arguments": { "rom": rom } }));
+        assert_eq!(messages[0]["success"], true);
+        assert_eq!(messages[1]["event"], "stopped");
+        assert_eq!(messages[1]["body"]["reason"], "instruction breakpoint");
+        assert_eq!(server.debugger().unwrap().vm().pc(), 0x202);
+    }
+
+    #[test]
+    fn dap_variables() {
+        let mut server = test_server();
+        server.handle(
+            &json!({ "seq": 1, "command": "launch", "arguments": { "rom": [0x60, 0xAB] } }),
+        );
+        server.handle(&json!({ "seq": 2, "command": "stepIn" }));
+
+        let messages = server.handle(
+            &json!({ "seq": 3, "command": "variables", "arguments": { "variablesReference": 1 } }),
+        );
+        let variables = &messages[0]["body"]["variables"];
+        assert_eq!(
+            variables[0],
+            json!({ "name": "V0", "value": "0xAB", "variablesReference": 0 })
+        );
+        assert_eq!(
+            variables[17],
+            json!({ "name": "PC", "value": "0x0202", "variablesReference": 0, "memoryReference": "0x0202" })
+        );
+
+        let messages = server.handle(&json!({
+            "seq": 4, "command": "writeMemory",
+            "arguments": { "memoryReference": "0x0300", "data": "3q2+7w==" }
+        }));
+        assert_eq!(messages[0]["body"], json!({ "bytesWritten": 4 }));
+        let memory = server.debugger().unwrap().vm().memory();
+        assert_eq!(memory.read(0x300.into()), 0xDE);
+        assert_eq!(memory.read(0x303.into()), 0xEF);
+
+        let messages = server.handle(&json!({
+            "seq": 5, "command": "readMemory",
+            "arguments": { "memoryReference": "0x0300", "offset": i64::MAX, "count": 1 }
+        }));
+        assert_eq!(messages[0]["success"], false);
+        assert_eq!(messages[0]["message"], "invalid offset");
+
+        let messages = server.handle(&json!({ "seq": 6, "command": "evaluate" }));
+        assert_eq!(messages[0]["success"], false);
+    }
+
+    #[test]
+    fn dap_write_memory_protected() {
+        let mut server: DapServer<XorShift, Display, NoBeeper, MappedBus> = DapServer::new(|rom| {
+            let mut bus = MappedBus::new(Memory::new());
+            bus.protect(0x200..=0x2FF, WriteProtection::Error);
+            let mut vm = VM::with_bus(
+                XorShift::default(),
+                |_, _| Ok(()),
+                crate::vm::Config::default(),
+                Display::new(crate::display::EdgeMode::Wrap),
+                NoBeeper,
+                bus,
+            );
+            vm.load_rom(rom)?;
+            Ok(vm)
+        });
+        server.handle(
+            &json!({ "seq": 1, "command": "launch", "arguments": { "rom": [0x60, 0xAB] } }),
+        );
+
+        let messages = server.handle(&json!({
+            "seq": 2, "command": "writeMemory",
+            "arguments": { "memoryReference": "0x0300", "data": "3q0=" }
+        }));
+        assert_eq!(messages[0]["body"], json!({ "bytesWritten": 2 }));
+
+        let messages = server.handle(&json!({
+            "seq": 3, "command": "writeMemory",
+            "arguments": { "memoryReference": "0x0200", "data": "3q0=" }
+        }));
+        assert_eq!(messages[0]["success"], false);
+        assert_eq!(messages[0]["message"], "write to protected address 512");
+        assert_eq!(
+            server.debugger().unwrap().vm().memory().read(0x200.into()),
+            0x60
+        );
+    }
+
+    #[test]
+    fn dap_serve_framing() -> io::Result<()> {
+        let mut server = test_server();
+        let mut input = Vec::new();
+        for request in [
+            json!({ "seq": 1, "type": "request", "command": "threads" }),
+            json!({ "seq": 2, "type": "request", "command": "disconnect" }),
+        ] {
+            write_message(&mut input, &request)?;
+        }
+        let mut output = Vec::new();
+
+        server.serve(&input[..], &mut output)?;
+
+        let mut output = &output[..];
+        let threads = read_message(&mut output)?.expect("threads response");
+        assert_eq!(
+            threads["body"],
+            json!({ "threads": [{ "id": 1, "name": "CHIP-8" }] })
+        );
+        let disconnect = read_message(&mut output)?.expect("disconnect response");
+        assert_eq!(disconnect["command"], "disconnect");
+        assert_eq!(read_message(&mut output)?, None);
+
+        let mut huge = &b"Content-Length: 18446744073709551615\r\n\r\n"[..];
+        assert_eq!(
+            read_message(&mut huge).map_err(|err| err.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn dap_base64() {
+        for bytes in [&b""[..], b"a", b"ab", b"abc", b"abcd", &[0xFF, 0x00, 0x7F]] {
+            assert_eq!(base64_decode(&base64_encode(bytes)).as_deref(), Some(bytes));
+        }
+        assert_eq!(base64_encode(b"abcd"), "YWJjZA==");
+    }
+}
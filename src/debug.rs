@@ -0,0 +1,557 @@
+//! Debugger
+//!
+//! A [`Debugger`] wraps a [`VM`] and runs it until a [`Breakpoint`], a [`Watchpoint`]
+//! or an instruction filter stops it. It has no user interface of its own,
+//! so a TUI, a remote protocol or a test can drive it.
+//!
+//! ```
+//! use chip_8::debug::{Breakpoint, Debugger, StopReason};
+//! use chip_8::instructions::Instruction;
+//! use chip_8::random::XorShift;
+//! use chip_8::vm::VM;
+//!
+//! let mut vm = VM::new(XorShift::default(), |_, _| Ok(()));
+//! // CLS; JP 0x200
+//! vm.load_rom(&[0x00, 0xE0, 0x12, 0x00])?;
+//!
+//! let mut debugger = Debugger::new(vm);
+//! debugger.add_breakpoint(Breakpoint::new(0x202.into()));
+//! debugger.break_on_instruction(|instruction| matches!(instruction, Instruction::Clear));
+//!
+//! assert_eq!(debugger.run()?, StopReason::Breakpoint(0x202.into()));
+//! assert_eq!(debugger.run()?, StopReason::Instruction(Instruction::Clear));
+//! # Ok::<(), chip_8::errors::Chip8Error>(())
+//! ```
+
+use crate::beeper::{Beeper, NoBeeper};
+use crate::display::{Display, Screen};
+use crate::instructions::{Addr, Instruction, VRegister};
+use crate::memory::{Bus, Memory};
+use crate::vm::{StepResult, VM};
+use alloc::vec::Vec;
+
+/// Comparison of a register with a value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+}
+
+/// Condition of a [`Breakpoint`], i.e. `register comparison value`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    /// Register on the left hand side
+    pub register: VRegister,
+    /// How the register is compared
+    pub comparison: Comparison,
+    /// Value on the right hand side
+    pub value: u8,
+}
+
+impl Condition {
+    /// Returns whether the condition holds for the `vm`
+    pub fn holds<R, S, B, M>(&self, vm: &VM<R, S, B, M>) -> bool
+    where
+        R: crate::random::RandomSource,
+        S: Screen,
+        B: Beeper,
+        M: Bus,
+    {
+        let lhs = vm.register(self.register);
+        match self.comparison {
+            Comparison::Equal => lhs == self.value,
+            Comparison::NotEqual => lhs != self.value,
+            Comparison::Less => lhs < self.value,
+            Comparison::LessEqual => lhs <= self.value,
+            Comparison::Greater => lhs > self.value,
+            Comparison::GreaterEqual => lhs >= self.value,
+        }
+    }
+}
+
+/// Stops before the instruction at an address is executed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Address of the instruction
+    pub addr: Addr,
+    /// Only stop if this holds
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    /// Creates a new instance that always stops at `addr`
+    #[must_use]
+    pub const fn new(addr: Addr) -> Self {
+        Self {
+            addr,
+            condition: None,
+        }
+    }
+
+    /// Creates a new instance that stops at `addr` if the `condition` holds
+    #[must_use]
+    pub const fn with_condition(addr: Addr, condition: Condition) -> Self {
+        Self {
+            addr,
+            condition: Some(condition),
+        }
+    }
+}
+
+/// Kind of memory access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Memory is read
+    Read,
+    /// Memory is written
+    Write,
+    /// Memory is read or written, only used by [`Watchpoint`]s
+    ReadWrite,
+}
+
+/// Stops after an instruction accessed memory
+///
+/// Only data accesses by instructions count, fetching instructions does not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// First watched address
+    pub addr: Addr,
+    /// Number of watched bytes
+    pub len: u16,
+    /// Watched kind of access
+    pub access: Access,
+}
+
+/// Why the [`Debugger`] stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The requested step finished
+    Step,
+    /// A [`Breakpoint`] at the address was hit, the instruction there was not executed yet
+    Breakpoint(Addr),
+    /// A [`Watchpoint`] was hit by the instruction that was just executed
+    Watchpoint {
+        /// First accessed address within the watchpoint
+        addr: Addr,
+        /// Kind of the access, either [`Access::Read`] or [`Access::Write`]
+        access: Access,
+    },
+    /// An instruction filter matched the instruction, it was not executed yet
+    Instruction(Instruction),
+    /// The VM is waiting for a key press
+    WaitingForKey,
+    /// The VM is halted
+    Halted,
+    /// [`Config::step_limit`] instructions were executed without any other reason to stop
+    StepLimit,
+}
+
+/// Configuration of the [`Debugger`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Maximum number of instructions executed by a single command
+    ///
+    /// This keeps e.g. [`Debugger::run`] from hanging in an endless loop.
+    pub step_limit: usize,
+}
+
+impl Config {
+    /// Default for [`step_limit`](Self::step_limit)
+    pub const DEFAULT_STEP_LIMIT: usize = 1_000_000;
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            step_limit: Self::DEFAULT_STEP_LIMIT,
+        }
+    }
+}
+
+/// Debugger of a [`VM`]
+pub struct Debugger<
+    R: crate::random::RandomSource,
+    S: Screen = Display,
+    B: Beeper = NoBeeper,
+    M: Bus = Memory,
+> {
+    vm: VM<R, S, B, M>,
+    config: Config,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    instruction_filters: Vec<fn(&Instruction) -> bool>,
+}
+
+impl<R, S, B, M> Debugger<R, S, B, M>
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    /// Creates a new instance for the `vm` with default [`Config`]
+    #[must_use]
+    pub fn new(vm: VM<R, S, B, M>) -> Self {
+        Self::with_config(vm, Config::default())
+    }
+
+    /// Creates a new instance for the `vm` with the given `config`
+    #[must_use]
+    pub fn with_config(vm: VM<R, S, B, M>, config: Config) -> Self {
+        Self {
+            vm,
+            config,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            instruction_filters: Vec::new(),
+        }
+    }
+
+    /// Returns the debugged VM
+    #[must_use]
+    pub fn vm(&self) -> &VM<R, S, B, M> {
+        &self.vm
+    }
+
+    /// Returns the debugged VM for modification, e.g. to press keys or tick timers
+    #[must_use]
+    pub fn vm_mut(&mut self) -> &mut VM<R, S, B, M> {
+        &mut self.vm
+    }
+
+    /// Returns the debugged VM, dropping the debugger
+    #[must_use]
+    pub fn into_vm(self) -> VM<R, S, B, M> {
+        self.vm
+    }
+
+    /// Returns all breakpoints
+    #[must_use]
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Adds the `breakpoint`
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    /// Removes all breakpoints at `addr`
+    pub fn remove_breakpoints(&mut self, addr: Addr) {
+        self.breakpoints
+            .retain(|breakpoint| breakpoint.addr != addr);
+    }
+
+    /// Returns all watchpoints
+    #[must_use]
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Adds the `watchpoint`
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Removes all watchpoints equal to `watchpoint`
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) {
+        self.watchpoints.retain(|other| other != watchpoint);
+    }
+
+    /// Stops before each instruction for which `filter` returns `true`
+    pub fn break_on_instruction(&mut self, filter: fn(&Instruction) -> bool) {
+        self.instruction_filters.push(filter);
+    }
+
+    /// Removes all instruction filters
+    pub fn clear_instruction_filters(&mut self) {
+        self.instruction_filters.clear();
+    }
+
+    /// Executes a single instruction
+    ///
+    /// Only watchpoints can stop it, since the instruction is executed anyway.
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`].
+    pub fn step_into(&mut self) -> crate::errors::Result<StopReason> {
+        self.run_until(|_| true)
+    }
+
+    /// Executes a single instruction, but a whole subroutine for `CALL addr`
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`].
+    pub fn step_over(&mut self) -> crate::errors::Result<StopReason> {
+        match self.vm.next_instruction() {
+            Ok(instruction @ Instruction::Call(_)) => {
+                let return_addr = self.vm.pc().wrapping_add(instruction.size());
+                let depth = self.vm.stack().len();
+                self.run_until(|vm| vm.pc() == return_addr && vm.stack().len() == depth)
+            }
+            _ => self.step_into(),
+        }
+    }
+
+    /// Executes until the current subroutine returns
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`].
+    pub fn step_out(&mut self) -> crate::errors::Result<StopReason> {
+        let depth = self.vm.stack().len();
+        self.run_until(|vm| vm.stack().len() < depth)
+    }
+
+    /// Executes until anything stops it
+    ///
+    /// A breakpoint at the program counter is ignored, so this continues after hitting it.
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`].
+    pub fn run(&mut self) -> crate::errors::Result<StopReason> {
+        self.run_until(|_| false)
+    }
+
+    /// Executes until `done` returns `true` after an instruction or anything else stops it
+    fn run_until(
+        &mut self,
+        done: impl Fn(&VM<R, S, B, M>) -> bool,
+    ) -> crate::errors::Result<StopReason> {
+        for steps in 0..self.config.step_limit {
+            if steps > 0 {
+                if let Some(reason) = self.check_before_step() {
+                    return Ok(reason);
+                }
+            }
+
+            let access = memory_access(&self.vm);
+            match self.vm.step()? {
+                StepResult::Executed(_) => {}
+                StepResult::WaitingForKey => return Ok(StopReason::WaitingForKey),
+                StepResult::Halted => return Ok(StopReason::Halted),
+            }
+            if let Some(reason) = access.and_then(|access| self.check_watchpoints(access)) {
+                return Ok(reason);
+            }
+            if done(&self.vm) {
+                return Ok(StopReason::Step);
+            }
+        }
+        Ok(StopReason::StepLimit)
+    }
+
+    /// Checks breakpoints and instruction filters before the next instruction is executed
+    fn check_before_step(&self) -> Option<StopReason> {
+        let pc = self.vm.pc();
+        let breakpoint = self.breakpoints.iter().find(|breakpoint| {
+            u16::from(breakpoint.addr) == pc
+                && breakpoint
+                    .condition
+                    .is_none_or(|condition| condition.holds(&self.vm))
+        });
+        if let Some(breakpoint) = breakpoint {
+            return Some(StopReason::Breakpoint(breakpoint.addr));
+        }
+
+        // decoding errors are left to the following step
+        let instruction = self.vm.next_instruction().ok()?;
+        if self
+            .instruction_filters
+            .iter()
+            .any(|filter| filter(&instruction))
+        {
+            return Some(StopReason::Instruction(instruction));
+        }
+        None
+    }
+
+    /// Returns the first watchpoint hit by the memory `access` of an instruction
+    fn check_watchpoints(&self, (access, start, len): (Access, u32, u32)) -> Option<StopReason> {
+        self.watchpoints
+            .iter()
+            .filter(|watchpoint| {
+                watchpoint.access == Access::ReadWrite || watchpoint.access == access
+            })
+            .find_map(|watchpoint| {
+                let watch_start = u32::from(u16::from(watchpoint.addr));
+                let first = start.max(watch_start);
+                let end = (start + len).min(watch_start + u32::from(watchpoint.len));
+                (first < end).then(|| StopReason::Watchpoint {
+                    addr: Addr::long(first as u16),
+                    access,
+                })
+            })
+    }
+}
+
+/// Returns the memory the next instruction of `vm` will access as kind, first address and length
+pub(crate) fn memory_access<R, S, B, M>(vm: &VM<R, S, B, M>) -> Option<(Access, u32, u32)>
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    let i = u32::from(vm.i());
+    let (access, len) = match vm.next_instruction().ok()? {
+        Instruction::LoadMemoryRegisters(vx) => (Access::Write, vx as u32 + 1),
+        Instruction::LoadRegistersMemory(vx) => (Access::Read, vx as u32 + 1),
+        Instruction::SaveRange(vx, vy) => (Access::Write, (vx as u32).abs_diff(vy as u32) + 1),
+        Instruction::LoadRange(vx, vy) => (Access::Read, (vx as u32).abs_diff(vy as u32) + 1),
+        Instruction::LoadBinaryCodedDecimal(_) => (Access::Write, 3),
+        Instruction::LoadAudio => (Access::Read, 16),
+        Instruction::Draw(_, _, nibble) => {
+            let size = match usize::from(nibble) as u32 {
+                0 => 32,
+                rows => rows,
+            };
+            let planes = vm.display().planes().iter().count() as u32;
+            (Access::Read, size * planes)
+        }
+        _ => return None,
+    };
+    Some((access, i, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::VRegister::*;
+    use crate::random::XorShift;
+
+    fn test_debugger(rom: &[u8]) -> Debugger<XorShift> {
+        let rng = XorShift::default();
+        let mut vm = VM::new(rng, |_, _| Ok(()));
+        vm.load_rom(rom).unwrap();
+        Debugger::new(vm)
+    }
+
+    #[test]
+    fn debugger_breakpoint() -> crate::errors::Result<()> {
+        // ADD V0, 1; JP 0x200
+        let mut debugger = test_debugger(&[0x70, 0x01, 0x12, 0x00]);
+        debugger.add_breakpoint(Breakpoint::new(0x200.into()));
+
+        assert_eq!(debugger.run()?, StopReason::Breakpoint(0x200.into()));
+        assert_eq!(debugger.vm().register(V0), 1);
+        assert_eq!(debugger.run()?, StopReason::Breakpoint(0x200.into()));
+        assert_eq!(debugger.vm().register(V0), 2);
+
+        debugger.remove_breakpoints(0x200.into());
+        debugger.add_breakpoint(Breakpoint::with_condition(
+            0x202.into(),
+            Condition {
+                register: V0,
+                comparison: Comparison::GreaterEqual,
+                value: 10,
+            },
+        ));
+        assert_eq!(debugger.run()?, StopReason::Breakpoint(0x202.into()));
+        assert_eq!(debugger.vm().register(V0), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn debugger_watchpoint() -> crate::errors::Result<()> {
+        // LD I, 0x300; LD [I], V1; LD V1, [I]; DRW V0, V0, 4
+        let mut debugger = test_debugger(&[0xA3, 0x00, 0xF1, 0x55, 0xF1, 0x65, 0xD0, 0x04]);
+        debugger.add_watchpoint(Watchpoint {
+            addr: 0x301.into(),
+            len: 1,
+            access: Access::Write,
+        });
+        debugger.add_watchpoint(Watchpoint {
+            addr: 0x303.into(),
+            len: 4,
+            access: Access::ReadWrite,
+        });
+
+        assert_eq!(
+            debugger.run()?,
+            StopReason::Watchpoint {
+                addr: 0x301.into(),
+                access: Access::Write
+            }
+        );
+        assert_eq!(debugger.vm().pc(), 0x204);
+        // `I` was incremented to 0x302 by the default quirks
+        assert_eq!(
+            debugger.run()?,
+            StopReason::Watchpoint {
+                addr: 0x303.into(),
+                access: Access::Read
+            }
+        );
+        assert_eq!(
+            debugger.step_into()?,
+            StopReason::Watchpoint {
+                addr: 0x304.into(),
+                access: Access::Read
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn debugger_instruction_filter() -> crate::errors::Result<()> {
+        // LD V0, 1; DRW V0, V0, 1; JP 0x200
+        let mut debugger = test_debugger(&[0x60, 0x01, 0xD0, 0x01, 0x12, 0x00]);
+        debugger.break_on_instruction(|instruction| matches!(instruction, Instruction::Draw(..)));
+
+        assert_eq!(
+            debugger.run()?,
+            StopReason::Instruction(Instruction::Draw(V0, V0, 1.into()))
+        );
+        assert_eq!(debugger.vm().pc(), 0x202);
+
+        debugger.clear_instruction_filters();
+        assert_eq!(debugger.step_into()?, StopReason::Step);
+        assert_eq!(debugger.vm().pc(), 0x204);
+        Ok(())
+    }
+
+    #[test]
+    fn debugger_step_over_and_out() -> crate::errors::Result<()> {
+        // CALL 0x206; ADD V1, 1; JP 0x202; ADD V0, 1; RET
+        let mut debugger =
+            test_debugger(&[0x22, 0x06, 0x71, 0x01, 0x12, 0x02, 0x70, 0x01, 0x00, 0xEE]);
+
+        assert_eq!(debugger.step_over()?, StopReason::Step);
+        assert_eq!(debugger.vm().pc(), 0x202);
+        assert_eq!(debugger.vm().register(V0), 1);
+
+        debugger.vm_mut().set_pc(0x200);
+        assert_eq!(debugger.step_into()?, StopReason::Step);
+        assert_eq!(debugger.vm().pc(), 0x206);
+        assert_eq!(debugger.step_out()?, StopReason::Step);
+        assert_eq!(debugger.vm().pc(), 0x202);
+        assert_eq!(debugger.vm().register(V0), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn debugger_step_limit() -> crate::errors::Result<()> {
+        // JP 0x200
+        let rng = XorShift::default();
+        let mut vm = VM::new(rng, |_, _| Ok(()));
+        vm.load_rom(&[0x12, 0x00])?;
+        let mut debugger = Debugger::with_config(vm, Config { step_limit: 10 });
+
+        assert_eq!(debugger.run()?, StopReason::StepLimit);
+        Ok(())
+    }
+}
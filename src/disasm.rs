@@ -0,0 +1,459 @@
+//! Disassembler
+//!
+//! Turns a ROM into a [`Listing`] of instructions and data bytes.
+//! Code is separated from data by following jumps, calls and skips from the start address.
+
+use crate::instructions::{Addr, Instruction, VRegister};
+use crate::vm::PROGRAM_START;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Assembly syntax of a [`Listing`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// Mnemonics of Cowgod's Chip-8 Technical Reference, e.g. `LD V0, 0x12`
+    #[default]
+    Cowgod,
+    /// Octo assembly language, e.g. `v0 := 0x12`
+    Octo,
+}
+
+/// Content of a single [`Line`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Item {
+    /// Reachable code
+    Instruction(Instruction),
+    /// Unreachable or undecodable data
+    Byte(u8),
+}
+
+/// Line of a [`Listing`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Line {
+    addr: Addr,
+    item: Item,
+}
+
+impl Line {
+    /// Returns the memory address of the item
+    pub fn addr(&self) -> Addr {
+        self.addr
+    }
+
+    /// Returns the instruction or data byte
+    pub fn item(&self) -> Item {
+        self.item
+    }
+}
+
+/// Disassembled ROM
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Listing {
+    start: u16,
+    lines: Vec<Line>,
+    labels: BTreeSet<u16>,
+}
+
+/// Disassembles the `rom` loaded at `0x200`
+pub fn disassemble(rom: &[u8]) -> Listing {
+    disassemble_at(rom, Addr::from(PROGRAM_START))
+}
+
+/// Disassembles the `rom` loaded at `start`
+pub fn disassemble_at(rom: &[u8], start: Addr) -> Listing {
+    let start: u16 = start.into();
+    let offset = |addr: u16| -> Option<usize> {
+        let offs = usize::from(addr.checked_sub(start)?);
+        if offs < rom.len() {
+            Some(offs)
+        } else {
+            None
+        }
+    };
+    let word = |addr: u16| -> Option<u16> {
+        let offs = offset(addr)?;
+        let high = *rom.get(offs)?;
+        let low = *rom.get(offs + 1)?;
+        Some(u16::from_be_bytes([high, low]))
+    };
+
+    let mut instructions = BTreeMap::new();
+    let mut claimed = BTreeSet::new();
+    let mut targets = BTreeSet::new();
+    let mut pending = Vec::from([start]);
+
+    while let Some(mut addr) = pending.pop() {
+        loop {
+            if instructions.contains_key(&addr) || claimed.contains(&addr) {
+                break;
+            }
+            let bits = match word(addr) {
+                Some(bits) => bits,
+                None => break,
+            };
+            let decoded = if Instruction::is_long(bits) {
+                match word(addr.wrapping_add(2)) {
+                    Some(next) => Instruction::decode_long(bits, next),
+                    None => break,
+                }
+            } else {
+                Instruction::decode(bits)
+            };
+            let instruction = match decoded {
+                Ok(instruction) => instruction,
+                Err(_) => break,
+            };
+            let size = instruction.size();
+            if (1..size).any(|offs| claimed.contains(&addr.wrapping_add(offs))) {
+                break;
+            }
+
+            instructions.insert(addr, instruction);
+            for offs in 0..size {
+                claimed.insert(addr.wrapping_add(offs));
+            }
+            let next = addr.wrapping_add(size);
+
+            match instruction {
+                Instruction::Jump(target) | Instruction::LongJump(target) => {
+                    targets.insert(u16::from(target));
+                    pending.push(target.into());
+                    break;
+                }
+                Instruction::Call(target) => {
+                    targets.insert(u16::from(target));
+                    pending.push(target.into());
+                }
+                Instruction::Return | Instruction::Exit => break,
+                Instruction::SkipEqualOperand(..)
+                | Instruction::SkipNotEqualOperand(..)
+                | Instruction::SkipEqual(..)
+                | Instruction::SkipNotEqual(..)
+                | Instruction::SkipKeyPressed(_)
+                | Instruction::SkipKeyNotPressed(_) => {
+                    let skipped = match word(next) {
+                        Some(bits) if Instruction::is_long(bits) => 4,
+                        _ => 2,
+                    };
+                    pending.push(next.wrapping_add(skipped));
+                }
+                _ => {}
+            }
+
+            addr = next;
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut offs = 0;
+    while offs < rom.len() {
+        let addr = start.wrapping_add(offs as u16);
+        let item = match instructions.get(&addr) {
+            Some(instruction) => {
+                offs += usize::from(instruction.size());
+                Item::Instruction(*instruction)
+            }
+            None => {
+                offs += 1;
+                Item::Byte(rom[offs - 1])
+            }
+        };
+        lines.push(Line {
+            addr: Addr::long(addr),
+            item,
+        });
+    }
+
+    // only targets at the start of a line can be labelled
+    let line_starts: BTreeSet<u16> = lines.iter().map(|line| line.addr.into()).collect();
+    let mut labels: BTreeSet<u16> = targets.intersection(&line_starts).copied().collect();
+    if !lines.is_empty() {
+        labels.insert(start);
+    }
+
+    Listing {
+        start,
+        lines,
+        labels,
+    }
+}
+
+impl Listing {
+    /// Returns all lines in order of their addresses
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Returns whether there is a label for `addr`
+    pub fn is_label(&self, addr: Addr) -> bool {
+        self.labels.contains(&addr.into())
+    }
+
+    /// Returns a formatter for the listing in the given `syntax`
+    pub fn display(&self, syntax: Syntax) -> ListingDisplay<'_> {
+        ListingDisplay {
+            listing: self,
+            syntax,
+        }
+    }
+
+    fn label(&self, addr: Addr) -> Label {
+        Label {
+            addr: addr.into(),
+            main: u16::from(addr) == self.start,
+        }
+    }
+
+    fn target(&self, addr: Addr) -> Target {
+        if self.is_label(addr) {
+            Target::Label(self.label(addr))
+        } else {
+            Target::Addr(addr)
+        }
+    }
+}
+
+impl fmt::Display for Listing {
+    /// Formats in [`Syntax::Cowgod`]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.display(Syntax::Cowgod).fmt(f)
+    }
+}
+
+/// Formatter of a [`Listing`] in a specific [`Syntax`]
+pub struct ListingDisplay<'a> {
+    listing: &'a Listing,
+    syntax: Syntax,
+}
+
+impl fmt::Display for ListingDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.listing.lines {
+            if self.listing.is_label(line.addr) {
+                let label = self.listing.label(line.addr);
+                match self.syntax {
+                    Syntax::Cowgod => writeln!(f, "{}:", label)?,
+                    Syntax::Octo => writeln!(f, ": {}", label)?,
+                }
+            }
+
+            write!(f, "    ")?;
+            match (self.syntax, line.item) {
+                (Syntax::Cowgod, Item::Instruction(instruction)) => {
+                    write_cowgod(f, self.listing, &instruction)?
+                }
+                (Syntax::Octo, Item::Instruction(instruction)) => {
+                    write_octo(f, self.listing, &instruction)?
+                }
+                (Syntax::Cowgod, Item::Byte(byte)) => write!(f, "db {:#04X}", byte)?,
+                (Syntax::Octo, Item::Byte(byte)) => write!(f, "{:#04X}", byte)?,
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generated name for a jump or call target
+struct Label {
+    addr: u16,
+    main: bool,
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.main {
+            write!(f, "main")
+        } else {
+            write!(f, "L{:03X}", self.addr)
+        }
+    }
+}
+
+/// Jump or call target, either labelled or a plain address
+enum Target {
+    Label(Label),
+    Addr(Addr),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Label(label) => label.fmt(f),
+            Self::Addr(addr) => addr.fmt(f),
+        }
+    }
+}
+
+fn write_cowgod(
+    f: &mut fmt::Formatter,
+    listing: &Listing,
+    instruction: &Instruction,
+) -> fmt::Result {
+    match *instruction {
+        Instruction::Jump(addr) => write!(f, "JP {}", listing.target(addr)),
+        Instruction::Call(addr) => write!(f, "CALL {}", listing.target(addr)),
+        Instruction::LongJump(addr) => write!(f, "JP V0, {}", listing.target(addr)),
+        _ => write!(f, "{}", instruction),
+    }
+}
+
+/// Octo register name, e.g. `va`
+struct Octo(VRegister);
+
+impl fmt::Display for Octo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{:x}", self.0 as usize)
+    }
+}
+
+fn write_octo(f: &mut fmt::Formatter, listing: &Listing, instruction: &Instruction) -> fmt::Result {
+    use Instruction::*;
+
+    match *instruction {
+        // Octo has no mnemonic for machine code routines
+        Sys(_) => {
+            let [high, low] = instruction.encode().to_be_bytes();
+            write!(f, "{:#04X} {:#04X}", high, low)
+        }
+        Clear => write!(f, "clear"),
+        Return => write!(f, "return"),
+        Jump(addr) => write!(f, "jump {}", listing.target(addr)),
+        Call(addr) => write!(f, ":call {}", listing.target(addr)),
+        SkipEqualOperand(vx, byte) => write!(f, "if {} != {:#04X} then", Octo(vx), byte),
+        SkipNotEqualOperand(vx, byte) => write!(f, "if {} == {:#04X} then", Octo(vx), byte),
+        SkipEqual(vx, vy) => write!(f, "if {} != {} then", Octo(vx), Octo(vy)),
+        LoadOperand(vx, byte) => write!(f, "{} := {:#04X}", Octo(vx), byte),
+        AddOperand(vx, byte) => write!(f, "{} += {:#04X}", Octo(vx), byte),
+        Load(vx, vy) => write!(f, "{} := {}", Octo(vx), Octo(vy)),
+        Or(vx, vy) => write!(f, "{} |= {}", Octo(vx), Octo(vy)),
+        And(vx, vy) => write!(f, "{} &= {}", Octo(vx), Octo(vy)),
+        XOr(vx, vy) => write!(f, "{} ^= {}", Octo(vx), Octo(vy)),
+        Add(vx, vy) => write!(f, "{} += {}", Octo(vx), Octo(vy)),
+        Sub(vx, vy) => write!(f, "{} -= {}", Octo(vx), Octo(vy)),
+        ShiftRight(vx, vy) => write!(f, "{} >>= {}", Octo(vx), Octo(vy)),
+        SubNegated(vx, vy) => write!(f, "{} =- {}", Octo(vx), Octo(vy)),
+        ShiftLeft(vx, vy) => write!(f, "{} <<= {}", Octo(vx), Octo(vy)),
+        SkipNotEqual(vx, vy) => write!(f, "if {} == {} then", Octo(vx), Octo(vy)),
+        LoadI(addr) => write!(f, "i := {}", addr),
+        LongJump(addr) => write!(f, "jump0 {}", listing.target(addr)),
+        Random(vx, byte) => write!(f, "{} := random {:#04X}", Octo(vx), byte),
+        Draw(vx, vy, nibble) => write!(f, "sprite {} {} {}", Octo(vx), Octo(vy), nibble),
+        SkipKeyPressed(vx) => write!(f, "if {} -key then", Octo(vx)),
+        SkipKeyNotPressed(vx) => write!(f, "if {} key then", Octo(vx)),
+        LoadRegisterDelayTimer(vx) => write!(f, "{} := delay", Octo(vx)),
+        LoadKey(vx) => write!(f, "{} := key", Octo(vx)),
+        LoadDelayTimerRegister(vx) => write!(f, "delay := {}", Octo(vx)),
+        LoadSoundTimerRegister(vx) => write!(f, "buzzer := {}", Octo(vx)),
+        AddI(vx) => write!(f, "i += {}", Octo(vx)),
+        LoadSprite(vx) => write!(f, "i := hex {}", Octo(vx)),
+        LoadBinaryCodedDecimal(vx) => write!(f, "bcd {}", Octo(vx)),
+        LoadMemoryRegisters(vx) => write!(f, "save {}", Octo(vx)),
+        LoadRegistersMemory(vx) => write!(f, "load {}", Octo(vx)),
+        ScrollDown(nibble) => write!(f, "scroll-down {}", nibble),
+        ScrollRight => write!(f, "scroll-right"),
+        ScrollLeft => write!(f, "scroll-left"),
+        Exit => write!(f, "exit"),
+        LowResolution => write!(f, "lores"),
+        HighResolution => write!(f, "hires"),
+        LoadBigSprite(vx) => write!(f, "i := bighex {}", Octo(vx)),
+        StoreFlags(vx) => write!(f, "saveflags {}", Octo(vx)),
+        LoadFlags(vx) => write!(f, "loadflags {}", Octo(vx)),
+        SaveRange(vx, vy) => write!(f, "save {} - {}", Octo(vx), Octo(vy)),
+        LoadRange(vx, vy) => write!(f, "load {} - {}", Octo(vx), Octo(vy)),
+        LoadILong(addr) => write!(f, "i := long {}", addr),
+        SelectPlanes(nibble) => write!(f, "plane {}", nibble),
+        LoadAudio => write!(f, "audio"),
+        Pitch(vx) => write!(f, "pitch := {}", Octo(vx)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_code_and_data() {
+        let rom = [
+            0x00, 0xE0, // 0x200: CLS
+            0x22, 0x08, // 0x202: CALL 0x208
+            0x12, 0x02, // 0x204: JP 0x202
+            0xFF, 0xFF, // 0x206: data
+            0x60, 0x12, // 0x208: LD V0, 0x12
+            0x00, 0xEE, // 0x20A: RET
+        ];
+
+        let listing = disassemble(&rom);
+
+        assert_eq!(listing.lines().len(), 7);
+        assert_eq!(listing.lines()[3].item(), Item::Byte(0xFF));
+        assert_eq!(listing.lines()[4].addr(), Addr::from(0x207));
+        assert_eq!(
+            listing.lines()[5].item(),
+            Item::Instruction(Instruction::LoadOperand(VRegister::V0, 0x12))
+        );
+        assert!(listing.is_label(0x202.into()));
+        assert!(listing.is_label(0x208.into()));
+        assert!(!listing.is_label(0x204.into()));
+    }
+
+    #[test]
+    fn disassemble_skip_long() {
+        let rom = [
+            0x30, 0x00, // 0x200: SE V0, 0x00
+            0xF0, 0x00, 0x12, 0x34, // 0x202: LD I, long 0x1234
+            0x00, 0xFD, // 0x206: EXIT
+        ];
+
+        let listing = disassemble(&rom);
+
+        assert_eq!(listing.lines().len(), 3);
+        assert_eq!(listing.lines()[2].addr(), Addr::from(0x206));
+    }
+
+    #[test]
+    fn display_cowgod() {
+        let rom = [
+            0x00, 0xE0, 0x22, 0x08, 0x12, 0x02, 0xFF, 0xFF, 0x60, 0x12, 0x00, 0xEE,
+        ];
+
+        let listing = disassemble(&rom);
+
+        assert_eq!(
+            listing.display(Syntax::Cowgod).to_string(),
+            "main:\n    CLS\n\
+             L202:\n    CALL L208\n    JP L202\n    db 0xFF\n    db 0xFF\n\
+             L208:\n    LD V0, 0x12\n    RET\n"
+        );
+        assert_eq!(
+            listing.to_string(),
+            listing.display(Syntax::Cowgod).to_string()
+        );
+    }
+
+    #[test]
+    fn display_octo() {
+        let rom = [
+            0x00, 0xE0, 0x22, 0x08, 0x12, 0x02, 0xFF, 0xFF, 0x60, 0x12, 0x00, 0xEE,
+        ];
+
+        let listing = disassemble(&rom);
+
+        assert_eq!(
+            listing.display(Syntax::Octo).to_string(),
+            ": main\n    clear\n\
+             : L202\n    :call L208\n    jump L202\n    0xFF\n    0xFF\n\
+             : L208\n    v0 := 0x12\n    return\n"
+        );
+    }
+
+    #[test]
+    fn display_unlabelled_target() {
+        let rom = [0x13, 0x00];
+
+        let listing = disassemble(&rom);
+
+        assert_eq!(listing.to_string(), "main:\n    JP 0x300\n");
+    }
+}
@@ -1,22 +1,19 @@
 //! Display
 
+use crate::snapshot::{DisplayState, DISPLAY_BITS_SIZE};
+use alloc::vec;
 use alloc::vec::Vec;
 
 /// Monochrome pixel
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Pixel {
     /// Off
+    #[default]
     Off,
     /// On
     On,
 }
 
-impl Default for Pixel {
-    fn default() -> Self {
-        Self::Off
-    }
-}
-
 impl core::ops::BitXorAssign for Pixel {
     fn bitxor_assign(&mut self, rhs: Self) {
         *self = match self {
@@ -50,6 +47,91 @@ pub enum DrawResult {
     Overdrawn,
 }
 
+/// What happens to sprite pixels beyond the edge of the `Display`
+///
+/// The start position of a sprite always wraps around the display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// Pixels beyond the edge are not drawn, like the original COSMAC VIP
+    Clip,
+    /// Pixels beyond the edge are drawn on the opposite side
+    #[default]
+    Wrap,
+}
+
+/// Resolution of the `Display`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Resolution {
+    /// 64 * 32 pixels of the original CHIP-8
+    #[default]
+    Low,
+    /// 128 * 64 pixels of SUPER-CHIP
+    High,
+}
+
+impl Resolution {
+    /// Horizontal pixel count
+    pub const fn width(self) -> usize {
+        match self {
+            Self::Low => Display::WIDTH,
+            Self::High => Display::HIGH_RES_WIDTH,
+        }
+    }
+
+    /// Vertical pixel count
+    pub const fn height(self) -> usize {
+        match self {
+            Self::Low => Display::HEIGHT,
+            Self::High => Display::HIGH_RES_HEIGHT,
+        }
+    }
+}
+
+/// Selection of the XO-CHIP bitplanes of the `Display`
+///
+/// Bit `0` is the first and bit `1` the second bitplane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Planes(u8);
+
+impl Planes {
+    /// No bitplane
+    pub const NONE: Self = Self(0b00);
+    /// Only the first bitplane, used by everything but XO-CHIP
+    pub const FIRST: Self = Self(0b01);
+    /// Only the second bitplane
+    pub const SECOND: Self = Self(0b10);
+    /// Both bitplanes
+    pub const BOTH: Self = Self(0b11);
+
+    /// Creates a new instance from the lowest two `bits`
+    pub const fn new(bits: u8) -> Self {
+        Self(bits & 0b11)
+    }
+
+    /// Returns the selection as bits
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns whether the bitplane with index `plane` is selected
+    pub const fn contains(self, plane: usize) -> bool {
+        plane < Display::PLANES && self.0 & (1 << plane) != 0
+    }
+
+    /// Returns an `Iterator` over each selected bitplane on its own, first to second
+    pub fn iter(self) -> impl Iterator<Item = Planes> {
+        (0..Display::PLANES)
+            .filter(move |plane| self.contains(*plane))
+            .map(|plane| Self(1 << plane))
+    }
+}
+
+impl Default for Planes {
+    fn default() -> Self {
+        Self::FIRST
+    }
+}
+
 /// X coordinate of a `Pixel` on the `Display`
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct XCoordinate(usize);
@@ -60,7 +142,17 @@ pub struct YCoordinate(usize);
 impl XCoordinate {
     /// Creates a new instance, wrapping around the display if needed
     pub fn new(x: usize) -> Self {
-        Self(x % Display::WIDTH)
+        Self::with_resolution(x, Resolution::Low)
+    }
+
+    /// Creates a new instance, wrapping around the display with `resolution` if needed
+    pub fn with_resolution(x: usize, resolution: Resolution) -> Self {
+        Self(x % resolution.width())
+    }
+
+    /// Returns the horizontal pixel index
+    pub const fn get(self) -> usize {
+        self.0
     }
 
     /// Returns a new x coordinate that might have wrapped around the display
@@ -72,7 +164,17 @@ impl XCoordinate {
 impl YCoordinate {
     /// Creates a new instance, wrapping around the display if needed
     pub fn new(y: usize) -> Self {
-        Self(y % Display::HEIGHT)
+        Self::with_resolution(y, Resolution::Low)
+    }
+
+    /// Creates a new instance, wrapping around the display with `resolution` if needed
+    pub fn with_resolution(y: usize, resolution: Resolution) -> Self {
+        Self(y % resolution.height())
+    }
+
+    /// Returns the vertical pixel index
+    pub const fn get(self) -> usize {
+        self.0
     }
 
     /// Returns a new y coordinate that might have wrapped around the display
@@ -81,59 +183,402 @@ impl YCoordinate {
     }
 }
 
-/// Display with 64 * 32 monochrome pixels
+/// Screen the VM draws on
+///
+/// Implement this to render into your own framebuffer, the built-in implementation is [`Display`].
+pub trait Screen {
+    /// Clears the screen by setting all pixels to the `Off` state
+    fn clear(&mut self);
+
+    /// Draw `sprite` at the given `x` + `y` coordinates
+    ///
+    /// Pixels of the `sprite` that are `On` flip the pixel on the screen.
+    /// Returns whether any pixels were erased, i.e. whether there was a collision.
+    fn draw(&mut self, sprite: &Sprite, x: XCoordinate, y: YCoordinate) -> DrawResult;
+
+    /// Returns the current resolution
+    fn resolution(&self) -> Resolution;
+
+    /// Switches to `resolution`
+    fn set_resolution(&mut self, resolution: Resolution);
+
+    /// Scrolls all pixels down by `rows`
+    fn scroll_down(&mut self, rows: usize);
+
+    /// Scrolls all pixels left by 4 pixels
+    fn scroll_left(&mut self);
+
+    /// Scrolls all pixels right by 4 pixels
+    fn scroll_right(&mut self);
+
+    /// Returns the bitplanes affected by clearing, drawing and scrolling
+    ///
+    /// Screens without XO-CHIP support only have the first bitplane.
+    fn planes(&self) -> Planes {
+        Planes::FIRST
+    }
+
+    /// Selects the bitplanes affected by clearing, drawing and scrolling
+    ///
+    /// Screens without XO-CHIP support ignore this.
+    fn select_planes(&mut self, _planes: Planes) {}
+}
+
+/// Pixels of a single bitplane, column by column
+type Bitplane = [[Pixel; Display::HIGH_RES_HEIGHT]; Display::HIGH_RES_WIDTH];
+
+/// Display with 64 * 32 or 128 * 64 pixels
+///
+/// There are two bitplanes for the four colours of XO-CHIP, everything else only uses the first.
 pub struct Display {
-    pixels: [[Pixel; Display::HEIGHT]; Display::WIDTH],
+    planes: [Bitplane; Display::PLANES],
+    selected: Planes,
+    resolution: Resolution,
+    edge_mode: EdgeMode,
 }
 
 impl Display {
-    /// Horizontal pixel count
+    /// Horizontal pixel count in low resolution
     pub const WIDTH: usize = 64;
-    /// Vertical pixel count
+    /// Vertical pixel count in low resolution
     pub const HEIGHT: usize = 32;
+    /// Horizontal pixel count in high resolution
+    pub const HIGH_RES_WIDTH: usize = 128;
+    /// Vertical pixel count in high resolution
+    pub const HIGH_RES_HEIGHT: usize = 64;
+    /// Number of bitplanes
+    pub const PLANES: usize = 2;
+
+    /// Creates a new instance in low resolution with all pixels `Off`, drawing sprites with `edge_mode`
+    pub fn new(edge_mode: EdgeMode) -> Self {
+        Self {
+            planes: [[[Pixel::default(); Self::HIGH_RES_HEIGHT]; Self::HIGH_RES_WIDTH];
+                Self::PLANES],
+            selected: Planes::default(),
+            resolution: Resolution::default(),
+            edge_mode,
+        }
+    }
+
+    /// Horizontal pixel count in the current resolution
+    pub fn width(&self) -> usize {
+        self.resolution.width()
+    }
+
+    /// Vertical pixel count in the current resolution
+    pub fn height(&self) -> usize {
+        self.resolution.height()
+    }
+
+    /// Returns the pixel of the first bitplane at the given `x` + `y` coordinates
+    pub fn pixel(&self, x: XCoordinate, y: YCoordinate) -> Pixel {
+        self.planes[0][x.0][y.0]
+    }
+
+    /// Returns the colour at the given `x` + `y` coordinates
+    ///
+    /// Colours are `0` .. `3`, bit `0` is the pixel of the first and bit `1` of the second bitplane.
+    pub fn color(&self, x: XCoordinate, y: YCoordinate) -> u8 {
+        self.color_at(x.0, y.0)
+    }
+
+    fn color_at(&self, x: usize, y: usize) -> u8 {
+        self.planes
+            .iter()
+            .enumerate()
+            .filter(|(_, plane)| plane[x][y] == Pixel::On)
+            .fold(0, |color, (idx, _)| color | 1 << idx)
+    }
+
+    /// Returns an `Iterator` over all rows of the first bitplane from top to bottom,
+    /// each with pixels from left to right
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = Pixel> + '_> + '_ {
+        let width = self.width();
+        (0..self.height()).map(move |y| (0..width).map(move |x| self.planes[0][x][y]))
+    }
+
+    /// Returns an `Iterator` over all columns of the first bitplane from left to right,
+    /// each with pixels from top to bottom
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = Pixel> + '_> + '_ {
+        let height = self.height();
+        self.planes[0][..self.width()]
+            .iter()
+            .map(move |column| column[..height].iter().copied())
+    }
 
-    /// Clears the display by setting all pixels to the `Off` state
-    pub fn clear(&mut self) {
-        for x in 0..Self::WIDTH {
-            for y in 0..Self::HEIGHT {
-                self.pixels[x][y] = Pixel::Off;
+    /// Returns all pixels of the first bitplane packed into one `u64` per row
+    ///
+    /// The most significant bit is the leftmost pixel, a set bit is `On`.
+    /// In high resolution this only contains the top left 64 * 32 pixels, see
+    /// [`to_high_res_bitplane`](Self::to_high_res_bitplane).
+    pub fn to_bitplane(&self) -> [u64; Self::HEIGHT] {
+        let mut bitplane = [0; Self::HEIGHT];
+        for (y, row) in bitplane.iter_mut().enumerate() {
+            for x in 0..Self::WIDTH {
+                if self.planes[0][x][y] == Pixel::On {
+                    *row |= 1 << (Self::WIDTH - 1 - x);
+                }
             }
         }
+        bitplane
     }
 
-    /// Draw `sprite` at the given `x` + `y` coordinates
-    pub fn draw(&mut self, sprite: &Sprite, x: XCoordinate, y: YCoordinate) -> DrawResult {
-        let mut x = x;
-        let mut y = y;
-        let mut res = DrawResult::Drawn;
-        for row in &sprite.rows {
-            for pixel in &row.0 {
-                if self.pixels[x.0][y.0] == Pixel::On && *pixel == Pixel::On {
-                    res = DrawResult::Overdrawn;
+    /// Returns all pixels of the first bitplane packed into one `u128` per row
+    ///
+    /// The most significant bit is the leftmost pixel, a set bit is `On`.
+    /// In low resolution this only contains pixels in the top left 64 * 32 pixels.
+    pub fn to_high_res_bitplane(&self) -> [u128; Self::HIGH_RES_HEIGHT] {
+        let mut bitplane = [0; Self::HIGH_RES_HEIGHT];
+        for (y, row) in bitplane.iter_mut().enumerate() {
+            for x in 0..Self::HIGH_RES_WIDTH {
+                if self.planes[0][x][y] == Pixel::On {
+                    *row |= 1 << (Self::HIGH_RES_WIDTH - 1 - x);
+                }
+            }
+        }
+        bitplane
+    }
+
+    /// Returns all pixels of the first bitplane as one byte each, row by row
+    ///
+    /// `On` is `0xFF` and `Off` is `0x00`.
+    pub fn to_greyscale(&self) -> Vec<u8> {
+        self.rows()
+            .flatten()
+            .map(|pixel| match pixel {
+                Pixel::Off => 0x00,
+                Pixel::On => 0xFF,
+            })
+            .collect()
+    }
+
+    /// Returns all pixels of the first bitplane as four bytes each, row by row
+    ///
+    /// Pixels are colored in the given `on` and `off` RGBA colors.
+    pub fn to_rgba(&self, on: [u8; 4], off: [u8; 4]) -> Vec<u8> {
+        self.rows()
+            .flatten()
+            .flat_map(|pixel| match pixel {
+                Pixel::Off => off,
+                Pixel::On => on,
+            })
+            .collect()
+    }
+
+    /// Returns the colours of all pixels as one byte each, row by row, see [`color`](Self::color)
+    pub fn to_indexed(&self) -> Vec<u8> {
+        let width = self.width();
+        (0..self.height())
+            .flat_map(|y| (0..width).map(move |x| self.color_at(x, y)))
+            .collect()
+    }
+
+    /// Returns the colours of all pixels as four bytes each, row by row
+    ///
+    /// Colours are looked up in the given `palette` of RGBA colours, see [`color`](Self::color).
+    pub fn to_rgba_palette(&self, palette: [[u8; 4]; 4]) -> Vec<u8> {
+        self.to_indexed()
+            .into_iter()
+            .flat_map(|color| palette[usize::from(color)])
+            .collect()
+    }
+}
+
+impl Display {
+    /// Returns the state for a [`Snapshot`](crate::snapshot::Snapshot)
+    pub(crate) fn save_state(&self) -> DisplayState {
+        let mut bits = vec![0; DISPLAY_BITS_SIZE];
+        for (idx, plane) in self.planes.iter().enumerate() {
+            for (x, column) in plane.iter().enumerate() {
+                for (y, pixel) in column.iter().enumerate() {
+                    if *pixel == Pixel::On {
+                        let bit = (idx * Self::HIGH_RES_HEIGHT + y) * Self::HIGH_RES_WIDTH + x;
+                        bits[bit / 8] |= 0x80 >> (bit % 8);
+                    }
+                }
+            }
+        }
+        DisplayState {
+            resolution: self.resolution,
+            selected: self.selected,
+            edge_mode: self.edge_mode,
+            bits,
+        }
+    }
+
+    /// Restores the state from a [`Snapshot`](crate::snapshot::Snapshot)
+    pub(crate) fn restore_state(&mut self, state: &DisplayState) {
+        for (idx, plane) in self.planes.iter_mut().enumerate() {
+            for (x, column) in plane.iter_mut().enumerate() {
+                for (y, pixel) in column.iter_mut().enumerate() {
+                    let bit = (idx * Self::HIGH_RES_HEIGHT + y) * Self::HIGH_RES_WIDTH + x;
+                    *pixel = if state.bits[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                        Pixel::On
+                    } else {
+                        Pixel::Off
+                    };
+                }
+            }
+        }
+        self.resolution = state.resolution;
+        self.selected = state.selected;
+        self.edge_mode = state.edge_mode;
+    }
+
+    /// Returns an `Iterator` over the selected bitplanes
+    fn selected_planes(&mut self) -> impl Iterator<Item = &mut Bitplane> {
+        let selected = self.selected;
+        self.planes
+            .iter_mut()
+            .enumerate()
+            .filter(move |(idx, _)| selected.contains(*idx))
+            .map(|(_, plane)| plane)
+    }
+}
+
+impl Screen for Display {
+    /// Clears the selected bitplanes by setting all pixels to the `Off` state
+    fn clear(&mut self) {
+        for plane in self.selected_planes() {
+            for column in plane.iter_mut() {
+                for pixel in column.iter_mut() {
+                    *pixel = Pixel::Off;
                 }
+            }
+        }
+    }
 
-                self.pixels[x.0][y.0] ^= *pixel;
+    /// Draw `sprite` at the given `x` + `y` coordinates on all selected bitplanes
+    ///
+    /// Pixels beyond the edge of the display are handled according to the [`EdgeMode`].
+    fn draw(&mut self, sprite: &Sprite, x: XCoordinate, y: YCoordinate) -> DrawResult {
+        let width = self.width();
+        let height = self.height();
+        let edge_mode = self.edge_mode;
 
-                x = x.wrapping_add(1);
+        let mut res = DrawResult::Drawn;
+        for pixels in self.selected_planes() {
+            if draw_on_plane(pixels, sprite, x, y, width, height, edge_mode)
+                == DrawResult::Overdrawn
+            {
+                res = DrawResult::Overdrawn;
             }
-            y = y.wrapping_add(1);
         }
 
         res
     }
+
+    fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Switches to `resolution` and clears all bitplanes
+    fn set_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+        let selected = self.selected;
+        self.selected = Planes::BOTH;
+        self.clear();
+        self.selected = selected;
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        let width = self.width();
+        let height = self.height();
+        for plane in self.selected_planes() {
+            for column in plane[..width].iter_mut() {
+                for y in (0..height).rev() {
+                    column[y] = if y >= rows {
+                        column[y - rows]
+                    } else {
+                        Pixel::Off
+                    };
+                }
+            }
+        }
+    }
+
+    fn scroll_left(&mut self) {
+        let width = self.width();
+        for pixels in self.selected_planes() {
+            for x in 0..width {
+                pixels[x] = if x + 4 < width {
+                    pixels[x + 4]
+                } else {
+                    [Pixel::Off; Self::HIGH_RES_HEIGHT]
+                };
+            }
+        }
+    }
+
+    fn scroll_right(&mut self) {
+        let width = self.width();
+        for pixels in self.selected_planes() {
+            for x in (0..width).rev() {
+                pixels[x] = if x >= 4 {
+                    pixels[x - 4]
+                } else {
+                    [Pixel::Off; Self::HIGH_RES_HEIGHT]
+                };
+            }
+        }
+    }
+
+    fn planes(&self) -> Planes {
+        self.selected
+    }
+
+    fn select_planes(&mut self, planes: Planes) {
+        self.selected = planes;
+    }
+}
+
+/// Draw `sprite` at the given `x` + `y` coordinates on a single bitplane of `width` * `height`
+fn draw_on_plane(
+    pixels: &mut Bitplane,
+    sprite: &Sprite,
+    x: XCoordinate,
+    y: YCoordinate,
+    width: usize,
+    height: usize,
+    edge_mode: EdgeMode,
+) -> DrawResult {
+    let mut res = DrawResult::Drawn;
+    for (dy, row) in sprite.rows.iter().enumerate() {
+        if edge_mode == EdgeMode::Clip && y.0 + dy >= height {
+            break;
+        }
+        let y = (y.0 + dy) % height;
+
+        for (dx, pixel) in row.pixels().iter().enumerate() {
+            if edge_mode == EdgeMode::Clip && x.0 + dx >= width {
+                break;
+            }
+            let x = (x.0 + dx) % width;
+
+            if pixels[x][y] == Pixel::On && *pixel == Pixel::On {
+                res = DrawResult::Overdrawn;
+            }
+
+            pixels[x][y] ^= *pixel;
+        }
+    }
+
+    res
 }
 
 impl Default for Display {
     fn default() -> Self {
-        Self {
-            pixels: [[Pixel::default(); Self::HEIGHT]; Self::WIDTH],
-        }
+        Self::new(EdgeMode::default())
     }
 }
 
-/// Row of 8 pixels in a sprite
+/// Row of 8 or 16 pixels in a sprite
 #[derive(Debug, PartialEq, Eq)]
-pub struct SpriteRow([Pixel; 8]);
+pub struct SpriteRow {
+    /// Pixels from left to right, `Off` beyond `width`
+    pixels: [Pixel; 16],
+    width: u8,
+}
 
 impl From<u8> for SpriteRow {
     fn from(bits: u8) -> Self {
@@ -178,13 +623,32 @@ impl From<u8> for SpriteRow {
             Pixel::Off
         };
 
-        Self([p0, p1, p2, p3, p4, p5, p6, p7])
+        let mut pixels = [Pixel::Off; 16];
+        pixels[..8].copy_from_slice(&[p0, p1, p2, p3, p4, p5, p6, p7]);
+
+        Self { pixels, width: 8 }
+    }
+}
+
+impl SpriteRow {
+    /// Creates a new instance 16 pixels wide
+    pub fn from_wide(bits: u16) -> Self {
+        let [high, low] = bits.to_be_bytes();
+        let mut pixels = SpriteRow::from(high).pixels;
+        pixels[8..].copy_from_slice(SpriteRow::from(low).pixels());
+
+        Self { pixels, width: 16 }
+    }
+
+    /// Returns the pixels from left to right
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels[..usize::from(self.width)]
     }
 }
 
 impl core::fmt::Display for SpriteRow {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        for pixel in &self.0 {
+        for pixel in self.pixels() {
             write!(f, "{}", pixel)?;
         }
         Ok(())
@@ -192,11 +656,32 @@ impl core::fmt::Display for SpriteRow {
 }
 
 /// Sprite of several rows of pixels
+///
+/// Sprites are 8 pixels wide from `u8` rows and 16 pixels wide from `u16` rows,
+/// see [`from_wide`](Self::from_wide).
 #[derive(Debug, PartialEq, Eq)]
 pub struct Sprite {
     rows: Vec<SpriteRow>,
 }
 
+impl Sprite {
+    /// Creates a new instance 16 pixels wide
+    pub fn from_wide(rows: &[u16]) -> Self {
+        let mut sprite_rows = Vec::with_capacity(rows.len());
+        for row in rows {
+            let sprite_row = SpriteRow::from_wide(*row);
+            sprite_rows.push(sprite_row);
+        }
+
+        Self { rows: sprite_rows }
+    }
+
+    /// Returns the rows from top to bottom
+    pub fn rows(&self) -> &[SpriteRow] {
+        &self.rows
+    }
+}
+
 impl From<&[u8]> for Sprite {
     fn from(rows: &[u8]) -> Self {
         let mut sprite_rows = Vec::with_capacity(rows.len());
@@ -277,7 +762,7 @@ mod tests {
 
         for x in 0..Display::WIDTH {
             for y in 0..Display::HEIGHT {
-                assert_eq!(display.pixels[x][y], Pixel::Off);
+                assert_eq!(display.planes[0][x][y], Pixel::Off);
             }
         }
     }
@@ -305,6 +790,226 @@ mod tests {
         assert_eq!(res, DrawResult::Overdrawn);
     }
 
+    #[test]
+    fn display_draw_rows() {
+        let mut display = Display::default();
+        let data = [0b1000_0000, 0b1000_0000];
+        let sprite: Sprite = data[..].into();
+
+        display.draw(&sprite, XCoordinate(1), YCoordinate(1));
+
+        assert_eq!(display.planes[0][1][1], Pixel::On);
+        assert_eq!(display.planes[0][1][2], Pixel::On);
+        assert_eq!(display.planes[0][2][2], Pixel::Off);
+    }
+
+    #[test]
+    fn display_draw_wrap() {
+        let mut display = Display::new(EdgeMode::Wrap);
+        let data = [0b1100_0000, 0b1100_0000];
+        let sprite: Sprite = data[..].into();
+
+        display.draw(
+            &sprite,
+            XCoordinate(Display::WIDTH - 1),
+            YCoordinate(Display::HEIGHT - 1),
+        );
+
+        assert_eq!(
+            display.planes[0][Display::WIDTH - 1][Display::HEIGHT - 1],
+            Pixel::On
+        );
+        assert_eq!(display.planes[0][0][Display::HEIGHT - 1], Pixel::On);
+        assert_eq!(display.planes[0][Display::WIDTH - 1][0], Pixel::On);
+        assert_eq!(display.planes[0][0][0], Pixel::On);
+    }
+
+    #[test]
+    fn display_draw_clip() {
+        let mut display = Display::new(EdgeMode::Clip);
+        let data = [0b1100_0000, 0b1100_0000];
+        let sprite: Sprite = data[..].into();
+
+        display.draw(
+            &sprite,
+            XCoordinate(Display::WIDTH - 1),
+            YCoordinate(Display::HEIGHT - 1),
+        );
+
+        assert_eq!(
+            display.planes[0][Display::WIDTH - 1][Display::HEIGHT - 1],
+            Pixel::On
+        );
+        assert_eq!(display.planes[0][0][Display::HEIGHT - 1], Pixel::Off);
+        assert_eq!(display.planes[0][Display::WIDTH - 1][0], Pixel::Off);
+        assert_eq!(display.planes[0][0][0], Pixel::Off);
+    }
+
+    #[test]
+    fn display_pixel() {
+        let mut display = Display::default();
+        display.planes[0][3][4] = Pixel::On;
+
+        assert_eq!(display.pixel(XCoordinate(3), YCoordinate(4)), Pixel::On);
+        assert_eq!(display.pixel(XCoordinate(4), YCoordinate(3)), Pixel::Off);
+    }
+
+    #[test]
+    fn display_rows_columns() {
+        let mut display = Display::default();
+        display.planes[0][1][0] = Pixel::On;
+
+        let rows: Vec<Vec<Pixel>> = display.rows().map(|row| row.collect()).collect();
+        assert_eq!(rows.len(), Display::HEIGHT);
+        assert_eq!(rows[0].len(), Display::WIDTH);
+        assert_eq!(rows[0][1], Pixel::On);
+        assert_eq!(rows[1][0], Pixel::Off);
+
+        let columns: Vec<Vec<Pixel>> = display.columns().map(|column| column.collect()).collect();
+        assert_eq!(columns.len(), Display::WIDTH);
+        assert_eq!(columns[0].len(), Display::HEIGHT);
+        assert_eq!(columns[1][0], Pixel::On);
+        assert_eq!(columns[0][1], Pixel::Off);
+    }
+
+    #[test]
+    fn display_to_bitplane() {
+        let mut display = Display::default();
+        display.planes[0][0][0] = Pixel::On;
+        display.planes[0][Display::WIDTH - 1][Display::HEIGHT - 1] = Pixel::On;
+
+        let bitplane = display.to_bitplane();
+
+        assert_eq!(bitplane[0], 1 << 63);
+        assert_eq!(bitplane[1], 0);
+        assert_eq!(bitplane[Display::HEIGHT - 1], 1);
+    }
+
+    #[test]
+    fn display_to_greyscale_rgba() {
+        let mut display = Display::default();
+        display.planes[0][1][0] = Pixel::On;
+
+        let greyscale = display.to_greyscale();
+        assert_eq!(greyscale.len(), Display::WIDTH * Display::HEIGHT);
+        assert_eq!(greyscale[..3], [0x00, 0xFF, 0x00]);
+
+        let rgba = display.to_rgba([1, 2, 3, 4], [5, 6, 7, 8]);
+        assert_eq!(rgba.len(), Display::WIDTH * Display::HEIGHT * 4);
+        assert_eq!(rgba[..8], [5, 6, 7, 8, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn display_set_resolution() {
+        let mut display = Display::default();
+        display.planes[0][1][1] = Pixel::On;
+
+        display.set_resolution(Resolution::High);
+
+        assert_eq!(display.resolution(), Resolution::High);
+        assert_eq!(display.width(), Display::HIGH_RES_WIDTH);
+        assert_eq!(display.height(), Display::HIGH_RES_HEIGHT);
+        assert_eq!(display.planes[0][1][1], Pixel::Off);
+        assert_eq!(display.rows().count(), Display::HIGH_RES_HEIGHT);
+        assert_eq!(display.to_greyscale().len(), 128 * 64);
+    }
+
+    #[test]
+    fn display_draw_high_resolution_wrap() {
+        let mut display = Display::new(EdgeMode::Wrap);
+        display.set_resolution(Resolution::High);
+        let data = [0b1100_0000];
+        let sprite: Sprite = data[..].into();
+
+        display.draw(
+            &sprite,
+            XCoordinate(Display::HIGH_RES_WIDTH - 1),
+            YCoordinate(40),
+        );
+
+        assert_eq!(
+            display.planes[0][Display::HIGH_RES_WIDTH - 1][40],
+            Pixel::On
+        );
+        assert_eq!(display.planes[0][0][40], Pixel::On);
+        assert_eq!(display.to_high_res_bitplane()[40], 1 << 127 | 1);
+    }
+
+    #[test]
+    fn display_scroll_down() {
+        let mut display = Display::default();
+        display.planes[0][3][0] = Pixel::On;
+        display.planes[0][3][Display::HEIGHT - 1] = Pixel::On;
+
+        display.scroll_down(2);
+
+        assert_eq!(display.planes[0][3][0], Pixel::Off);
+        assert_eq!(display.planes[0][3][2], Pixel::On);
+        assert_eq!(display.planes[0][3][Display::HEIGHT - 1], Pixel::Off);
+    }
+
+    #[test]
+    fn display_scroll_left_right() {
+        let mut display = Display::default();
+        display.planes[0][5][1] = Pixel::On;
+
+        display.scroll_left();
+        assert_eq!(display.planes[0][1][1], Pixel::On);
+        assert_eq!(display.planes[0][5][1], Pixel::Off);
+
+        display.scroll_left();
+        assert!(display.to_bitplane().iter().all(|row| *row == 0));
+
+        display.planes[0][Display::WIDTH - 1][1] = Pixel::On;
+        display.scroll_right();
+        assert!(display.to_bitplane().iter().all(|row| *row == 0));
+    }
+
+    #[test]
+    fn planes_iter() {
+        let planes: Vec<Planes> = Planes::BOTH.iter().collect();
+
+        assert_eq!(planes, [Planes::FIRST, Planes::SECOND]);
+        assert_eq!(Planes::NONE.iter().count(), 0);
+        assert_eq!(Planes::new(0xF), Planes::BOTH);
+    }
+
+    #[test]
+    fn display_select_planes() {
+        let mut display = Display::default();
+        let data = [0b1000_0000];
+        let sprite: Sprite = data[..].into();
+
+        display.select_planes(Planes::SECOND);
+        display.draw(&sprite, XCoordinate(1), YCoordinate(0));
+        display.select_planes(Planes::BOTH);
+        display.draw(&sprite, XCoordinate(0), YCoordinate(0));
+
+        assert_eq!(display.color(XCoordinate(0), YCoordinate(0)), 3);
+        assert_eq!(display.color(XCoordinate(1), YCoordinate(0)), 2);
+        assert_eq!(display.pixel(XCoordinate(1), YCoordinate(0)), Pixel::Off);
+        assert_eq!(display.to_indexed()[..3], [3, 2, 0]);
+        assert_eq!(
+            display.to_rgba_palette([[0; 4], [1; 4], [2; 4], [3; 4]])[..12],
+            [3, 3, 3, 3, 2, 2, 2, 2, 0, 0, 0, 0]
+        );
+
+        display.select_planes(Planes::FIRST);
+        display.clear();
+
+        assert_eq!(display.color(XCoordinate(0), YCoordinate(0)), 2);
+    }
+
+    #[test]
+    fn spriterow_from_wide() {
+        let sprite_row = SpriteRow::from_wide(0b1000_0000_0000_0001);
+
+        assert_eq!(sprite_row.pixels().len(), 16);
+        assert_eq!(sprite_row.pixels()[0], Pixel::On);
+        assert_eq!(sprite_row.pixels()[1], Pixel::Off);
+        assert_eq!(sprite_row.pixels()[15], Pixel::On);
+    }
+
     #[test]
     fn spriterow_from_u8() {
         let data = 0b1010_0101;
@@ -312,8 +1017,8 @@ mod tests {
         let sprite_row: SpriteRow = data.into();
 
         assert_eq!(
-            sprite_row,
-            SpriteRow([
+            sprite_row.pixels(),
+            [
                 Pixel::On,
                 Pixel::Off,
                 Pixel::On,
@@ -322,7 +1027,7 @@ mod tests {
                 Pixel::On,
                 Pixel::Off,
                 Pixel::On
-            ])
+            ]
         );
     }
 
@@ -332,32 +1037,32 @@ mod tests {
 
         let sprite: Sprite = data[..].into();
 
+        assert_eq!(sprite.rows().len(), 2);
         assert_eq!(
-            sprite,
-            Sprite {
-                rows: vec![
-                    SpriteRow([
-                        Pixel::On,
-                        Pixel::On,
-                        Pixel::On,
-                        Pixel::On,
-                        Pixel::Off,
-                        Pixel::Off,
-                        Pixel::Off,
-                        Pixel::Off
-                    ]),
-                    SpriteRow([
-                        Pixel::Off,
-                        Pixel::Off,
-                        Pixel::Off,
-                        Pixel::Off,
-                        Pixel::On,
-                        Pixel::On,
-                        Pixel::On,
-                        Pixel::On
-                    ]),
-                ]
-            }
+            sprite.rows()[0].pixels(),
+            [
+                Pixel::On,
+                Pixel::On,
+                Pixel::On,
+                Pixel::On,
+                Pixel::Off,
+                Pixel::Off,
+                Pixel::Off,
+                Pixel::Off
+            ]
+        );
+        assert_eq!(
+            sprite.rows()[1].pixels(),
+            [
+                Pixel::Off,
+                Pixel::Off,
+                Pixel::Off,
+                Pixel::Off,
+                Pixel::On,
+                Pixel::On,
+                Pixel::On,
+                Pixel::On
+            ]
         );
     }
 
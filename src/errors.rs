@@ -1,5 +1,6 @@
 //! Crate error types
 
+use alloc::string::String;
 #[cfg(feature = "std")]
 use thiserror::Error;
 
@@ -19,6 +20,10 @@ pub enum Chip8Error {
     #[cfg_attr(feature = "std", error("unknown instruction {0:?}"))]
     UnknownInstruction(u16),
 
+    /// First word of a four byte instruction without the second word
+    #[cfg_attr(feature = "std", error("incomplete instruction {0:?}"))]
+    IncompleteInstruction(u16),
+
     /// Known but unimplemented instruction
     #[cfg_attr(feature = "std", error("unimplemented instruction {0:?}"))]
     UnimplementedInstruction(crate::instructions::Instruction),
@@ -30,6 +35,113 @@ pub enum Chip8Error {
     /// Value is out of valid range
     #[cfg_attr(feature = "std", error("out of range {0:?}"))]
     OutOfRange(u16),
+
+    /// ROM does not fit into memory
+    #[cfg_attr(
+        feature = "std",
+        error("ROM of {size} bytes exceeds {available} bytes of available memory")
+    )]
+    RomTooLarge {
+        /// Size of the ROM in bytes
+        size: usize,
+        /// Memory available for the ROM in bytes
+        available: usize,
+    },
+
+    /// Call stack is full, i.e. too many nested subroutine calls
+    #[cfg_attr(feature = "std", error("stack overflow with depth {0:?}"))]
+    StackOverflow(usize),
+
+    /// Call stack is empty, i.e. return without subroutine call
+    #[cfg_attr(feature = "std", error("stack underflow"))]
+    StackUnderflow,
+
+    /// Invalid assembly source
+    #[cfg_attr(feature = "std", error("{kind} at line {line}, column {column}"))]
+    Parse {
+        /// Line in the source, starting at `1`
+        line: usize,
+        /// Column in the line, starting at `1`
+        column: usize,
+        /// What went wrong
+        kind: ParseErrorKind,
+    },
+
+    /// Malformed [`Snapshot`](crate::snapshot::Snapshot) bytes
+    #[cfg_attr(feature = "std", error("invalid snapshot"))]
+    InvalidSnapshot,
+
+    /// [`Snapshot`](crate::snapshot::Snapshot) bytes of an unsupported format version
+    #[cfg_attr(feature = "std", error("unsupported snapshot version {0:?}"))]
+    UnsupportedSnapshotVersion(u16),
+
+    /// Malformed [`Movie`](crate::movie::Movie) bytes
+    #[cfg_attr(feature = "std", error("invalid movie"))]
+    InvalidMovie,
+
+    /// [`Movie`](crate::movie::Movie) bytes of an unsupported format version
+    #[cfg_attr(feature = "std", error("unsupported movie version {0:?}"))]
+    UnsupportedMovieVersion(u16),
+
+    /// Malformed [`trace`](crate::trace) bytes
+    #[cfg_attr(feature = "std", error("invalid trace"))]
+    InvalidTrace,
+
+    /// [`trace`](crate::trace) bytes of an unsupported format version
+    #[cfg_attr(feature = "std", error("unsupported trace version {0:?}"))]
+    UnsupportedTraceVersion(u16),
+
+    /// Write into a protected region of a [`MappedBus`](crate::memory::MappedBus)
+    #[cfg_attr(feature = "std", error("write to protected address {0:?}"))]
+    WriteProtected(u16),
+}
+
+/// Kind of [`Chip8Error::Parse`]
+#[derive(Debug, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    /// Character that does not start any token
+    UnexpectedCharacter(char),
+    /// Token that is not valid at this position
+    UnexpectedToken,
+    /// Line ended although more tokens were expected
+    UnexpectedEnd,
+    /// Mnemonic or directive that is not known
+    UnknownMnemonic(String),
+    /// Operands that do not match the mnemonic
+    InvalidOperands,
+    /// Label or constant that is not defined
+    UndefinedSymbol(String),
+    /// Label or constant that is defined more than once
+    DuplicateSymbol(String),
+    /// Constant that is defined by itself
+    RecursiveSymbol(String),
+    /// Value that does not fit into its operand
+    ValueOutOfRange(i64),
+    /// Division by zero in an expression
+    DivisionByZero,
+    /// Block that is closed without being opened or never closed
+    UnbalancedBlock,
+    /// XO-CHIP instruction although XO-CHIP is not enabled
+    RequiresXoChip,
+}
+
+impl core::fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            Self::UnexpectedToken => write!(f, "unexpected token"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of line"),
+            Self::UnknownMnemonic(name) => write!(f, "unknown mnemonic {:?}", name),
+            Self::InvalidOperands => write!(f, "invalid operands"),
+            Self::UndefinedSymbol(name) => write!(f, "undefined symbol {:?}", name),
+            Self::DuplicateSymbol(name) => write!(f, "duplicate symbol {:?}", name),
+            Self::RecursiveSymbol(name) => write!(f, "recursive symbol {:?}", name),
+            Self::ValueOutOfRange(value) => write!(f, "value {} out of range", value),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::UnbalancedBlock => write!(f, "unbalanced block"),
+            Self::RequiresXoChip => write!(f, "requires XO-CHIP"),
+        }
+    }
 }
 
 /// Result alias
@@ -195,6 +195,313 @@ pub(crate) const SPRITE_DATA_E: [u8; FONT_SPRITE_ROWS] =
 pub(crate) const SPRITE_DATA_F: [u8; FONT_SPRITE_ROWS] =
     [0b11110000, 0b10000000, 0b11110000, 0b10000000, 0b10000000];
 
+/// Number of rows for a big font sprite
+pub(crate) const BIG_FONT_SPRITE_ROWS: usize = 10;
+
+/// Big font sprite data for `0`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_0: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b11000011, 0b11000011, 0b11000011, 0b11000011, 0b11000011, 0b11000011,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `1`
+///
+/// ```plain
+/// ░░░▓▓░░░
+/// ░▓▓▓▓░░░
+/// ░▓▓▓▓░░░
+/// ░░░▓▓░░░
+/// ░░░▓▓░░░
+/// ░░░▓▓░░░
+/// ░░░▓▓░░░
+/// ░░░▓▓░░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_1: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b00011000, 0b01111000, 0b01111000, 0b00011000, 0b00011000, 0b00011000, 0b00011000, 0b00011000,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `2`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_2: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b00000011, 0b00000011, 0b11111111, 0b11111111, 0b11000000, 0b11000000,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `3`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_3: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b00000011, 0b00000011, 0b11111111, 0b11111111, 0b00000011, 0b00000011,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `4`
+///
+/// ```plain
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_4: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11000011, 0b11000011, 0b11000011, 0b11000011, 0b11111111, 0b11111111, 0b00000011, 0b00000011,
+    0b00000011, 0b00000011,
+];
+
+/// Big font sprite data for `5`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_5: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b11000000, 0b11000000, 0b11111111, 0b11111111, 0b00000011, 0b00000011,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `6`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_6: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b11000000, 0b11000000, 0b11111111, 0b11111111, 0b11000011, 0b11000011,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `7`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ░░░░░▓▓░
+/// ░░░░▓▓░░
+/// ░░░▓▓░░░
+/// ░░░▓▓░░░
+/// ░░░▓▓░░░
+/// ░░░▓▓░░░
+/// ```
+pub(crate) const BIG_SPRITE_DATA_7: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b00000011, 0b00000011, 0b00000110, 0b00001100, 0b00011000, 0b00011000,
+    0b00011000, 0b00011000,
+];
+
+/// Big font sprite data for `8`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_8: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b11000011, 0b11000011, 0b11111111, 0b11111111, 0b11000011, 0b11000011,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `9`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░░░░░▓▓
+/// ░░░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_9: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b11000011, 0b11000011, 0b11111111, 0b11111111, 0b00000011, 0b00000011,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `A`
+///
+/// ```plain
+/// ░▓▓▓▓▓▓░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_A: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b01111110, 0b11111111, 0b11000011, 0b11000011, 0b11000011, 0b11111111, 0b11111111, 0b11000011,
+    0b11000011, 0b11000011,
+];
+
+/// Big font sprite data for `B`
+///
+/// ```plain
+/// ▓▓▓▓▓▓░░
+/// ▓▓▓▓▓▓░░
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓░░
+/// ▓▓▓▓▓▓░░
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓░░
+/// ▓▓▓▓▓▓░░
+/// ```
+pub(crate) const BIG_SPRITE_DATA_B: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111100, 0b11111100, 0b11000011, 0b11000011, 0b11111100, 0b11111100, 0b11000011, 0b11000011,
+    0b11111100, 0b11111100,
+];
+
+/// Big font sprite data for `C`
+///
+/// ```plain
+/// ░░▓▓▓▓░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ░░▓▓▓▓░░
+/// ```
+pub(crate) const BIG_SPRITE_DATA_C: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b00111100, 0b11111111, 0b11000011, 0b11000000, 0b11000000, 0b11000000, 0b11000000, 0b11000011,
+    0b11111111, 0b00111100,
+];
+
+/// Big font sprite data for `D`
+///
+/// ```plain
+/// ▓▓▓▓▓▓░░
+/// ▓▓▓▓▓▓▓░
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓░░░░▓▓
+/// ▓▓▓▓▓▓▓░
+/// ▓▓▓▓▓▓░░
+/// ```
+pub(crate) const BIG_SPRITE_DATA_D: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111100, 0b11111110, 0b11000011, 0b11000011, 0b11000011, 0b11000011, 0b11000011, 0b11000011,
+    0b11111110, 0b11111100,
+];
+
+/// Big font sprite data for `E`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ```
+pub(crate) const BIG_SPRITE_DATA_E: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b11000000, 0b11000000, 0b11111111, 0b11111111, 0b11000000, 0b11000000,
+    0b11111111, 0b11111111,
+];
+
+/// Big font sprite data for `F`
+///
+/// ```plain
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓▓▓▓▓▓▓
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ▓▓░░░░░░
+/// ```
+pub(crate) const BIG_SPRITE_DATA_F: [u8; BIG_FONT_SPRITE_ROWS] = [
+    0b11111111, 0b11111111, 0b11000000, 0b11000000, 0b11111111, 0b11111111, 0b11000000, 0b11000000,
+    0b11000000, 0b11000000,
+];
+
 pub(crate) fn font_as_bytes_iter() -> impl Iterator<Item = &'static u8> {
     SPRITE_DATA_0
         .iter()
@@ -214,3 +521,23 @@ pub(crate) fn font_as_bytes_iter() -> impl Iterator<Item = &'static u8> {
         .chain(SPRITE_DATA_E.iter())
         .chain(SPRITE_DATA_F.iter())
 }
+
+pub(crate) fn big_font_as_bytes_iter() -> impl Iterator<Item = &'static u8> {
+    BIG_SPRITE_DATA_0
+        .iter()
+        .chain(BIG_SPRITE_DATA_1.iter())
+        .chain(BIG_SPRITE_DATA_2.iter())
+        .chain(BIG_SPRITE_DATA_3.iter())
+        .chain(BIG_SPRITE_DATA_4.iter())
+        .chain(BIG_SPRITE_DATA_5.iter())
+        .chain(BIG_SPRITE_DATA_6.iter())
+        .chain(BIG_SPRITE_DATA_7.iter())
+        .chain(BIG_SPRITE_DATA_8.iter())
+        .chain(BIG_SPRITE_DATA_9.iter())
+        .chain(BIG_SPRITE_DATA_A.iter())
+        .chain(BIG_SPRITE_DATA_B.iter())
+        .chain(BIG_SPRITE_DATA_C.iter())
+        .chain(BIG_SPRITE_DATA_D.iter())
+        .chain(BIG_SPRITE_DATA_E.iter())
+        .chain(BIG_SPRITE_DATA_F.iter())
+}
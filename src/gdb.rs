@@ -0,0 +1,685 @@
+//! GDB remote serial protocol
+//!
+//! A [`GdbServer`] lets GDB or any other client of the
+//! [remote serial protocol](https://sourceware.org/gdb/current/onlinedocs/gdb.html/Remote-Protocol.html)
+//! inspect and control a [`VM`](crate::vm::VM) through a [`Debugger`].
+//!
+//! The registers are, in this order and all little endian:
+//!
+//! | Number      | Name      | Bits | Content                                  |
+//! |-------------|-----------|------|------------------------------------------|
+//! | `0`..`15`   | `v0`..`vf`| 8    | General purpose registers                |
+//! | `16`        | `i`       | 16   | Address register                         |
+//! | `17`        | `pc`      | 16   | Program counter                          |
+//! | `18`        | `sp`      | 8    | Number of return addresses on the stack  |
+//! | `19`        | `dt`      | 8    | Delay timer                              |
+//! | `20`        | `st`      | 8    | Sound timer                              |
+//!
+//! `sp`, `dt` and `st` are read-only. The address space is the VM's [`Bus`], memory writes obey its write protection.
+//! Software and hardware breakpoints are [`Breakpoint`]s, write, read and access watchpoints are [`Watchpoint`]s.
+//!
+//! Continuing stops with `SIGINT` after [`Config::step_limit`](crate::debug::Config::step_limit)
+//! instructions or if the VM waits for a key press, since the client can not press keys.
+//!
+//! ```no_run
+//! use chip_8::debug::Debugger;
+//! use chip_8::gdb::GdbServer;
+//! use chip_8::random::XorShift;
+//! use chip_8::vm::VM;
+//!
+//! let mut vm = VM::new(XorShift::default(), |_, _| Ok(()));
+//! vm.load_rom(&std::fs::read("game.ch8")?)?;
+//!
+//! // connect with `target remote localhost:1234` in GDB
+//! let mut server = GdbServer::new(Debugger::new(vm));
+//! server.listen("127.0.0.1:1234")?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! # Features
+//!
+//! This is supported on **crate feature `std`** only, since it needs [`std::io`] and [`std::net`].
+
+use crate::beeper::Beeper;
+use crate::debug::{Access, Breakpoint, Debugger, StopReason, Watchpoint};
+use crate::display::Screen;
+use crate::errors::Chip8Error;
+use crate::instructions::{Addr, VRegister};
+use crate::memory::{Bus, Memory};
+use core::convert::TryFrom;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+
+/// Number of registers, see the [module documentation](self)
+const REGISTERS: usize = 21;
+
+/// Register number of `I`
+const I_REGISTER: usize = 16;
+
+/// Register number of the program counter
+const PC_REGISTER: usize = 17;
+
+/// Register number of the stack pointer
+const SP_REGISTER: usize = 18;
+
+/// Register number of the delay timer
+const DT_REGISTER: usize = 19;
+
+/// Register number of the sound timer
+const ST_REGISTER: usize = 20;
+
+/// Signal for breakpoints, watchpoints and steps
+const SIGTRAP: u8 = 5;
+
+/// Signal for interrupts
+const SIGINT: u8 = 2;
+
+/// Signal for unknown instructions and other errors of the VM
+const SIGILL: u8 = 4;
+
+/// Signal for memory accesses outside of memory
+const SIGSEGV: u8 = 11;
+
+/// Interrupt request sent by the client outside of packets
+const INTERRUPT: u8 = 0x03;
+
+/// Target description sent for `qXfer:features:read:target.xml`
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// What to do after handling a packet
+#[derive(Debug, PartialEq, Eq)]
+enum Response {
+    /// Send the reply
+    Reply(String),
+    /// Send the reply, then close the connection
+    Close(Option<String>),
+}
+
+/// Server of the GDB remote serial protocol for a [`Debugger`]
+pub struct GdbServer<R: crate::random::RandomSource, S: Screen, B: Beeper, M: Bus = Memory> {
+    debugger: Debugger<R, S, B, M>,
+    no_ack: bool,
+}
+
+impl<R, S, B, M> GdbServer<R, S, B, M>
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    /// Creates a new instance controlling the `debugger`
+    #[must_use]
+    pub fn new(debugger: Debugger<R, S, B, M>) -> Self {
+        Self {
+            debugger,
+            no_ack: false,
+        }
+    }
+
+    /// Returns the controlled debugger
+    #[must_use]
+    pub fn debugger(&self) -> &Debugger<R, S, B, M> {
+        &self.debugger
+    }
+
+    /// Returns the controlled debugger, dropping the server
+    #[must_use]
+    pub fn into_debugger(self) -> Debugger<R, S, B, M> {
+        self.debugger
+    }
+
+    /// Accepts a single client on `addr` and serves it until it detaches or disconnects
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from binding, accepting or the connection.
+    pub fn listen<A: ToSocketAddrs>(&mut self, addr: A) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        self.serve(stream)
+    }
+
+    /// Serves a client connected through `stream` until it detaches or disconnects
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from reading or writing the `stream`.
+    pub fn serve<T: Read + Write>(&mut self, mut stream: T) -> io::Result<()> {
+        self.no_ack = false;
+        while let Some(packet) = self.read_packet(&mut stream)? {
+            let response = match packet {
+                Some(packet) => self.handle(&packet),
+                None => Response::Reply(signal(SIGINT)),
+            };
+            match response {
+                Response::Reply(reply) => write_packet(&mut stream, &reply)?,
+                Response::Close(reply) => {
+                    if let Some(reply) = reply {
+                        write_packet(&mut stream, &reply)?;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the next packet, `Some(None)` for an interrupt and `None` for the end of the `stream`
+    fn read_packet<T: Read + Write>(&self, stream: &mut T) -> io::Result<Option<Option<String>>> {
+        loop {
+            let byte = match read_byte(stream)? {
+                Some(byte) => byte,
+                None => return Ok(None),
+            };
+            match byte {
+                INTERRUPT => return Ok(Some(None)),
+                b'$' => {}
+                // acknowledgements and anything else between packets
+                _ => continue,
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match read_byte(stream)? {
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                    None => return Ok(None),
+                }
+            }
+            let mut checksum = [0; 2];
+            stream.read_exact(&mut checksum)?;
+
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok())
+                == Some(checksum_of(&data));
+            if !self.no_ack {
+                stream.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(Some(String::from_utf8_lossy(&data).into_owned())));
+            }
+        }
+    }
+
+    /// Handles a single `packet` without its framing
+    fn handle(&mut self, packet: &str) -> Response {
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+        let reply = match command {
+            "?" => Some(signal(SIGTRAP)),
+            "g" => self.read_registers(),
+            "G" => self.write_registers(args),
+            "p" => self.read_register(args),
+            "P" => self.write_register(args),
+            "m" => self.read_memory(args),
+            "M" => self.write_memory(args),
+            "c" => self.resume(Debugger::run),
+            "s" => self.resume(Debugger::step_into),
+            "Z" => self.insert_point(args),
+            "z" => self.remove_point(args),
+            "H" => Some(ok()),
+            "D" => return Response::Close(Some(ok())),
+            "k" => return Response::Close(None),
+            "q" | "Q" => self.query(packet),
+            _ => Some(String::new()),
+        };
+        Response::Reply(reply.unwrap_or_else(|| "E01".into()))
+    }
+
+    fn query(&mut self, packet: &str) -> Option<String> {
+        if packet.starts_with("qSupported") {
+            return Some("PacketSize=4000;qXfer:features:read+;QStartNoAckMode+".into());
+        }
+        if let Some(args) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let (offset, len) = args.split_once(',')?;
+            let offset = usize::from_str_radix(offset, 16)
+                .ok()?
+                .min(TARGET_XML.len());
+            let len = usize::from_str_radix(len, 16).ok()?;
+            let chunk = &TARGET_XML[offset..offset.saturating_add(len).min(TARGET_XML.len())];
+            let more = if offset + chunk.len() < TARGET_XML.len() {
+                'm'
+            } else {
+                'l'
+            };
+            return Some(format!("{}{}", more, chunk));
+        }
+        let reply = match packet {
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                ok()
+            }
+            "qAttached" => "1".into(),
+            "qC" => "QC1".into(),
+            "qfThreadInfo" => "m1".into(),
+            "qsThreadInfo" => "l".into(),
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    fn register_value(&self, register: usize) -> Option<u16> {
+        let vm = self.debugger.vm();
+        let value = match register {
+            0..=15 => vm
+                .register(VRegister::try_from(register as u8).ok()?)
+                .into(),
+            I_REGISTER => vm.i(),
+            PC_REGISTER => vm.pc(),
+            SP_REGISTER => vm.stack().len() as u16,
+            DT_REGISTER => vm.delay_timer().into(),
+            ST_REGISTER => vm.sound_timer().into(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn read_registers(&self) -> Option<String> {
+        let mut reply = String::new();
+        for register in 0..REGISTERS {
+            let value = self.register_value(register)?;
+            push_hex(&mut reply, &value.to_le_bytes()[..register_size(register)]);
+        }
+        Some(reply)
+    }
+
+    fn read_register(&self, args: &str) -> Option<String> {
+        let register = usize::from_str_radix(args, 16).ok()?;
+        let value = self.register_value(register)?;
+        let mut reply = String::new();
+        push_hex(&mut reply, &value.to_le_bytes()[..register_size(register)]);
+        Some(reply)
+    }
+
+    /// Sets a writable `register`, ignores read-only registers if `value` does not change them
+    fn set_register_value(&mut self, register: usize, value: u16) -> Option<()> {
+        let vm = self.debugger.vm_mut();
+        match register {
+            0..=15 => vm.set_register(VRegister::try_from(register as u8).ok()?, value as u8),
+            I_REGISTER => vm.set_i(value),
+            PC_REGISTER => vm.set_pc(value),
+            SP_REGISTER..=ST_REGISTER if self.register_value(register)? == value => {}
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn write_registers(&mut self, args: &str) -> Option<String> {
+        let bytes = parse_hex(args)?;
+        let mut values = Vec::with_capacity(REGISTERS);
+        let mut offset = 0;
+        for register in 0..REGISTERS {
+            let size = register_size(register);
+            values.push(le_value(bytes.get(offset..offset + size)?));
+            offset += size;
+        }
+        for (register, value) in values.into_iter().enumerate() {
+            self.set_register_value(register, value)?;
+        }
+        Some(ok())
+    }
+
+    fn write_register(&mut self, args: &str) -> Option<String> {
+        let (register, value) = args.split_once('=')?;
+        let register = usize::from_str_radix(register, 16).ok()?;
+        let bytes = parse_hex(value)?;
+        if bytes.len() != register_size(register) {
+            return None;
+        }
+        self.set_register_value(register, le_value(&bytes))?;
+        Some(ok())
+    }
+
+    /// Returns the addresses of `addr,len`, which must be within memory
+    ///
+    /// The range is of `u32`, since it ends at `0x10000` for the last byte of a 64 KiB memory.
+    fn memory_range(&self, args: &str) -> Option<core::ops::Range<u32>> {
+        let (addr, len) = args.split_once(',')?;
+        let start = u16::from_str_radix(addr, 16).ok()?;
+        let len = u16::from_str_radix(len, 16).ok()?;
+        let memory = self.debugger.vm().memory();
+        if len > 0 {
+            memory.checked_addr(start.checked_add(len - 1)?).ok()?;
+        }
+        Some(u32::from(start)..u32::from(start) + u32::from(len))
+    }
+
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let memory = self.debugger.vm().memory();
+        let bytes: Vec<u8> = self
+            .memory_range(args)?
+            .map(|addr| memory.read(Addr::long(addr as u16)))
+            .collect();
+        let mut reply = String::new();
+        push_hex(&mut reply, &bytes);
+        Some(reply)
+    }
+
+    fn write_memory(&mut self, args: &str) -> Option<String> {
+        let (range, data) = args.split_once(':')?;
+        let range = self.memory_range(range)?;
+        let bytes = parse_hex(data)?;
+        if bytes.len() != range.len() {
+            return None;
+        }
+        let memory = self.debugger.vm_mut().memory_mut();
+        for (addr, byte) in range.zip(bytes) {
+            memory.write(Addr::long(addr as u16), byte).ok()?;
+        }
+        Some(ok())
+    }
+
+    /// Returns the kind, address and length of `Z` and `z` packets
+    fn parse_point(args: &str) -> Option<(u8, Addr, u16)> {
+        let mut parts = args.splitn(3, ',');
+        let kind = parts.next()?.parse().ok()?;
+        let addr = Addr::long(u16::from_str_radix(parts.next()?, 16).ok()?);
+        let len = u16::from_str_radix(parts.next()?.split(';').next()?, 16).ok()?;
+        Some((kind, addr, len))
+    }
+
+    fn watchpoint(kind: u8, addr: Addr, len: u16) -> Option<Watchpoint> {
+        let access = match kind {
+            2 => Access::Write,
+            3 => Access::Read,
+            4 => Access::ReadWrite,
+            _ => return None,
+        };
+        Some(Watchpoint { addr, len, access })
+    }
+
+    fn insert_point(&mut self, args: &str) -> Option<String> {
+        let (kind, addr, len) = Self::parse_point(args)?;
+        match kind {
+            0 | 1 => self.debugger.add_breakpoint(Breakpoint::new(addr)),
+            _ => match Self::watchpoint(kind, addr, len) {
+                Some(watchpoint) => self.debugger.add_watchpoint(watchpoint),
+                None => return Some(String::new()),
+            },
+        }
+        Some(ok())
+    }
+
+    fn remove_point(&mut self, args: &str) -> Option<String> {
+        let (kind, addr, len) = Self::parse_point(args)?;
+        match kind {
+            0 | 1 => self.debugger.remove_breakpoints(addr),
+            _ => match Self::watchpoint(kind, addr, len) {
+                Some(watchpoint) => self.debugger.remove_watchpoint(&watchpoint),
+                None => return Some(String::new()),
+            },
+        }
+        Some(ok())
+    }
+
+    /// Runs the debugger with `command` and returns the stop reply
+    fn resume(
+        &mut self,
+        command: fn(&mut Debugger<R, S, B, M>) -> crate::errors::Result<StopReason>,
+    ) -> Option<String> {
+        let reply = match command(&mut self.debugger) {
+            Ok(StopReason::Step)
+            | Ok(StopReason::Breakpoint(_))
+            | Ok(StopReason::Instruction(_)) => signal(SIGTRAP),
+            Ok(StopReason::Watchpoint { addr, access }) => {
+                let kind = match access {
+                    Access::Read => "rwatch",
+                    Access::Write => "watch",
+                    Access::ReadWrite => "awatch",
+                };
+                format!("T{:02x}{}:{:x};", SIGTRAP, kind, u16::from(addr))
+            }
+            Ok(StopReason::WaitingForKey) | Ok(StopReason::StepLimit) => signal(SIGINT),
+            Ok(StopReason::Halted) => "W00".into(),
+            Err(Chip8Error::OutOfRange(_)) => signal(SIGSEGV),
+            Err(_) => signal(SIGILL),
+        };
+        Some(reply)
+    }
+}
+
+fn ok() -> String {
+    "OK".into()
+}
+
+fn signal(signal: u8) -> String {
+    format!("S{:02x}", signal)
+}
+
+/// Returns the size of `register` in bytes
+fn register_size(register: usize) -> usize {
+    match register {
+        I_REGISTER | PC_REGISTER => 2,
+        _ => 1,
+    }
+}
+
+fn le_value(bytes: &[u8]) -> u16 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, byte| value << 8 | u16::from(*byte))
+}
+
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        write!(out, "{:02x}", byte).expect("writing to a String does not fail");
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn read_byte<T: Read>(stream: &mut T) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match stream.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+/// Writes `reply` as packet, escaping the characters with special meaning
+fn write_packet<T: Write>(stream: &mut T, reply: &str) -> io::Result<()> {
+    let mut data = Vec::with_capacity(reply.len());
+    for byte in reply.bytes() {
+        match byte {
+            b'#' | b'$' | b'}' | b'*' => data.extend_from_slice(&[b'}', byte ^ 0x20]),
+            _ => data.push(byte),
+        }
+    }
+    let mut packet = Vec::with_capacity(data.len() + 4);
+    packet.push(b'$');
+    packet.extend_from_slice(&data);
+    packet.extend_from_slice(format!("#{:02x}", checksum_of(&data)).as_bytes());
+    stream.write_all(&packet)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Display;
+    use crate::memory::{MappedBus, WriteProtection};
+    use crate::random::XorShift;
+    use crate::vm::VM;
+    use std::io::BufReader;
+    use std::net::TcpStream;
+
+    type TestServer<M = Memory> = GdbServer<XorShift, Display, crate::beeper::NoBeeper, M>;
+
+    fn test_server() -> TestServer {
+        let rng = XorShift::default();
+        let mut vm = VM::new(rng, |_, _| Ok(()));
+        // LD V0, 0x12; LD I, 0x300; LD [I], V0; JP 0x206
+        vm.load_rom(&[0x60, 0x12, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06])
+            .unwrap();
+        GdbServer::new(Debugger::new(vm))
+    }
+
+    fn reply<M: Bus>(server: &mut TestServer<M>, packet: &str) -> String {
+        match server.handle(packet) {
+            Response::Reply(reply) => reply,
+            Response::Close(reply) => panic!("closed with {:?}", reply),
+        }
+    }
+
+    #[test]
+    fn gdb_registers() {
+        let mut server = test_server();
+
+        assert_eq!(reply(&mut server, "s"), "S05");
+        assert_eq!(
+            reply(&mut server, "g"),
+            "1200000000000000000000000000000000000202000000"
+        );
+        assert_eq!(reply(&mut server, "P10=3412"), "OK");
+        assert_eq!(reply(&mut server, "p10"), "3412");
+        assert_eq!(reply(&mut server, "p11"), "0202");
+        assert_eq!(reply(&mut server, "P12=01"), "E01");
+        assert_eq!(reply(&mut server, "p15"), "E01");
+    }
+
+    #[test]
+    fn gdb_memory() {
+        let mut server = test_server();
+
+        assert_eq!(reply(&mut server, "m200,4"), "6012a300");
+        assert_eq!(reply(&mut server, "M300,2:abcd"), "OK");
+        assert_eq!(reply(&mut server, "m2ff,3"), "00abcd");
+        assert_eq!(reply(&mut server, "mfff,2"), "E01");
+    }
+
+    #[test]
+    fn gdb_memory_end_of_xo_chip() {
+        let config = crate::vm::Config {
+            memory_size: Memory::XO_CHIP_SIZE,
+            ..crate::vm::Config::default()
+        };
+        let vm = VM::with_config(XorShift::default(), |_, _| Ok(()), config);
+        let mut server = GdbServer::new(Debugger::new(vm));
+
+        assert_eq!(reply(&mut server, "Mffff,1:ab"), "OK");
+        assert_eq!(reply(&mut server, "mffff,1"), "ab");
+        assert_eq!(reply(&mut server, "mfffe,2"), "00ab");
+        assert_eq!(reply(&mut server, "mffff,2"), "E01");
+    }
+
+    #[test]
+    fn gdb_memory_protected() {
+        let mut bus = MappedBus::new(Memory::new());
+        bus.protect(0x200..=0x2FF, WriteProtection::Error);
+        let vm = VM::with_bus(
+            XorShift::default(),
+            |_, _| Ok(()),
+            crate::vm::Config::default(),
+            Display::new(crate::display::EdgeMode::Wrap),
+            crate::beeper::NoBeeper,
+            bus,
+        );
+        let mut server = GdbServer::new(Debugger::new(vm));
+
+        assert_eq!(reply(&mut server, "M300,2:abcd"), "OK");
+        assert_eq!(reply(&mut server, "m300,2"), "abcd");
+        assert_eq!(reply(&mut server, "M2fe,2:1234"), "E01");
+        assert_eq!(reply(&mut server, "m2fe,2"), "0000");
+    }
+
+    #[test]
+    fn gdb_breakpoints_and_watchpoints() {
+        let mut server = test_server();
+
+        assert_eq!(reply(&mut server, "Z0,202,2"), "OK");
+        assert_eq!(reply(&mut server, "c"), "S05");
+        assert_eq!(reply(&mut server, "p11"), "0202");
+        assert_eq!(reply(&mut server, "z0,202,2"), "OK");
+
+        assert_eq!(reply(&mut server, "Z2,300,1"), "OK");
+        assert_eq!(reply(&mut server, "c"), "T05watch:300;");
+        assert_eq!(reply(&mut server, "z2,300,1"), "OK");
+    }
+
+    #[test]
+    fn gdb_loopback_client() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            let mut server = test_server();
+            server.serve(stream)?;
+            Ok::<_, io::Error>(server.into_debugger().vm().pc())
+        });
+
+        let stream = TcpStream::connect(addr)?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let mut exchange = |packet: &str| -> io::Result<String> {
+            write!(writer, "${}#{:02x}", packet, checksum_of(packet.as_bytes()))?;
+            let mut ack = [0];
+            reader.read_exact(&mut ack)?;
+            assert_eq!(&ack, b"+");
+            let mut packet = Vec::new();
+            loop {
+                let byte = read_byte(&mut reader)?.expect("reply");
+                if byte == b'#' {
+                    break;
+                }
+                packet.push(byte);
+            }
+            let mut checksum = [0; 2];
+            reader.read_exact(&mut checksum)?;
+            writer.write_all(b"+")?;
+            Ok(String::from_utf8_lossy(&packet[1..]).into_owned())
+        };
+
+        assert_eq!(exchange("?")?, "S05");
+        assert_eq!(exchange("s")?, "S05");
+        assert_eq!(exchange("p0")?, "12");
+        assert!(exchange("qXfer:features:read:target.xml:0,20")?.starts_with("m<?xml"));
+        assert!(exchange("qXfer:features:read:target.xml:10,ffffffffffffffff")?.starts_with('l'));
+        assert_eq!(exchange("D")?, "OK");
+
+        assert_eq!(server.join().expect("server thread")?, 0x202);
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 //! Machine language and byte code instructions
 
 use crate::errors::Chip8Error;
+use alloc::vec::Vec;
 use core::convert::TryFrom;
 
 /// General purpose register
@@ -68,6 +69,12 @@ impl TryFrom<u8> for VRegister {
     }
 }
 
+impl core::fmt::Display for VRegister {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "V{:X}", *self as usize)
+    }
+}
+
 struct VRegisterRangeIter {
     next: Option<VRegister>,
     range: Option<VRegister>,
@@ -79,9 +86,7 @@ impl Iterator for VRegisterRangeIter {
     fn next(&mut self) -> Option<Self::Item> {
         use VRegister::*;
 
-        if self.range.is_none() {
-            return None;
-        }
+        self.range?;
 
         let next = self.next;
         self.next = match self.next {
@@ -120,6 +125,15 @@ impl VRegister {
             range: Some(upper_bound),
         }
     }
+
+    /// Returns an `Iterator` from `from` up or down to including `to`
+    pub fn iter_range(from: VRegister, to: VRegister) -> impl Iterator<Item = VRegister> {
+        let (from, to) = (from as u8, to as u8);
+        (0..=from.abs_diff(to)).map(move |offs| {
+            let idx = if from <= to { from + offs } else { from - offs };
+            Self::try_from(idx).expect("index between two registers is valid")
+        })
+    }
 }
 
 /// First register in an instruction
@@ -133,7 +147,8 @@ pub type Byte = u8;
 
 /// Absolute memory address
 ///
-/// Valid addresses are within `0x0` .. `0xFFF`.
+/// Valid addresses in instructions are within `0x0` .. `0xFFF`.
+/// Only XO-CHIP can address up to `0xFFFF`, see [`Addr::long`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Addr(u16);
 
@@ -150,6 +165,22 @@ impl Addr {
             Ok(Self(bits & 0x0FFF))
         }
     }
+
+    /// Creates a new instance from all 16 `bits`, as used by XO-CHIP
+    pub const fn long(bits: u16) -> Self {
+        Self(bits)
+    }
+}
+
+impl core::fmt::Display for Addr {
+    /// Formats as hex with three digits, or four digits for long XO-CHIP addresses
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if self.0 > 0x0FFF {
+            write!(f, "{:#06X}", self.0)
+        } else {
+            write!(f, "{:#05X}", self.0)
+        }
+    }
 }
 
 impl From<u16> for Addr {
@@ -182,6 +213,12 @@ impl From<u8> for Nibble {
     }
 }
 
+impl core::fmt::Display for Nibble {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl From<Nibble> for usize {
     fn from(nibble: Nibble) -> Self {
         nibble.0 as usize
@@ -286,6 +323,8 @@ pub enum Instruction {
     /// Read `n` bytes of memory from address `I`, draw it at `Vx` and `Vy` screen coordinates and set `VF` for erased pixels
     ///
     /// `Dxyn` - `DRW Vx, Vy, nibble`
+    ///
+    /// For `n` = `0` SUPER-CHIP draws a 16 * 16 sprite from 32 bytes.
     Draw(Vx, Vy, Nibble),
     /// Skip next instruction if key `Vx` is pressed
     ///
@@ -331,20 +370,113 @@ pub enum Instruction {
     ///
     /// `Fx65` - `LD Vx, [I]`
     LoadRegistersMemory(Vx),
+    /// Scrolls the display down by `n` pixels
+    ///
+    /// `00Cn` - `SCD nibble` (SUPER-CHIP)
+    ScrollDown(Nibble),
+    /// Scrolls the display right by 4 pixels
+    ///
+    /// `00FB` - `SCR` (SUPER-CHIP)
+    ScrollRight,
+    /// Scrolls the display left by 4 pixels
+    ///
+    /// `00FC` - `SCL` (SUPER-CHIP)
+    ScrollLeft,
+    /// Exits the interpreter
+    ///
+    /// `00FD` - `EXIT` (SUPER-CHIP)
+    Exit,
+    /// Switches the display to low resolution
+    ///
+    /// `00FE` - `LOW` (SUPER-CHIP)
+    LowResolution,
+    /// Switches the display to high resolution
+    ///
+    /// `00FF` - `HIGH` (SUPER-CHIP)
+    HighResolution,
+    /// Set `I` to the address of the big sprite `Vx`
+    ///
+    /// `Fx30` - `LD HF, Vx` (SUPER-CHIP)
+    LoadBigSprite(Vx),
+    /// Store registers `V0`..`Vx` in RPL user flags
+    ///
+    /// `Fx75` - `LD R, Vx` (SUPER-CHIP)
+    StoreFlags(Vx),
+    /// Read registers `V0`..`Vx` from RPL user flags
+    ///
+    /// `Fx85` - `LD Vx, R` (SUPER-CHIP)
+    LoadFlags(Vx),
+    /// Store registers `Vx`..`Vy` in memory at `I`, without changing `I`
+    ///
+    /// `5xy2` - `SAVE Vx, Vy` (XO-CHIP)
+    SaveRange(Vx, Vy),
+    /// Read registers `Vx`..`Vy` from memory at `I`, without changing `I`
+    ///
+    /// `5xy3` - `LOAD Vx, Vy` (XO-CHIP)
+    LoadRange(Vx, Vy),
+    /// Loads the 16-bit `Addr` into register `I`
+    ///
+    /// `F000 nnnn` - `LD I, long addr` (XO-CHIP)
+    ///
+    /// This is the only instruction with four bytes.
+    LoadILong(Addr),
+    /// Selects the bitplanes `n` for drawing, clearing and scrolling
+    ///
+    /// `Fn01` - `PLANE nibble` (XO-CHIP)
+    SelectPlanes(Nibble),
+    /// Loads 16 bytes of memory from address `I` into the audio pattern buffer
+    ///
+    /// `F002` - `AUDIO` (XO-CHIP)
+    LoadAudio,
+    /// Sets the audio pitch to `Vx`
+    ///
+    /// `Fx3A` - `PITCH Vx` (XO-CHIP)
+    Pitch(Vx),
 }
 
 impl Instruction {
-    /// Decodes raw `bits` into a valid `Instruction`
+    /// Decodes the first word `bits` and the following word `next` into a valid `Instruction`
+    ///
+    /// Unlike [`decode`](Self::decode) this also supports the four byte `LD I, long addr`.
+    /// `next` is ignored for all other instructions.
     ///
     /// # Errors
     ///
     /// Will return [`Chip8Error::UnknownInstruction`](crate::errors::Chip8Error::UnknownInstruction)
     /// if given `bits` don't match any known instruction.
+    pub fn decode_long(bits: u16, next: u16) -> crate::errors::Result<Self> {
+        match Self::decode(bits) {
+            Err(Chip8Error::IncompleteInstruction(_)) => Ok(Self::LoadILong(Addr::long(next))),
+            other => other,
+        }
+    }
+
+    /// Returns whether `bits` are the first word of a four byte instruction
+    pub const fn is_long(bits: u16) -> bool {
+        bits == 0xF000
+    }
+
+    /// Returns the size of the encoded instruction in bytes, i.e. `2` or `4`
+    pub const fn size(&self) -> u16 {
+        match self {
+            Self::LoadILong(_) => 4,
+            _ => 2,
+        }
+    }
+
+    /// Decodes raw `bits` into a valid `Instruction`
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::UnknownInstruction`](crate::errors::Chip8Error::UnknownInstruction)
+    /// if given `bits` don't match any known instruction, or
+    /// [`Chip8Error::IncompleteInstruction`](crate::errors::Chip8Error::IncompleteInstruction)
+    /// for the first word of a four byte instruction, see [`decode_long`](Self::decode_long).
     pub fn decode(bits: u16) -> crate::errors::Result<Self> {
         use self::Instruction::*;
 
         // lowest 12 bits
-        let nnn = (bits & 0x0FFF) as u16;
+        let nnn = bits & 0x0FFF;
         // highest 4 bits of high byte
         let high_nibble = ((bits & 0xF000) >> 12) as u8;
         // lowest 4 bits of low byte
@@ -360,6 +492,12 @@ impl Instruction {
             0x0 => match nnn {
                 0x0E0 => Ok(Clear),
                 0x0EE => Ok(Return),
+                0x0C0..=0x0CF => Ok(ScrollDown(Nibble::from(low_nibble))),
+                0x0FB => Ok(ScrollRight),
+                0x0FC => Ok(ScrollLeft),
+                0x0FD => Ok(Exit),
+                0x0FE => Ok(LowResolution),
+                0x0FF => Ok(HighResolution),
                 _ => Ok(Sys(nnn.into())),
             },
             0x1 => Ok(Jump(nnn.into())),
@@ -368,6 +506,8 @@ impl Instruction {
             0x4 => Ok(SkipNotEqualOperand(VRegister::try_from(x)?, kk)),
             0x5 => match low_nibble {
                 0x0 => Ok(SkipEqual(VRegister::try_from(x)?, VRegister::try_from(y)?)),
+                0x2 => Ok(SaveRange(VRegister::try_from(x)?, VRegister::try_from(y)?)),
+                0x3 => Ok(LoadRange(VRegister::try_from(x)?, VRegister::try_from(y)?)),
                 _ => Err(Chip8Error::UnknownInstruction(bits)),
             },
             0x6 => Ok(LoadOperand(VRegister::try_from(x)?, kk)),
@@ -406,15 +546,22 @@ impl Instruction {
                 _ => Err(Chip8Error::UnknownInstruction(bits)),
             },
             0xF => match kk {
+                0x00 if x == 0x0 => Err(Chip8Error::IncompleteInstruction(bits)),
+                0x01 => Ok(SelectPlanes(Nibble::from(x))),
+                0x02 if x == 0x0 => Ok(LoadAudio),
                 0x07 => Ok(LoadRegisterDelayTimer(VRegister::try_from(x)?)),
                 0x0A => Ok(LoadKey(VRegister::try_from(x)?)),
                 0x15 => Ok(LoadDelayTimerRegister(VRegister::try_from(x)?)),
                 0x18 => Ok(LoadSoundTimerRegister(VRegister::try_from(x)?)),
                 0x1E => Ok(AddI(VRegister::try_from(x)?)),
                 0x29 => Ok(LoadSprite(VRegister::try_from(x)?)),
+                0x30 => Ok(LoadBigSprite(VRegister::try_from(x)?)),
+                0x3A => Ok(Pitch(VRegister::try_from(x)?)),
                 0x33 => Ok(LoadBinaryCodedDecimal(VRegister::try_from(x)?)),
                 0x55 => Ok(LoadMemoryRegisters(VRegister::try_from(x)?)),
                 0x65 => Ok(LoadRegistersMemory(VRegister::try_from(x)?)),
+                0x75 => Ok(StoreFlags(VRegister::try_from(x)?)),
+                0x85 => Ok(LoadFlags(VRegister::try_from(x)?)),
                 _ => Err(Chip8Error::UnknownInstruction(bits)),
             },
 
@@ -422,7 +569,21 @@ impl Instruction {
         }
     }
 
+    /// Encodes a valid `Instruction` into raw bytes, i.e. `2` or `4` bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size().into());
+        bytes.extend_from_slice(&self.encode().to_be_bytes());
+        if let Self::LoadILong(addr) = self {
+            bytes.extend_from_slice(&addr.0.to_be_bytes());
+        }
+        bytes
+    }
+
     /// Encodes a valid `Instruction` into raw bits
+    ///
+    /// For the four byte `LD I, long addr` this is only the first word, see [`to_bytes`](Self::to_bytes).
+    // `| 0x0` keeps the lowest nibble visible for all opcodes of a group
+    #[allow(clippy::identity_op)]
     pub fn encode(&self) -> u16 {
         fn x(reg: VRegister) -> u16 {
             (reg as u16) << 8
@@ -437,7 +598,7 @@ impl Instruction {
         }
 
         fn nnn(addr: Addr) -> u16 {
-            addr.0 as u16
+            addr.0
         }
 
         fn n(nibble: Nibble) -> u16 {
@@ -480,6 +641,79 @@ impl Instruction {
             Self::LoadBinaryCodedDecimal(vx) => 0xF000 | x(vx) | 0x33,
             Self::LoadMemoryRegisters(vx) => 0xF000 | x(vx) | 0x55,
             Self::LoadRegistersMemory(vx) => 0xF000 | x(vx) | 0x65,
+            Self::ScrollDown(nibble) => 0x00C0 | n(nibble),
+            Self::ScrollRight => 0x00FB,
+            Self::ScrollLeft => 0x00FC,
+            Self::Exit => 0x00FD,
+            Self::LowResolution => 0x00FE,
+            Self::HighResolution => 0x00FF,
+            Self::LoadBigSprite(vx) => 0xF000 | x(vx) | 0x30,
+            Self::StoreFlags(vx) => 0xF000 | x(vx) | 0x75,
+            Self::LoadFlags(vx) => 0xF000 | x(vx) | 0x85,
+            Self::SaveRange(vx, vy) => 0x5000 | x(vx) | y(vy) | 0x2,
+            Self::LoadRange(vx, vy) => 0x5000 | x(vx) | y(vy) | 0x3,
+            Self::LoadILong(_) => 0xF000,
+            Self::SelectPlanes(nibble) => 0xF000 | (n(nibble) << 8) | 0x01,
+            Self::LoadAudio => 0xF002,
+            Self::Pitch(vx) => 0xF000 | x(vx) | 0x3A,
+        }
+    }
+}
+
+impl core::fmt::Display for Instruction {
+    /// Formats in the assembly syntax of Cowgod's Chip-8 Technical Reference
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match *self {
+            Self::Sys(addr) => write!(f, "SYS {}", addr),
+            Self::Clear => write!(f, "CLS"),
+            Self::Return => write!(f, "RET"),
+            Self::Jump(addr) => write!(f, "JP {}", addr),
+            Self::Call(addr) => write!(f, "CALL {}", addr),
+            Self::SkipEqualOperand(vx, byte) => write!(f, "SE {}, {:#04X}", vx, byte),
+            Self::SkipNotEqualOperand(vx, byte) => write!(f, "SNE {}, {:#04X}", vx, byte),
+            Self::SkipEqual(vx, vy) => write!(f, "SE {}, {}", vx, vy),
+            Self::LoadOperand(vx, byte) => write!(f, "LD {}, {:#04X}", vx, byte),
+            Self::AddOperand(vx, byte) => write!(f, "ADD {}, {:#04X}", vx, byte),
+            Self::Load(vx, vy) => write!(f, "LD {}, {}", vx, vy),
+            Self::Or(vx, vy) => write!(f, "OR {}, {}", vx, vy),
+            Self::And(vx, vy) => write!(f, "AND {}, {}", vx, vy),
+            Self::XOr(vx, vy) => write!(f, "XOR {}, {}", vx, vy),
+            Self::Add(vx, vy) => write!(f, "ADD {}, {}", vx, vy),
+            Self::Sub(vx, vy) => write!(f, "SUB {}, {}", vx, vy),
+            Self::ShiftRight(vx, vy) => write!(f, "SHR {}, {}", vx, vy),
+            Self::SubNegated(vx, vy) => write!(f, "SUBN {}, {}", vx, vy),
+            Self::ShiftLeft(vx, vy) => write!(f, "SHL {}, {}", vx, vy),
+            Self::SkipNotEqual(vx, vy) => write!(f, "SNE {}, {}", vx, vy),
+            Self::LoadI(addr) => write!(f, "LD I, {}", addr),
+            Self::LongJump(addr) => write!(f, "JP V0, {}", addr),
+            Self::Random(vx, byte) => write!(f, "RND {}, {:#04X}", vx, byte),
+            Self::Draw(vx, vy, nibble) => write!(f, "DRW {}, {}, {}", vx, vy, nibble),
+            Self::SkipKeyPressed(vx) => write!(f, "SKP {}", vx),
+            Self::SkipKeyNotPressed(vx) => write!(f, "SKNP {}", vx),
+            Self::LoadRegisterDelayTimer(vx) => write!(f, "LD {}, DT", vx),
+            Self::LoadKey(vx) => write!(f, "LD {}, K", vx),
+            Self::LoadDelayTimerRegister(vx) => write!(f, "LD DT, {}", vx),
+            Self::LoadSoundTimerRegister(vx) => write!(f, "LD ST, {}", vx),
+            Self::AddI(vx) => write!(f, "ADD I, {}", vx),
+            Self::LoadSprite(vx) => write!(f, "LD F, {}", vx),
+            Self::LoadBinaryCodedDecimal(vx) => write!(f, "LD B, {}", vx),
+            Self::LoadMemoryRegisters(vx) => write!(f, "LD [I], {}", vx),
+            Self::LoadRegistersMemory(vx) => write!(f, "LD {}, [I]", vx),
+            Self::ScrollDown(nibble) => write!(f, "SCD {}", nibble),
+            Self::ScrollRight => write!(f, "SCR"),
+            Self::ScrollLeft => write!(f, "SCL"),
+            Self::Exit => write!(f, "EXIT"),
+            Self::LowResolution => write!(f, "LOW"),
+            Self::HighResolution => write!(f, "HIGH"),
+            Self::LoadBigSprite(vx) => write!(f, "LD HF, {}", vx),
+            Self::StoreFlags(vx) => write!(f, "LD R, {}", vx),
+            Self::LoadFlags(vx) => write!(f, "LD {}, R", vx),
+            Self::SaveRange(vx, vy) => write!(f, "SAVE {}, {}", vx, vy),
+            Self::LoadRange(vx, vy) => write!(f, "LOAD {}, {}", vx, vy),
+            Self::LoadILong(addr) => write!(f, "LD I, long {}", addr),
+            Self::SelectPlanes(nibble) => write!(f, "PLANE {}", nibble),
+            Self::LoadAudio => write!(f, "AUDIO"),
+            Self::Pitch(vx) => write!(f, "PITCH {}", vx),
         }
     }
 }
@@ -775,30 +1009,182 @@ mod tests {
         );
     }
 
+    #[test]
+    fn decode_scrolldown() {
+        assert_eq!(
+            Instruction::decode(0x00C4),
+            Ok(Instruction::ScrollDown(0x4.into()))
+        );
+    }
+
+    #[test]
+    fn decode_scrollright() {
+        assert_eq!(Instruction::decode(0x00FB), Ok(Instruction::ScrollRight));
+    }
+
+    #[test]
+    fn decode_scrollleft() {
+        assert_eq!(Instruction::decode(0x00FC), Ok(Instruction::ScrollLeft));
+    }
+
+    #[test]
+    fn decode_exit() {
+        assert_eq!(Instruction::decode(0x00FD), Ok(Instruction::Exit));
+    }
+
+    #[test]
+    fn decode_lowresolution() {
+        assert_eq!(Instruction::decode(0x00FE), Ok(Instruction::LowResolution));
+    }
+
+    #[test]
+    fn decode_highresolution() {
+        assert_eq!(Instruction::decode(0x00FF), Ok(Instruction::HighResolution));
+    }
+
+    #[test]
+    fn decode_loadbigsprite() {
+        assert_eq!(
+            Instruction::decode(0xFB30),
+            Ok(Instruction::LoadBigSprite(VRegister::VB))
+        );
+    }
+
+    #[test]
+    fn decode_storeflags() {
+        assert_eq!(
+            Instruction::decode(0xFC75),
+            Ok(Instruction::StoreFlags(VRegister::VC))
+        );
+    }
+
+    #[test]
+    fn decode_loadflags() {
+        assert_eq!(
+            Instruction::decode(0xFD85),
+            Ok(Instruction::LoadFlags(VRegister::VD))
+        );
+    }
+
+    #[test]
+    fn decode_saverange() {
+        assert_eq!(
+            Instruction::decode(0x5122),
+            Ok(Instruction::SaveRange(VRegister::V1, VRegister::V2))
+        );
+    }
+
+    #[test]
+    fn decode_loadrange() {
+        assert_eq!(
+            Instruction::decode(0x5213),
+            Ok(Instruction::LoadRange(VRegister::V2, VRegister::V1))
+        );
+    }
+
+    #[test]
+    fn decode_loadilong_incomplete() {
+        assert_eq!(
+            Instruction::decode(0xF000),
+            Err(Chip8Error::IncompleteInstruction(0xF000))
+        );
+    }
+
+    #[test]
+    fn decode_long_loadilong() {
+        assert_eq!(
+            Instruction::decode_long(0xF000, 0xABCD),
+            Ok(Instruction::LoadILong(Addr::long(0xABCD)))
+        );
+    }
+
+    #[test]
+    fn decode_long_short() {
+        assert_eq!(
+            Instruction::decode_long(0x00E0, 0xABCD),
+            Ok(Instruction::Clear)
+        );
+    }
+
+    #[test]
+    fn decode_selectplanes() {
+        assert_eq!(
+            Instruction::decode(0xF301),
+            Ok(Instruction::SelectPlanes(0x3.into()))
+        );
+    }
+
+    #[test]
+    fn decode_loadaudio() {
+        assert_eq!(Instruction::decode(0xF002), Ok(Instruction::LoadAudio));
+    }
+
+    #[test]
+    fn decode_pitch() {
+        assert_eq!(
+            Instruction::decode(0xF43A),
+            Ok(Instruction::Pitch(VRegister::V4))
+        );
+    }
+
+    #[test]
+    fn to_bytes() {
+        assert_eq!(Instruction::Clear.to_bytes(), [0x00, 0xE0]);
+        assert_eq!(
+            Instruction::LoadILong(Addr::long(0xABCD)).to_bytes(),
+            [0xF0, 0x00, 0xAB, 0xCD]
+        );
+    }
+
+    #[test]
+    fn instruction_display() {
+        assert_eq!(Instruction::Clear.to_string(), "CLS");
+        assert_eq!(Instruction::Jump(0x2A4.into()).to_string(), "JP 0x2A4");
+        assert_eq!(
+            Instruction::LoadOperand(VRegister::VA, 0x0F).to_string(),
+            "LD VA, 0x0F"
+        );
+        assert_eq!(
+            Instruction::Draw(VRegister::V0, VRegister::V1, 5.into()).to_string(),
+            "DRW V0, V1, 5"
+        );
+        assert_eq!(
+            Instruction::LoadILong(Addr::long(0xABCD)).to_string(),
+            "LD I, long 0xABCD"
+        );
+    }
+
+    #[test]
+    fn vregister_iter_range() {
+        let up: Vec<VRegister> = VRegister::iter_range(VRegister::V1, VRegister::V3).collect();
+        assert_eq!(up, [VRegister::V1, VRegister::V2, VRegister::V3]);
+
+        let down: Vec<VRegister> = VRegister::iter_range(VRegister::V3, VRegister::V1).collect();
+        assert_eq!(down, [VRegister::V3, VRegister::V2, VRegister::V1]);
+    }
+
     #[test]
     fn vregister_iter_to_v0() {
         use super::VRegister::*;
-        assert!(VRegister::iter_to(V0).eq(vec![V0].iter().map(|r| *r)));
+        assert!(VRegister::iter_to(V0).eq([V0].iter().copied()));
     }
 
     #[test]
     fn vregister_iter_to_va() {
         use super::VRegister::*;
         assert!(
-            VRegister::iter_to(VA).eq(vec![V0, V1, V2, V3, V4, V5, V6, V7, V8, V9, VA]
-                .iter()
-                .map(|r| *r))
+            VRegister::iter_to(VA).eq([V0, V1, V2, V3, V4, V5, V6, V7, V8, V9, VA].iter().copied())
         );
     }
 
     #[test]
     fn vregister_iter_to_vf() {
         use super::VRegister::*;
-        assert!(VRegister::iter_to(VF).eq(vec![
+        assert!(VRegister::iter_to(VF).eq([
             V0, V1, V2, V3, V4, V5, V6, V7, V8, V9, VA, VB, VC, VD, VE, VF
         ]
         .iter()
-        .map(|r| *r)));
+        .copied()));
     }
 
     proptest! {
@@ -5,21 +5,15 @@ use core::convert::TryFrom;
 use core::ops::{Index, IndexMut};
 
 /// Possible state for each key
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum KeyState {
     /// Key not pressed
+    #[default]
     NotPressed = 0,
     /// Key pressed
     Pressed = 1,
 }
 
-impl Default for KeyState {
-    #[must_use]
-    fn default() -> Self {
-        Self::NotPressed
-    }
-}
-
 /// Individual key on the [`Keypad`]
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -129,21 +123,18 @@ impl Keypad {
 impl Index<Key> for Keypad {
     type Output = KeyState;
 
-    #[must_use]
     fn index(&self, index: Key) -> &Self::Output {
         &self.state[index as usize]
     }
 }
 
 impl IndexMut<Key> for Keypad {
-    #[must_use]
     fn index_mut(&mut self, index: Key) -> &mut Self::Output {
         &mut self.state[index as usize]
     }
 }
 
 impl Default for Keypad {
-    #[must_use]
     fn default() -> Self {
         Self::new()
     }
@@ -3,6 +3,8 @@
 //! # Features
 //! This crate uses [Cargo "features"](https://doc.rust-lang.org/cargo/reference/features.html#the-features-section) for conditional compilation.
 //! - `std`: Enables usage of [Rust's standard library `std`](https://doc.rust-lang.org/std/)
+//! - `rand`: Enables the [`rand`](https://docs.rs/rand/0.8) crate as [`random::RandomSource`]
+//! - `dap`: Enables the [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/) server in [`dap`], implies `std`
 //!
 //! Functionality affected by features should have a `rustdoc` hint in this documentation, e.g.:
 //! > This is supported on **crate feature `std`** only.
@@ -16,6 +18,15 @@
 //!
 //! Even if disabled this crate still requires the [Rust core allocation and collections library `alloc`](https://doc.rust-lang.org/alloc/), i.e. a global allocator.
 //!
+//! ## Feature `rand`
+//! This is a default feature. Without it the crate does not depend on `rand`
+//! and the VM needs a [`random::RandomSource`] like [`random::XorShift`].
+//!
+//! [`VM::default`](vm::VM::default) requires both this and feature `std`.
+//!
+//! ## Feature `dap`
+//! Adds a dependency on [`serde_json`](https://docs.rs/serde_json) for the messages of the Debug Adapter Protocol.
+//!
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![cfg_attr(docsrs, feature(doc_cfg_hide))]
@@ -25,15 +36,31 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
-#[macro_use]
 extern crate alloc;
 
+pub mod asm;
+pub mod beeper;
+#[cfg_attr(docsrs, doc(cfg(feature = "dap")))]
+#[cfg(feature = "dap")]
+pub mod dap;
+pub mod debug;
+pub mod disasm;
 pub mod display;
 pub mod errors;
 mod font;
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[cfg(feature = "std")]
+pub mod gdb;
 pub mod instructions;
 pub mod keypad;
 pub mod memory;
+pub mod movie;
+pub mod octo;
+pub mod quirks;
+pub mod random;
+pub mod rewind;
+pub mod snapshot;
+pub mod trace;
 pub mod vm;
 
 #[cfg(test)]
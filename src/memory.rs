@@ -1,30 +1,103 @@
 //! Memory
+//!
+//! The [`VM`](crate::vm::VM) accesses memory through a [`Bus`], which is plain RAM in [`Memory`] by default.
+//! A [`MappedBus`] additionally maps [`Device`]s and write protected regions into the address space.
 
+use crate::errors::Chip8Error;
 use crate::instructions::Addr;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::ops::RangeInclusive;
 
 const RAM_SIZE: usize = 4096;
+const XO_CHIP_RAM_SIZE: usize = 65536;
 /// RAM
 pub struct Memory {
-    ram: [u8; RAM_SIZE],
+    ram: Vec<u8>,
 }
 
 impl Memory {
-    /// Creates a new instance intialized with `0`
+    /// Default size of the RAM in bytes
+    pub const SIZE: usize = RAM_SIZE;
+
+    /// Size of the RAM in bytes for XO-CHIP
+    pub const XO_CHIP_SIZE: usize = XO_CHIP_RAM_SIZE;
+
+    /// Creates a new instance of the default size intialized with `0`
     pub fn new() -> Self {
-        Self { ram: [0; RAM_SIZE] }
+        Self::with_size(RAM_SIZE)
+    }
+
+    /// Creates a new instance of `size` bytes intialized with `0`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` exceeds the 16-bit address space, i.e. [`Memory::XO_CHIP_SIZE`].
+    pub fn with_size(size: usize) -> Self {
+        assert!(size <= XO_CHIP_RAM_SIZE, "memory exceeds address space");
+        Self { ram: vec![0; size] }
+    }
+
+    /// Returns the size of the RAM in bytes
+    pub fn size(&self) -> usize {
+        self.ram.len()
+    }
+
+    /// Returns the `addr` if it is within memory
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::OutOfRange`](crate::errors::Chip8Error::OutOfRange)
+    /// if `addr` is not within memory.
+    pub fn checked_addr(&self, addr: u16) -> crate::errors::Result<Addr> {
+        if usize::from(addr) < self.size() {
+            Ok(Addr::long(addr))
+        } else {
+            Err(Chip8Error::OutOfRange(addr))
+        }
     }
 
     /// Reads a byte at `addr`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addr` is not within memory, see [`checked_addr`](Self::checked_addr).
     pub fn read(&self, addr: Addr) -> u8 {
         let addr: usize = addr.into();
         self.ram[addr]
     }
 
     /// Writes a `val` byte at `addr`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addr` is not within memory, see [`checked_addr`](Self::checked_addr).
     pub fn write(&mut self, addr: Addr, val: u8) {
         let addr: usize = addr.into();
         self.ram[addr] = val;
     }
+
+    /// Writes all `bytes` starting at `addr`
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::OutOfRange`](crate::errors::Chip8Error::OutOfRange)
+    /// with the first address that does not fit into memory if `bytes` are too long.
+    /// That address saturates at `0xFFFF` for a memory of [`Memory::XO_CHIP_SIZE`].
+    /// Memory is not modified in that case.
+    pub fn write_slice(&mut self, addr: Addr, bytes: &[u8]) -> crate::errors::Result<()> {
+        let start: usize = addr.into();
+        let end = start + bytes.len();
+        if end > self.size() {
+            let first_invalid = u16::try_from(self.size().max(start)).unwrap_or(u16::MAX);
+            return Err(Chip8Error::OutOfRange(first_invalid));
+        }
+
+        self.ram[start..end].copy_from_slice(bytes);
+        Ok(())
+    }
 }
 
 impl Default for Memory {
@@ -32,3 +105,293 @@ impl Default for Memory {
         Self::new()
     }
 }
+
+/// Address space the VM fetches instructions from and reads and writes data to
+pub trait Bus {
+    /// Returns the size of the address space in bytes
+    fn size(&self) -> usize;
+
+    /// Reads a byte at `addr`, which is within [`size`](Self::size)
+    ///
+    /// Debuggers read as well, so this should not have side effects.
+    fn read(&self, addr: Addr) -> u8;
+
+    /// Writes a `val` byte at `addr`, which is within [`size`](Self::size)
+    ///
+    /// # Errors
+    ///
+    /// May return an error, e.g. [`Chip8Error::WriteProtected`](crate::errors::Chip8Error::WriteProtected),
+    /// which aborts the instruction.
+    fn write(&mut self, addr: Addr, val: u8) -> crate::errors::Result<()>;
+
+    /// Writes all `bytes` starting at `addr` regardless of any protection, e.g. for fonts and ROMs
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::OutOfRange`](crate::errors::Chip8Error::OutOfRange)
+    /// if `bytes` do not fit into the address space.
+    fn load(&mut self, addr: Addr, bytes: &[u8]) -> crate::errors::Result<()>;
+
+    /// Returns the `addr` if it is within the address space
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::OutOfRange`](crate::errors::Chip8Error::OutOfRange)
+    /// if `addr` is not within the address space.
+    fn checked_addr(&self, addr: u16) -> crate::errors::Result<Addr> {
+        if usize::from(addr) < self.size() {
+            Ok(Addr::long(addr))
+        } else {
+            Err(Chip8Error::OutOfRange(addr))
+        }
+    }
+}
+
+impl Bus for Memory {
+    fn size(&self) -> usize {
+        Memory::size(self)
+    }
+
+    fn read(&self, addr: Addr) -> u8 {
+        Memory::read(self, addr)
+    }
+
+    fn write(&mut self, addr: Addr, val: u8) -> crate::errors::Result<()> {
+        Memory::write(self, addr, val);
+        Ok(())
+    }
+
+    fn load(&mut self, addr: Addr, bytes: &[u8]) -> crate::errors::Result<()> {
+        self.write_slice(addr, bytes)
+    }
+}
+
+/// Device mapped into a region of a [`MappedBus`], e.g. a debug console port
+///
+/// Addresses are offsets from the start of the region.
+pub trait Device {
+    /// Reads a byte at `offset`
+    ///
+    /// Debuggers read as well, so this should not have side effects.
+    fn read(&self, offset: u16) -> u8;
+
+    /// Writes a `val` byte at `offset`
+    ///
+    /// # Errors
+    ///
+    /// May return an error, which aborts the instruction.
+    fn write(&mut self, offset: u16, val: u8) -> crate::errors::Result<()>;
+}
+
+/// What happens on writes into a protected region of a [`MappedBus`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteProtection {
+    /// Writes fail with [`Chip8Error::WriteProtected`](crate::errors::Chip8Error::WriteProtected)
+    Error,
+    /// Writes are silently ignored
+    Ignore,
+}
+
+enum Region {
+    Protected(WriteProtection),
+    Device(Box<dyn Device>),
+}
+
+/// [`Memory`] with mapped devices and write protected regions
+///
+/// Regions mapped later take precedence over earlier ones where they overlap,
+/// everything else is RAM.
+///
+/// ```
+/// use chip_8::errors::Chip8Error;
+/// use chip_8::memory::{Bus, MappedBus, Memory, WriteProtection};
+///
+/// let mut bus = MappedBus::new(Memory::new());
+/// // interpreter area with the fonts
+/// bus.protect(0x000..=0x1FF, WriteProtection::Error);
+///
+/// assert_eq!(bus.write(0x100.into(), 0xFF), Err(Chip8Error::WriteProtected(0x100)));
+/// assert_eq!(bus.write(0x200.into(), 0xFF), Ok(()));
+/// ```
+pub struct MappedBus {
+    ram: Memory,
+    regions: Vec<(RangeInclusive<u16>, Region)>,
+}
+
+impl MappedBus {
+    /// Creates a new instance with the `ram` and nothing mapped
+    #[must_use]
+    pub fn new(ram: Memory) -> Self {
+        Self {
+            ram,
+            regions: Vec::new(),
+        }
+    }
+
+    /// Returns the RAM, including the bytes hidden by devices
+    #[must_use]
+    pub fn ram(&self) -> &Memory {
+        &self.ram
+    }
+
+    /// Protects the addresses in `range` from writes by the VM
+    pub fn protect(&mut self, range: RangeInclusive<u16>, protection: WriteProtection) {
+        self.regions.push((range, Region::Protected(protection)));
+    }
+
+    /// Maps the `device` to the addresses in `range`
+    pub fn map(&mut self, range: RangeInclusive<u16>, device: Box<dyn Device>) {
+        self.regions.push((range, Region::Device(device)));
+    }
+
+    /// Returns the start and kind of the region containing `addr`
+    fn region(&self, addr: u16) -> Option<(u16, &Region)> {
+        self.regions
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&addr))
+            .map(|(range, region)| (*range.start(), region))
+    }
+}
+
+impl Bus for MappedBus {
+    fn size(&self) -> usize {
+        self.ram.size()
+    }
+
+    fn read(&self, addr: Addr) -> u8 {
+        let raw = u16::from(addr);
+        match self.region(raw) {
+            Some((start, Region::Device(device))) => device.read(raw - start),
+            _ => self.ram.read(addr),
+        }
+    }
+
+    fn write(&mut self, addr: Addr, val: u8) -> crate::errors::Result<()> {
+        let raw = u16::from(addr);
+        let region = self
+            .regions
+            .iter_mut()
+            .rev()
+            .find(|(range, _)| range.contains(&raw));
+        match region {
+            Some((_, Region::Protected(WriteProtection::Error))) => {
+                Err(Chip8Error::WriteProtected(raw))
+            }
+            Some((_, Region::Protected(WriteProtection::Ignore))) => Ok(()),
+            Some((range, Region::Device(device))) => device.write(raw - *range.start(), val),
+            None => {
+                self.ram.write(addr, val);
+                Ok(())
+            }
+        }
+    }
+
+    fn load(&mut self, addr: Addr, bytes: &[u8]) -> crate::errors::Result<()> {
+        self.ram.write_slice(addr, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_write_slice() -> crate::errors::Result<()> {
+        let mut memory = Memory::new();
+
+        memory.write_slice(0x0FFE.into(), &[0xAA, 0xBB])?;
+
+        assert_eq!(memory.read(0x0FFD.into()), 0x00);
+        assert_eq!(memory.read(0x0FFE.into()), 0xAA);
+        assert_eq!(memory.read(0x0FFF.into()), 0xBB);
+        Ok(())
+    }
+
+    #[test]
+    fn memory_checked_addr() {
+        let memory = Memory::new();
+
+        assert_eq!(memory.checked_addr(0x0FFF), Ok(0x0FFF.into()));
+        assert_eq!(
+            memory.checked_addr(0x1000),
+            Err(Chip8Error::OutOfRange(0x1000))
+        );
+    }
+
+    #[test]
+    fn memory_xo_chip_size() -> crate::errors::Result<()> {
+        let mut memory = Memory::with_size(Memory::XO_CHIP_SIZE);
+
+        let addr = memory.checked_addr(0xFFFF)?;
+        memory.write(addr, 0xAA);
+
+        assert_eq!(memory.read(addr), 0xAA);
+        Ok(())
+    }
+
+    #[test]
+    fn memory_write_slice_out_of_range() {
+        let mut memory = Memory::new();
+
+        let res = memory.write_slice(0x0FFE.into(), &[0xAA, 0xBB, 0xCC]);
+
+        assert_eq!(res, Err(Chip8Error::OutOfRange(0x1000)));
+        assert_eq!(memory.read(0x0FFE.into()), 0x00);
+    }
+
+    /// Console port which collects written bytes and reads as the number of them
+    struct Console(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl Device for Console {
+        fn read(&self, _offset: u16) -> u8 {
+            self.0.borrow().len() as u8
+        }
+
+        fn write(&mut self, _offset: u16, val: u8) -> crate::errors::Result<()> {
+            self.0.borrow_mut().push(val);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn mapped_bus_protect() -> crate::errors::Result<()> {
+        let mut bus = MappedBus::new(Memory::new());
+        bus.protect(0x000..=0x1FF, WriteProtection::Error);
+        bus.protect(0x100..=0x10F, WriteProtection::Ignore);
+
+        assert_eq!(
+            bus.write(0x1FF.into(), 0xAA),
+            Err(Chip8Error::WriteProtected(0x1FF))
+        );
+        bus.write(0x100.into(), 0xAA)?;
+        bus.write(0x200.into(), 0xAA)?;
+        bus.load(0x000.into(), &[0xBB])?;
+
+        assert_eq!(bus.read(0x000.into()), 0xBB);
+        assert_eq!(bus.read(0x100.into()), 0x00);
+        assert_eq!(bus.read(0x1FF.into()), 0x00);
+        assert_eq!(bus.read(0x200.into()), 0xAA);
+        Ok(())
+    }
+
+    #[test]
+    fn mapped_bus_device() -> crate::errors::Result<()> {
+        let output = std::rc::Rc::default();
+        let mut bus = MappedBus::new(Memory::new());
+        bus.map(
+            0xFF0..=0xFFF,
+            Box::new(Console(std::rc::Rc::clone(&output))),
+        );
+
+        bus.write(0xFF0.into(), b'h')?;
+        bus.write(0xFFF.into(), b'i')?;
+        bus.write(0xFEF.into(), b'!')?;
+
+        assert_eq!(*output.borrow(), b"hi");
+        assert_eq!(bus.read(0xFF5.into()), 2);
+        assert_eq!(bus.read(0xFEF.into()), b'!');
+        assert_eq!(bus.ram().read(0xFF0.into()), 0x00);
+        Ok(())
+    }
+}
@@ -0,0 +1,483 @@
+//! Input recording and replay
+//!
+//! A [`Movie`] holds everything needed to replay a session deterministically:
+//! the seed of the random number generator, the [`Quirks`] profile,
+//! the number of [`VM::step`]s per frame and the key presses and releases of each frame.
+//! The ROM is not part of it.
+//!
+//! A [`Recorder`] runs a [`VM`] frame by frame and logs the input,
+//! a [`Player`] feeds it back into another `VM` created the same way.
+//! Both must use the same RNG type, seeded with [`Movie::rng`].
+//!
+//! # Format
+//!
+//! [`Movie::to_bytes`] writes version [`Movie::VERSION`] of this binary format.
+//! All numbers are little endian.
+//!
+//! | Size    | Content                                                                  |
+//! |---------|--------------------------------------------------------------------------|
+//! | 4       | Magic [`Movie::MAGIC`]                                                   |
+//! | 2       | Format version                                                           |
+//! | 8       | Seed of the random number generator                                      |
+//! | 1       | [`Quirks`] flags, bit `0` [`shift_vx`](Quirks::shift_vx), bit `1` [`jump_vx`](Quirks::jump_vx), bit `2` [`logic_resets_vf`](Quirks::logic_resets_vf) |
+//! | 1       | [`Quirks::memory_increment`], `0` for `XPlusOne`, `1` for `X`, `2` for `Unchanged` |
+//! | 1       | [`Quirks::key_wait`], `0` for `Press`, `1` for `Release`                 |
+//! | 1       | [`Quirks::edge_mode`], `0` for `Clip`, `1` for `Wrap`                    |
+//! | 4       | Steps per frame                                                          |
+//! | 4       | Number `n` of frames                                                     |
+//! | `n` * … | Frames, each with the number `e` of key events followed by `e` key events |
+//!
+//! The number of key events is a single byte, as is each key event:
+//! The key in the lower four bits and bit `7` set for a press or unset for a release.
+
+use crate::beeper::Beeper;
+use crate::display::{EdgeMode, Screen};
+use crate::errors::Chip8Error;
+use crate::keypad::{Key, KeyState, Keypad};
+use crate::memory::Bus;
+use crate::quirks::{KeyWait, MemoryIncrement, Quirks};
+use crate::snapshot::Reader;
+use crate::vm::VM;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Bit of a key event that is set for a press
+const PRESSED: u8 = 0x80;
+
+/// Key press or release at the start of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Key that changed its state
+    pub key: Key,
+    /// New state of the key
+    pub state: KeyState,
+}
+
+/// Recorded input of a session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    seed: u64,
+    quirks: Quirks,
+    steps_per_frame: u32,
+    frames: Vec<Vec<KeyEvent>>,
+}
+
+impl Movie {
+    /// Magic bytes at the start of the binary format
+    pub const MAGIC: [u8; 4] = *b"C8MV";
+
+    /// Version of the binary format written by [`to_bytes`](Self::to_bytes)
+    pub const VERSION: u16 = 1;
+
+    /// Returns the seed of the random number generator
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns a new random number generator seeded with [`seed`](Self::seed)
+    ///
+    /// Without feature `rand` use [`XorShift::new`](crate::random::XorShift::new) with the seed instead.
+    #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+    #[cfg(feature = "rand")]
+    #[must_use]
+    pub fn rng<R: rand::SeedableRng>(&self) -> R {
+        R::seed_from_u64(self.seed)
+    }
+
+    /// Returns the quirks profile of the recorded VM
+    #[must_use]
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Returns the number of [`VM::step`]s per frame
+    #[must_use]
+    pub fn steps_per_frame(&self) -> u32 {
+        self.steps_per_frame
+    }
+
+    /// Returns the key events of each frame
+    #[must_use]
+    pub fn frames(&self) -> &[Vec<KeyEvent>] {
+        &self.frames
+    }
+
+    /// Serializes into the binary format described in the [module documentation](self)
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u32::MAX` frames.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(28 + self.frames.len());
+        bytes.extend_from_slice(&Self::MAGIC);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(
+            u8::from(self.quirks.shift_vx)
+                | u8::from(self.quirks.jump_vx) << 1
+                | u8::from(self.quirks.logic_resets_vf) << 2,
+        );
+        bytes.push(match self.quirks.memory_increment {
+            MemoryIncrement::XPlusOne => 0,
+            MemoryIncrement::X => 1,
+            MemoryIncrement::Unchanged => 2,
+        });
+        bytes.push(match self.quirks.key_wait {
+            KeyWait::Press => 0,
+            KeyWait::Release => 1,
+        });
+        bytes.push(match self.quirks.edge_mode {
+            EdgeMode::Clip => 0,
+            EdgeMode::Wrap => 1,
+        });
+        bytes.extend_from_slice(&self.steps_per_frame.to_le_bytes());
+        let frames = u32::try_from(self.frames.len()).expect("at most u32::MAX frames");
+        bytes.extend_from_slice(&frames.to_le_bytes());
+        for events in &self.frames {
+            // each key changes at most once per frame
+            bytes.push(events.len() as u8);
+            for event in events {
+                bytes.push(match event.state {
+                    KeyState::Pressed => PRESSED | event.key as u8,
+                    KeyState::NotPressed => event.key as u8,
+                });
+            }
+        }
+        bytes
+    }
+
+    /// Deserializes from the binary format described in the [module documentation](self)
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::UnsupportedMovieVersion`] if `bytes` are of another format version,
+    /// or [`Chip8Error::InvalidMovie`] if they are malformed otherwise.
+    pub fn from_bytes(bytes: &[u8]) -> crate::errors::Result<Self> {
+        let mut reader = Reader::new(bytes, || Chip8Error::InvalidMovie);
+        if reader.take(Self::MAGIC.len())? != Self::MAGIC {
+            return Err(Chip8Error::InvalidMovie);
+        }
+        let version = reader.u16()?;
+        if version != Self::VERSION {
+            return Err(Chip8Error::UnsupportedMovieVersion(version));
+        }
+
+        let seed = reader.u64()?;
+        let flags = reader.u8()?;
+        if flags > 0b111 {
+            return Err(Chip8Error::InvalidMovie);
+        }
+        let memory_increment = match reader.u8()? {
+            0 => MemoryIncrement::XPlusOne,
+            1 => MemoryIncrement::X,
+            2 => MemoryIncrement::Unchanged,
+            _ => return Err(Chip8Error::InvalidMovie),
+        };
+        let key_wait = match reader.u8()? {
+            0 => KeyWait::Press,
+            1 => KeyWait::Release,
+            _ => return Err(Chip8Error::InvalidMovie),
+        };
+        let edge_mode = match reader.u8()? {
+            0 => EdgeMode::Clip,
+            1 => EdgeMode::Wrap,
+            _ => return Err(Chip8Error::InvalidMovie),
+        };
+        let quirks = Quirks {
+            shift_vx: flags & 0b001 != 0,
+            jump_vx: flags & 0b010 != 0,
+            logic_resets_vf: flags & 0b100 != 0,
+            memory_increment,
+            key_wait,
+            edge_mode,
+        };
+        let steps_per_frame = reader.u32()?;
+        let frame_count = reader.u32()?;
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            let event_count = reader.u8()?;
+            let events = reader
+                .take(event_count.into())?
+                .iter()
+                .map(|event| {
+                    let key =
+                        Key::try_from(event & !PRESSED).map_err(|_| Chip8Error::InvalidMovie)?;
+                    let state = if event & PRESSED != 0 {
+                        KeyState::Pressed
+                    } else {
+                        KeyState::NotPressed
+                    };
+                    Ok(KeyEvent { key, state })
+                })
+                .collect::<crate::errors::Result<_>>()?;
+            frames.push(events);
+        }
+        reader.finish()?;
+
+        Ok(Self {
+            seed,
+            quirks,
+            steps_per_frame,
+            frames,
+        })
+    }
+}
+
+/// Presses and releases the keys of `events` on the `vm`
+pub(crate) fn apply_events<R, S, B, M>(vm: &mut VM<R, S, B, M>, events: &[KeyEvent])
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    for event in events {
+        match event.state {
+            KeyState::Pressed => vm.key_down(event.key),
+            KeyState::NotPressed => vm.key_up(event.key),
+        }
+    }
+}
+
+/// Runs one frame: applies the key `events`, steps the `vm` and ticks its timers
+fn run_frame<R, S, B, M>(
+    vm: &mut VM<R, S, B, M>,
+    events: &[KeyEvent],
+    steps: u32,
+) -> crate::errors::Result<()>
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    apply_events(vm, events);
+    for _ in 0..steps {
+        vm.step()?;
+    }
+    vm.tick_timers();
+    Ok(())
+}
+
+/// Runs a [`VM`] frame by frame and records its input into a [`Movie`]
+#[derive(Debug)]
+pub struct Recorder {
+    movie: Movie,
+    keypad: Keypad,
+}
+
+impl Recorder {
+    /// Creates a new instance for a `VM` with an RNG seeded with `seed`, the `quirks` profile
+    /// and `steps_per_frame` [`VM::step`]s per frame
+    #[must_use]
+    pub fn new(seed: u64, quirks: Quirks, steps_per_frame: u32) -> Self {
+        Self {
+            movie: Movie {
+                seed,
+                quirks,
+                steps_per_frame,
+                frames: Vec::new(),
+            },
+            keypad: Keypad::new(),
+        }
+    }
+
+    /// Returns the movie recorded so far
+    #[must_use]
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    /// Records the changes from the previous to the current `keypad` and runs a frame of the `vm`
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`]. The frame is recorded nevertheless.
+    pub fn frame<R, S, B, M>(
+        &mut self,
+        vm: &mut VM<R, S, B, M>,
+        keypad: &Keypad,
+    ) -> crate::errors::Result<()>
+    where
+        R: crate::random::RandomSource,
+        S: Screen,
+        B: Beeper,
+        M: Bus,
+    {
+        let events: Vec<_> = (0..16u8)
+            .filter_map(|key| Key::try_from(key).ok())
+            .filter(|key| self.keypad[*key] != keypad[*key])
+            .map(|key| KeyEvent {
+                key,
+                state: keypad[key],
+            })
+            .collect();
+        self.keypad = *keypad;
+        self.movie.frames.push(events);
+        let events = &self.movie.frames[self.movie.frames.len() - 1];
+        run_frame(vm, events, self.movie.steps_per_frame)
+    }
+
+    /// Returns the recorded movie
+    #[must_use]
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+/// Replays the input of a [`Movie`] frame by frame into a [`VM`]
+#[derive(Debug)]
+pub struct Player<'a> {
+    movie: &'a Movie,
+    frame: usize,
+}
+
+impl<'a> Player<'a> {
+    /// Creates a new instance starting at the first frame of the `movie`
+    #[must_use]
+    pub fn new(movie: &'a Movie) -> Self {
+        Self { movie, frame: 0 }
+    }
+
+    /// Returns whether all frames have been played
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.frames.len()
+    }
+
+    /// Runs the next frame of the `vm` with the recorded input
+    ///
+    /// Returns `false` without running anything if all frames have been played.
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`].
+    pub fn frame<R, S, B, M>(&mut self, vm: &mut VM<R, S, B, M>) -> crate::errors::Result<bool>
+    where
+        R: crate::random::RandomSource,
+        S: Screen,
+        B: Beeper,
+        M: Bus,
+    {
+        let events = match self.movie.frames.get(self.frame) {
+            Some(events) => events,
+            None => return Ok(false),
+        };
+        self.frame += 1;
+        run_frame(vm, events, self.movie.steps_per_frame)?;
+        Ok(true)
+    }
+
+    /// Runs all remaining frames of the `vm` with the recorded input
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`].
+    pub fn play<R, S, B, M>(&mut self, vm: &mut VM<R, S, B, M>) -> crate::errors::Result<()>
+    where
+        R: crate::random::RandomSource,
+        S: Screen,
+        B: Beeper,
+        M: Bus,
+    {
+        while self.frame(vm)? {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::XorShift;
+    use crate::vm::Config;
+
+    /// Draws a random sprite at a random position whenever key `5` is down
+    const ROM: [u8; 14] = [
+        0x60, 0x05, // LD V0, 5
+        0xE0, 0xA1, // SKNP V0
+        0x12, 0x08, // JP 0x208
+        0x12, 0x00, // JP 0x200
+        0xC1, 0x3F, // RND V1, 0x3F
+        0xF1, 0x29, // LD F, V1
+        0xD1, 0x15, // DRW V1, V1, 5
+    ];
+
+    fn test_vm(movie: &Movie) -> VM<XorShift> {
+        let config = Config {
+            quirks: movie.quirks(),
+            ..Config::default()
+        };
+        let mut vm = VM::with_config(XorShift::new(movie.seed()), |_, _| Ok(()), config);
+        vm.load_rom(&ROM).unwrap();
+        vm
+    }
+
+    #[test]
+    fn movie_record_and_play() -> crate::errors::Result<()> {
+        let mut recorder = Recorder::new(42, Quirks::COSMAC_VIP, 7);
+        let mut vm = test_vm(recorder.movie());
+        let mut keypad = Keypad::new();
+        for frame in 0..20 {
+            keypad[Key::Key5] = if frame % 3 == 0 {
+                KeyState::Pressed
+            } else {
+                KeyState::NotPressed
+            };
+            recorder.frame(&mut vm, &keypad)?;
+        }
+        let movie = Movie::from_bytes(&recorder.finish().to_bytes())?;
+
+        let mut replayed = test_vm(&movie);
+        let mut player = Player::new(&movie);
+        player.play(&mut replayed)?;
+
+        assert!(player.is_finished());
+        assert_eq!(movie.frames().len(), 20);
+        assert_eq!(
+            movie.frames()[0],
+            [KeyEvent {
+                key: Key::Key5,
+                state: KeyState::Pressed
+            }]
+        );
+        assert_eq!(movie.frames()[2], []);
+        assert_eq!(replayed.snapshot(), vm.snapshot());
+        Ok(())
+    }
+
+    #[test]
+    fn movie_record_error() -> crate::errors::Result<()> {
+        let mut recorder = Recorder::new(1, Quirks::default(), 3);
+        let mut vm = test_vm(recorder.movie());
+        vm.load_rom(&[0xFF, 0xFF])?;
+        let mut keypad = Keypad::new();
+        keypad[Key::Key5] = KeyState::Pressed;
+
+        assert_eq!(
+            recorder.frame(&mut vm, &keypad),
+            Err(Chip8Error::UnknownInstruction(0xFFFF))
+        );
+        assert_eq!(recorder.movie().frames().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn movie_from_bytes_invalid() {
+        let movie = Recorder::new(1, Quirks::default(), 10).finish();
+        let bytes = movie.to_bytes();
+
+        assert_eq!(
+            Movie::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Chip8Error::InvalidMovie)
+        );
+
+        let mut newer = bytes;
+        newer[4] = 0x02;
+        assert_eq!(
+            Movie::from_bytes(&newer),
+            Err(Chip8Error::UnsupportedMovieVersion(2))
+        );
+    }
+}
@@ -0,0 +1,1209 @@
+//! Octo compiler
+//!
+//! Compiles source in the [Octo](https://github.com/JohnEarnest/Octo) assembly language into a ROM.
+//!
+//! Supported are labels (`: name`, `:next name`), `:alias`, `:const`, `:calc`, `:macro`, `:org`,
+//! `:byte`, `:call`, `:unpack`, all statements for CHIP-8 and SUPER-CHIP,
+//! `if … then`, `if … begin … else … end` and `loop … while … again`.
+//! XO-CHIP statements are only supported if enabled with [`Config::xo_chip`].
+//!
+//! Unlike Octo, `:calc` uses integer arithmetic.
+
+use crate::errors::{Chip8Error, ParseErrorKind};
+use crate::instructions::{Addr, Instruction, Nibble, VRegister};
+use crate::vm::PROGRAM_START;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Maximum number of macro expansions, to stop recursive macros
+const MAX_MACRO_EXPANSIONS: usize = 10_000;
+
+/// Configuration of the Octo compiler
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Memory address where the ROM is loaded
+    pub program_start: Addr,
+
+    /// Whether XO-CHIP statements like `plane` or `i := long` are supported
+    pub xo_chip: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            program_start: PROGRAM_START.into(),
+            xo_chip: false,
+        }
+    }
+}
+
+/// Compiles the Octo `source` into a ROM with the default [`Config`]
+///
+/// # Errors
+///
+/// Will return [`Chip8Error::Parse`] with the line and column of the first error in `source`.
+pub fn compile(source: &str) -> crate::errors::Result<Vec<u8>> {
+    compile_with_config(source, Config::default())
+}
+
+/// Compiles the Octo `source` into a ROM with the given `config`
+///
+/// # Errors
+///
+/// Will return [`Chip8Error::Parse`] with the line and column of the first error in `source`.
+pub fn compile_with_config(source: &str, config: Config) -> crate::errors::Result<Vec<u8>> {
+    let mut compiler = Compiler::new(source, config);
+    while !compiler.tokens.is_empty() {
+        compiler.statement()?;
+    }
+    compiler.finish()
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, kind: ParseErrorKind) -> Chip8Error {
+        Chip8Error::Parse {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+
+    fn is(&self, text: &str) -> bool {
+        self.text == text
+    }
+}
+
+/// Splits the `source` at whitespace and removes `#` comments
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+    for (idx, text) in source.lines().enumerate() {
+        let mut word: Option<(usize, String)> = None;
+        for (column, c) in text.chars().enumerate() {
+            if c == '#' && word.is_none() {
+                break;
+            }
+            if c.is_whitespace() {
+                if let Some((column, text)) = word.take() {
+                    tokens.push_back(Token {
+                        text,
+                        line: idx + 1,
+                        column,
+                    });
+                }
+            } else {
+                word.get_or_insert_with(|| (column + 1, String::new()))
+                    .1
+                    .push(c);
+            }
+        }
+        if let Some((column, text)) = word {
+            tokens.push_back(Token {
+                text,
+                line: idx + 1,
+                column,
+            });
+        }
+    }
+    tokens
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = text.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = text.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        text.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_register(text: &str) -> Option<VRegister> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v') | Some('V'), Some(digit), None) => {
+            VRegister::try_from(digit.to_digit(16)? as u8).ok()
+        }
+        _ => None,
+    }
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// Reference to a label that is not defined yet
+struct Pending {
+    offset: usize,
+    token: Token,
+    long: bool,
+    make: Box<dyn Fn(Addr) -> Instruction>,
+}
+
+enum Block {
+    /// `if … begin` with the jump to the `else` or `end`
+    If(usize),
+    /// `else` with the jump to the `end`
+    Else(usize),
+    /// `loop` with its start and the jumps of all `while`s
+    Loop(u16, Vec<usize>),
+}
+
+/// Operator of a condition, i.e. `vx OP rhs`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Key,
+    NotKey,
+}
+
+impl Comparison {
+    fn negate(self) -> Self {
+        match self {
+            Self::Equal => Self::NotEqual,
+            Self::NotEqual => Self::Equal,
+            Self::Less => Self::GreaterEqual,
+            Self::Greater => Self::LessEqual,
+            Self::LessEqual => Self::Greater,
+            Self::GreaterEqual => Self::Less,
+            Self::Key => Self::NotKey,
+            Self::NotKey => Self::Key,
+        }
+    }
+}
+
+/// Right hand side of a condition or an arithmetic statement
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    Register(VRegister),
+    Byte(u8),
+}
+
+struct Condition {
+    vx: VRegister,
+    comparison: Comparison,
+    rhs: Option<Operand>,
+}
+
+struct Compiler {
+    config: Config,
+    tokens: VecDeque<Token>,
+    last: Token,
+    start: u16,
+    here: u16,
+    rom: Vec<u8>,
+    labels: BTreeMap<String, u16>,
+    constants: BTreeMap<String, i64>,
+    aliases: BTreeMap<String, VRegister>,
+    macros: BTreeMap<String, Macro>,
+    pending: Vec<Pending>,
+    blocks: Vec<(Block, Token)>,
+    expansions: usize,
+}
+
+impl Compiler {
+    fn new(source: &str, config: Config) -> Self {
+        let start = config.program_start.into();
+        let tokens = tokenize(source);
+        let last = tokens.back().cloned().unwrap_or(Token {
+            text: String::new(),
+            line: 1,
+            column: 1,
+        });
+        Self {
+            config,
+            tokens,
+            last,
+            start,
+            // the first instruction is a jump to `main`, see `define_label`
+            here: start + 2,
+            rom: vec![0; 2],
+            labels: BTreeMap::new(),
+            constants: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            macros: BTreeMap::new(),
+            pending: Vec::new(),
+            blocks: Vec::new(),
+            expansions: 0,
+        }
+    }
+
+    fn next(&mut self) -> crate::errors::Result<Token> {
+        self.tokens
+            .pop_front()
+            .ok_or_else(|| self.last.error(ParseErrorKind::UnexpectedEnd))
+    }
+
+    fn expect(&mut self, text: &str) -> crate::errors::Result<Token> {
+        let token = self.next()?;
+        if token.is(text) {
+            Ok(token)
+        } else {
+            Err(token.error(ParseErrorKind::UnexpectedToken))
+        }
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.tokens.front().is_some_and(|token| token.is(text))
+    }
+
+    fn require_xo_chip(&self, token: &Token) -> crate::errors::Result<()> {
+        if self.config.xo_chip {
+            Ok(())
+        } else {
+            Err(token.error(ParseErrorKind::RequiresXoChip))
+        }
+    }
+
+    fn to_register(&self, token: &Token) -> Option<VRegister> {
+        parse_register(&token.text).or_else(|| self.aliases.get(&token.text).copied())
+    }
+
+    fn register(&mut self) -> crate::errors::Result<VRegister> {
+        let token = self.next()?;
+        self.to_register(&token)
+            .ok_or_else(|| token.error(ParseErrorKind::InvalidOperands))
+    }
+
+    /// Returns the value of a number, constant or already defined label
+    fn value(&self, token: &Token) -> Option<i64> {
+        parse_number(&token.text)
+            .or_else(|| self.constants.get(&token.text).copied())
+            .or_else(|| self.labels.get(&token.text).map(|addr| i64::from(*addr)))
+    }
+
+    fn number(&mut self) -> crate::errors::Result<(i64, Token)> {
+        let token = self.next()?;
+        match self.value(&token) {
+            Some(value) => Ok((value, token)),
+            None => Err(token.error(ParseErrorKind::UndefinedSymbol(token.text.clone()))),
+        }
+    }
+
+    fn ranged(&mut self, min: i64, max: i64) -> crate::errors::Result<i64> {
+        let (value, token) = self.number()?;
+        if (min..=max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(token.error(ParseErrorKind::ValueOutOfRange(value)))
+        }
+    }
+
+    /// Returns a byte, negative values are two's complement
+    fn byte(&mut self) -> crate::errors::Result<u8> {
+        Ok(self.ranged(-0x80, 0xFF)? as u8)
+    }
+
+    fn nibble(&mut self) -> crate::errors::Result<Nibble> {
+        Ok((self.ranged(0x0, 0xF)? as u8).into())
+    }
+
+    fn operand(&mut self) -> crate::errors::Result<Operand> {
+        match self
+            .tokens
+            .front()
+            .and_then(|token| self.to_register(token))
+        {
+            Some(vy) => {
+                self.next()?;
+                Ok(Operand::Register(vy))
+            }
+            None => Ok(Operand::Byte(self.byte()?)),
+        }
+    }
+
+    fn emit_bytes(&mut self, token: &Token, bytes: &[u8]) -> crate::errors::Result<()> {
+        let offset = usize::from(self.here - self.start);
+        let end = offset + bytes.len();
+        // `here` must stay addressable after the bytes
+        if end + usize::from(self.start) >= 0x10000 {
+            return Err(token.error(ParseErrorKind::ValueOutOfRange(end as i64)));
+        }
+        if self.rom.len() < end {
+            self.rom.resize(end, 0);
+        }
+        self.rom[offset..end].copy_from_slice(bytes);
+        self.here = self.here.wrapping_add(bytes.len() as u16);
+        Ok(())
+    }
+
+    fn emit(&mut self, token: &Token, instruction: Instruction) -> crate::errors::Result<()> {
+        self.emit_bytes(token, &instruction.to_bytes())
+    }
+
+    /// Overwrites the instruction at `offset` in the ROM
+    fn patch(&mut self, offset: usize, instruction: Instruction) {
+        let bytes = instruction.to_bytes();
+        self.rom[offset..offset + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    fn offset(&self) -> usize {
+        usize::from(self.here - self.start)
+    }
+
+    /// Emits an instruction with an address that might be a label which is defined later
+    fn emit_addr(
+        &mut self,
+        long: bool,
+        make: Box<dyn Fn(Addr) -> Instruction>,
+    ) -> crate::errors::Result<()> {
+        let token = self.next()?;
+        match self.value(&token) {
+            Some(value) => {
+                let addr = to_addr(&token, value, long)?;
+                self.emit(&token, make(addr))
+            }
+            None => {
+                let offset = self.offset();
+                self.emit(&token, make(Addr::long(0)))?;
+                self.pending.push(Pending {
+                    offset,
+                    token,
+                    long,
+                    make,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Emits a jump that is patched later, returns its offset
+    fn emit_jump_placeholder(&mut self, token: &Token) -> crate::errors::Result<usize> {
+        let offset = self.offset();
+        self.emit(token, Instruction::Jump(Addr::from(0)))?;
+        Ok(offset)
+    }
+
+    fn define_label(&mut self, name: Token, addr: u16) -> crate::errors::Result<()> {
+        if self.labels.contains_key(&name.text) || self.constants.contains_key(&name.text) {
+            return Err(name.error(ParseErrorKind::DuplicateSymbol(name.text.clone())));
+        }
+        // like Octo, the jump to `main` is removed if nothing comes before it
+        if name.is("main") && self.here == self.start + 2 && self.rom.len() == 2 {
+            self.here = self.start;
+            self.rom.clear();
+            return self.define_label(name, self.start);
+        }
+        self.labels.insert(name.text, addr);
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: Token, value: i64) -> crate::errors::Result<()> {
+        if self.labels.contains_key(&name.text) || self.constants.contains_key(&name.text) {
+            return Err(name.error(ParseErrorKind::DuplicateSymbol(name.text.clone())));
+        }
+        self.constants.insert(name.text, value);
+        Ok(())
+    }
+
+    fn condition(&mut self) -> crate::errors::Result<Condition> {
+        let vx = self.register()?;
+        let token = self.next()?;
+        let comparison = match token.text.as_str() {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            "<=" => Comparison::LessEqual,
+            ">=" => Comparison::GreaterEqual,
+            "key" => Comparison::Key,
+            "-key" => Comparison::NotKey,
+            _ => return Err(token.error(ParseErrorKind::UnexpectedToken)),
+        };
+        let rhs = match comparison {
+            Comparison::Key | Comparison::NotKey => None,
+            _ => Some(self.operand()?),
+        };
+        Ok(Condition {
+            vx,
+            comparison,
+            rhs,
+        })
+    }
+
+    /// Emits instructions so that the following instruction only runs if the `condition` holds
+    fn emit_condition(
+        &mut self,
+        token: &Token,
+        condition: &Condition,
+        negate: bool,
+    ) -> crate::errors::Result<()> {
+        use Instruction::*;
+
+        let vx = condition.vx;
+        let comparison = if negate {
+            condition.comparison.negate()
+        } else {
+            condition.comparison
+        };
+        let instruction = match (comparison, condition.rhs) {
+            (Comparison::Equal, Some(Operand::Byte(byte))) => SkipNotEqualOperand(vx, byte),
+            (Comparison::Equal, Some(Operand::Register(vy))) => SkipNotEqual(vx, vy),
+            (Comparison::NotEqual, Some(Operand::Byte(byte))) => SkipEqualOperand(vx, byte),
+            (Comparison::NotEqual, Some(Operand::Register(vy))) => SkipEqual(vx, vy),
+            (Comparison::Key, _) => SkipKeyNotPressed(vx),
+            (Comparison::NotKey, _) => SkipKeyPressed(vx),
+            (_, Some(rhs)) => {
+                // VF is the not borrow flag of a subtraction, i.e. whether lhs >= rhs
+                let (setup, compare) = match (comparison, rhs) {
+                    (Comparison::Less | Comparison::GreaterEqual, Operand::Register(vy)) => {
+                        (Load(VRegister::VF, vx), Sub(VRegister::VF, vy))
+                    }
+                    (Comparison::Less | Comparison::GreaterEqual, Operand::Byte(byte)) => (
+                        LoadOperand(VRegister::VF, byte),
+                        SubNegated(VRegister::VF, vx),
+                    ),
+                    (_, Operand::Register(vy)) => (Load(VRegister::VF, vy), Sub(VRegister::VF, vx)),
+                    (_, Operand::Byte(byte)) => {
+                        (LoadOperand(VRegister::VF, byte), Sub(VRegister::VF, vx))
+                    }
+                };
+                self.emit(token, setup)?;
+                self.emit(token, compare)?;
+                match comparison {
+                    Comparison::Less | Comparison::Greater => SkipNotEqualOperand(VRegister::VF, 0),
+                    _ => SkipEqualOperand(VRegister::VF, 0),
+                }
+            }
+            (_, None) => return Err(token.error(ParseErrorKind::InvalidOperands)),
+        };
+        self.emit(token, instruction)
+    }
+
+    /// Reads the tokens of a `:calc` expression up to the closing `}`
+    fn calc(&mut self) -> crate::errors::Result<i64> {
+        let open = self.expect("{")?;
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.is("}") {
+                break;
+            }
+            tokens.push(token);
+        }
+        let mut pos = 0;
+        let value = self.calc_expr(&open, &tokens, &mut pos)?;
+        match tokens.get(pos) {
+            Some(token) => Err(token.error(ParseErrorKind::UnexpectedToken)),
+            None => Ok(value),
+        }
+    }
+
+    /// Evaluates right to left without operator precedence, like Octo
+    fn calc_expr(
+        &self,
+        open: &Token,
+        tokens: &[Token],
+        pos: &mut usize,
+    ) -> crate::errors::Result<i64> {
+        let lhs = self.calc_term(open, tokens, pos)?;
+        let op = match tokens.get(*pos) {
+            Some(op) if op.is(")") => return Ok(lhs),
+            Some(op) => op,
+            None => return Ok(lhs),
+        };
+        *pos += 1;
+        let rhs = self.calc_expr(open, tokens, pos)?;
+        let value = match op.text.as_str() {
+            "+" => lhs.checked_add(rhs),
+            "-" => lhs.checked_sub(rhs),
+            "*" => lhs.checked_mul(rhs),
+            "/" | "%" if rhs == 0 => return Err(op.error(ParseErrorKind::DivisionByZero)),
+            "/" => lhs.checked_div(rhs),
+            "%" => lhs.checked_rem(rhs),
+            "&" => Some(lhs & rhs),
+            "|" => Some(lhs | rhs),
+            "^" => Some(lhs ^ rhs),
+            "<<" => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shl(rhs)),
+            ">>" => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shr(rhs)),
+            "min" => Some(lhs.min(rhs)),
+            "max" => Some(lhs.max(rhs)),
+            "<" => Some((lhs < rhs).into()),
+            ">" => Some((lhs > rhs).into()),
+            "<=" => Some((lhs <= rhs).into()),
+            ">=" => Some((lhs >= rhs).into()),
+            "==" => Some((lhs == rhs).into()),
+            "!=" => Some((lhs != rhs).into()),
+            _ => return Err(op.error(ParseErrorKind::UnexpectedToken)),
+        };
+        value.ok_or_else(|| op.error(ParseErrorKind::ValueOutOfRange(lhs)))
+    }
+
+    fn calc_term(
+        &self,
+        open: &Token,
+        tokens: &[Token],
+        pos: &mut usize,
+    ) -> crate::errors::Result<i64> {
+        let token = tokens
+            .get(*pos)
+            .ok_or_else(|| open.error(ParseErrorKind::UnexpectedEnd))?;
+        *pos += 1;
+        match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expr(open, tokens, pos)?;
+                match tokens.get(*pos) {
+                    Some(close) if close.is(")") => {
+                        *pos += 1;
+                        Ok(value)
+                    }
+                    Some(other) => Err(other.error(ParseErrorKind::UnexpectedToken)),
+                    None => Err(open.error(ParseErrorKind::UnexpectedEnd)),
+                }
+            }
+            "-" => {
+                let value = self.calc_term(open, tokens, pos)?;
+                value
+                    .checked_neg()
+                    .ok_or_else(|| token.error(ParseErrorKind::ValueOutOfRange(value)))
+            }
+            "~" => Ok(!self.calc_term(open, tokens, pos)?),
+            "!" => Ok((self.calc_term(open, tokens, pos)? == 0).into()),
+            "HERE" => Ok(self.here.into()),
+            _ => self
+                .value(token)
+                .ok_or_else(|| token.error(ParseErrorKind::UndefinedSymbol(token.text.clone()))),
+        }
+    }
+
+    fn expand_macro(&mut self, token: &Token) -> crate::errors::Result<()> {
+        self.expansions += 1;
+        if self.expansions > MAX_MACRO_EXPANSIONS {
+            return Err(token.error(ParseErrorKind::RecursiveSymbol(token.text.clone())));
+        }
+
+        let params = self.macros[&token.text].params.clone();
+        let mut args = BTreeMap::new();
+        for param in params {
+            args.insert(param, self.next()?);
+        }
+        let body = &self.macros[&token.text].body;
+        let expanded: Vec<Token> = body
+            .iter()
+            .map(|token| {
+                args.get(&token.text)
+                    .cloned()
+                    .unwrap_or_else(|| token.clone())
+            })
+            .collect();
+        for token in expanded.into_iter().rev() {
+            self.tokens.push_front(token);
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> crate::errors::Result<()> {
+        use Instruction::*;
+
+        let token = self.next()?;
+        if let Some(vx) = self.to_register(&token) {
+            return self.register_statement(&token, vx);
+        }
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(name, self.here)?;
+            }
+            ":next" => {
+                let name = self.next()?;
+                let next = self
+                    .here
+                    .checked_add(1)
+                    .ok_or_else(|| name.error(ParseErrorKind::ValueOutOfRange(0x10000)))?;
+                self.define_label(name, next)?;
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let vx = self.register()?;
+                self.aliases.insert(name.text, vx);
+            }
+            ":const" => {
+                let name = self.next()?;
+                let (value, _) = self.number()?;
+                self.define_constant(name, value)?;
+            }
+            ":calc" => {
+                let name = self.next()?;
+                let value = self.calc()?;
+                self.define_constant(name, value)?;
+            }
+            ":byte" => {
+                let value = if self.peek_is("{") {
+                    self.calc()?
+                } else {
+                    self.number()?.0
+                };
+                if !(-0x80..=0xFF).contains(&value) {
+                    return Err(token.error(ParseErrorKind::ValueOutOfRange(value)));
+                }
+                self.emit_bytes(&token, &[value as u8])?;
+            }
+            ":org" => {
+                let (value, addr) = self.number()?;
+                match u16::try_from(value) {
+                    Ok(value) if value >= self.start => self.here = value,
+                    _ => return Err(addr.error(ParseErrorKind::ValueOutOfRange(value))),
+                }
+            }
+            ":macro" => {
+                let name = self.next()?;
+                let mut params = Vec::new();
+                loop {
+                    let param = self.next()?;
+                    if param.is("{") {
+                        break;
+                    }
+                    params.push(param.text);
+                }
+                let mut body = Vec::new();
+                let mut depth = 0;
+                loop {
+                    let token = self.next()?;
+                    if token.is("{") {
+                        depth += 1;
+                    } else if token.is("}") {
+                        if depth == 0 {
+                            break;
+                        }
+                        depth -= 1;
+                    }
+                    body.push(token);
+                }
+                self.macros.insert(name.text, Macro { params, body });
+            }
+            ":call" => self.emit_addr(false, Box::new(Call))?,
+            ":unpack" => {
+                let high = self.ranged(0x0, 0xF)? as u8;
+                let label = self.next()?;
+                self.tokens.push_front(label.clone());
+                self.emit_addr(
+                    false,
+                    Box::new(move |addr| {
+                        LoadOperand(VRegister::V0, high << 4 | (u16::from(addr) >> 8) as u8)
+                    }),
+                )?;
+                self.tokens.push_front(label);
+                self.emit_addr(
+                    false,
+                    Box::new(|addr| LoadOperand(VRegister::V1, u16::from(addr) as u8)),
+                )?;
+            }
+            ";" | "return" => self.emit(&token, Return)?,
+            "clear" => self.emit(&token, Clear)?,
+            "bcd" => {
+                let vx = self.register()?;
+                self.emit(&token, LoadBinaryCodedDecimal(vx))?;
+            }
+            "save" | "load" => {
+                let vx = self.register()?;
+                let instruction = if self.peek_is("-") {
+                    self.require_xo_chip(&token)?;
+                    self.next()?;
+                    let vy = self.register()?;
+                    if token.is("save") {
+                        SaveRange(vx, vy)
+                    } else {
+                        LoadRange(vx, vy)
+                    }
+                } else if token.is("save") {
+                    LoadMemoryRegisters(vx)
+                } else {
+                    LoadRegistersMemory(vx)
+                };
+                self.emit(&token, instruction)?;
+            }
+            "sprite" => {
+                let vx = self.register()?;
+                let vy = self.register()?;
+                let nibble = self.nibble()?;
+                self.emit(&token, Draw(vx, vy, nibble))?;
+            }
+            "jump" => self.emit_addr(false, Box::new(Jump))?,
+            "jump0" => self.emit_addr(false, Box::new(LongJump))?,
+            "native" => self.emit_addr(false, Box::new(Sys))?,
+            "hires" => self.emit(&token, HighResolution)?,
+            "lores" => self.emit(&token, LowResolution)?,
+            "scroll-down" => {
+                let nibble = self.nibble()?;
+                self.emit(&token, ScrollDown(nibble))?;
+            }
+            "scroll-left" => self.emit(&token, ScrollLeft)?,
+            "scroll-right" => self.emit(&token, ScrollRight)?,
+            "exit" => self.emit(&token, Exit)?,
+            "saveflags" => {
+                let vx = self.register()?;
+                self.emit(&token, StoreFlags(vx))?;
+            }
+            "loadflags" => {
+                let vx = self.register()?;
+                self.emit(&token, LoadFlags(vx))?;
+            }
+            "plane" => {
+                self.require_xo_chip(&token)?;
+                let nibble = self.nibble()?;
+                self.emit(&token, SelectPlanes(nibble))?;
+            }
+            "audio" => {
+                self.require_xo_chip(&token)?;
+                self.emit(&token, LoadAudio)?;
+            }
+            "pitch" => {
+                self.require_xo_chip(&token)?;
+                self.expect(":=")?;
+                let vx = self.register()?;
+                self.emit(&token, Pitch(vx))?;
+            }
+            "delay" => {
+                self.expect(":=")?;
+                let vx = self.register()?;
+                self.emit(&token, LoadDelayTimerRegister(vx))?;
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+                let vx = self.register()?;
+                self.emit(&token, LoadSoundTimerRegister(vx))?;
+            }
+            "i" => self.i_statement(&token)?,
+            "if" => {
+                let condition = self.condition()?;
+                let then = self.next()?;
+                match then.text.as_str() {
+                    "then" => self.emit_condition(&token, &condition, false)?,
+                    "begin" => {
+                        self.emit_condition(&token, &condition, true)?;
+                        let jump = self.emit_jump_placeholder(&token)?;
+                        self.blocks.push((Block::If(jump), token));
+                    }
+                    _ => return Err(then.error(ParseErrorKind::UnexpectedToken)),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some((Block::If(jump), _)) => {
+                    let end = self.emit_jump_placeholder(&token)?;
+                    self.patch(jump, Jump(self.here.into()));
+                    self.blocks.push((Block::Else(end), token));
+                }
+                _ => return Err(token.error(ParseErrorKind::UnbalancedBlock)),
+            },
+            "end" => match self.blocks.pop() {
+                Some((Block::If(jump), _)) | Some((Block::Else(jump), _)) => {
+                    self.patch(jump, Jump(self.here.into()));
+                }
+                _ => return Err(token.error(ParseErrorKind::UnbalancedBlock)),
+            },
+            "loop" => self
+                .blocks
+                .push((Block::Loop(self.here, Vec::new()), token)),
+            "while" => {
+                let condition = self.condition()?;
+                self.emit_condition(&token, &condition, true)?;
+                let jump = self.emit_jump_placeholder(&token)?;
+                match self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find_map(|(block, _)| match block {
+                        Block::Loop(_, breaks) => Some(breaks),
+                        _ => None,
+                    }) {
+                    Some(breaks) => breaks.push(jump),
+                    None => return Err(token.error(ParseErrorKind::UnbalancedBlock)),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some((Block::Loop(start, breaks), _)) => {
+                    self.emit(&token, Jump(start.into()))?;
+                    for jump in breaks {
+                        self.patch(jump, Jump(self.here.into()));
+                    }
+                }
+                _ => return Err(token.error(ParseErrorKind::UnbalancedBlock)),
+            },
+            // XO-CHIP scroll up is not supported by the instruction set
+            "scroll-up" => {
+                return Err(token.error(ParseErrorKind::UnknownMnemonic(token.text.clone())))
+            }
+            _ if self.macros.contains_key(&token.text) => self.expand_macro(&token)?,
+            _ => match parse_number(&token.text) {
+                Some(value) if (-0x80..=0xFF).contains(&value) => {
+                    self.emit_bytes(&token, &[value as u8])?
+                }
+                Some(value) => return Err(token.error(ParseErrorKind::ValueOutOfRange(value))),
+                None => {
+                    // a plain label calls the subroutine
+                    self.tokens.push_front(token);
+                    self.emit_addr(false, Box::new(Call))?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, token: &Token, vx: VRegister) -> crate::errors::Result<()> {
+        use Instruction::*;
+
+        let op = self.next()?;
+        let instruction = match op.text.as_str() {
+            ":=" => {
+                if self.peek_is("random") {
+                    self.next()?;
+                    Random(vx, self.byte()?)
+                } else if self.peek_is("key") {
+                    self.next()?;
+                    LoadKey(vx)
+                } else if self.peek_is("delay") {
+                    self.next()?;
+                    LoadRegisterDelayTimer(vx)
+                } else {
+                    match self.operand()? {
+                        Operand::Register(vy) => Load(vx, vy),
+                        Operand::Byte(byte) => LoadOperand(vx, byte),
+                    }
+                }
+            }
+            "+=" => match self.operand()? {
+                Operand::Register(vy) => Add(vx, vy),
+                Operand::Byte(byte) => AddOperand(vx, byte),
+            },
+            "-=" => match self.operand()? {
+                Operand::Register(vy) => Sub(vx, vy),
+                Operand::Byte(byte) => AddOperand(vx, byte.wrapping_neg()),
+            },
+            "=-" => SubNegated(vx, self.register()?),
+            "|=" => Or(vx, self.register()?),
+            "&=" => And(vx, self.register()?),
+            "^=" => XOr(vx, self.register()?),
+            ">>=" => ShiftRight(vx, self.register()?),
+            "<<=" => ShiftLeft(vx, self.register()?),
+            _ => return Err(op.error(ParseErrorKind::UnexpectedToken)),
+        };
+        self.emit(token, instruction)
+    }
+
+    fn i_statement(&mut self, token: &Token) -> crate::errors::Result<()> {
+        use Instruction::*;
+
+        let op = self.next()?;
+        match op.text.as_str() {
+            ":=" => {
+                if self.peek_is("hex") {
+                    self.next()?;
+                    let vx = self.register()?;
+                    self.emit(token, LoadSprite(vx))
+                } else if self.peek_is("bighex") {
+                    self.next()?;
+                    let vx = self.register()?;
+                    self.emit(token, LoadBigSprite(vx))
+                } else if self.peek_is("long") {
+                    self.require_xo_chip(token)?;
+                    self.next()?;
+                    self.emit_addr(true, Box::new(LoadILong))
+                } else {
+                    self.emit_addr(false, Box::new(LoadI))
+                }
+            }
+            "+=" => {
+                let vx = self.register()?;
+                self.emit(token, AddI(vx))
+            }
+            _ => Err(op.error(ParseErrorKind::UnexpectedToken)),
+        }
+    }
+
+    fn finish(mut self) -> crate::errors::Result<Vec<u8>> {
+        if let Some((_, token)) = self.blocks.pop() {
+            return Err(token.error(ParseErrorKind::UnbalancedBlock));
+        }
+
+        let main = match self.labels.get("main") {
+            Some(main) => *main,
+            None => {
+                return Err(Chip8Error::Parse {
+                    line: 1,
+                    column: 1,
+                    kind: ParseErrorKind::UndefinedSymbol("main".into()),
+                })
+            }
+        };
+        if main != self.start {
+            self.patch(0, Instruction::Jump(main.into()));
+        }
+
+        for pending in core::mem::take(&mut self.pending) {
+            let value = match self.value(&pending.token) {
+                Some(value) => value,
+                None => {
+                    return Err(pending
+                        .token
+                        .error(ParseErrorKind::UndefinedSymbol(pending.token.text.clone())))
+                }
+            };
+            let addr = to_addr(&pending.token, value, pending.long)?;
+            self.patch(pending.offset, (pending.make)(addr));
+        }
+
+        Ok(self.rom)
+    }
+}
+
+fn to_addr(token: &Token, value: i64, long: bool) -> crate::errors::Result<Addr> {
+    let max = if long { 0xFFFF } else { 0xFFF };
+    if (0..=max).contains(&value) {
+        Ok(Addr::long(value as u16))
+    } else {
+        Err(token.error(ParseErrorKind::ValueOutOfRange(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_main_first() -> crate::errors::Result<()> {
+        let rom = compile(": main\n  clear\n  v0 := 0x12 # comment\n  loop again")?;
+
+        assert_eq!(rom, [0x00, 0xE0, 0x60, 0x12, 0x12, 0x04]);
+        Ok(())
+    }
+
+    #[test]
+    fn compile_jump_to_main() -> crate::errors::Result<()> {
+        let rom = compile(": sub return\n: main sub")?;
+
+        assert_eq!(rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+        Ok(())
+    }
+
+    #[test]
+    fn compile_forward_label_alias_const() -> crate::errors::Result<()> {
+        let rom = compile(
+            ":alias x v3\n\
+             :const SIZE 5\n\
+             : main\n\
+               i := sprite\n\
+               sprite x x SIZE\n\
+             : sprite\n\
+               0xFF 0x81 0x81 0x81 0xFF",
+        )?;
+
+        assert_eq!(rom, [0xA2, 0x04, 0xD3, 0x35, 0xFF, 0x81, 0x81, 0x81, 0xFF]);
+        Ok(())
+    }
+
+    #[test]
+    fn compile_if_then() -> crate::errors::Result<()> {
+        let rom = compile(
+            ": main\n\
+               if v0 == 1 then v1 := 2\n\
+               if v0 key then v1 += v2\n\
+               if v0 < v1 then clear",
+        )?;
+
+        assert_eq!(
+            rom,
+            [
+                0x40, 0x01, 0x61, 0x02, // if v0 == 1 then
+                0xE0, 0xA1, 0x81, 0x24, // if v0 key then
+                0x8F, 0x00, 0x8F, 0x15, 0x4F, 0x00, 0x00, 0xE0, // if v0 < v1 then
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn compile_if_comparisons_run() -> crate::errors::Result<()> {
+        use crate::random::XorShift;
+        use crate::vm::VM;
+
+        for rhs in ["v1", "2"] {
+            for (op, holds) in [
+                ("<", [true, false, false]),
+                (">", [false, false, true]),
+                ("<=", [true, true, false]),
+                (">=", [false, true, true]),
+            ] {
+                for (lhs, expected) in (1..=3).zip(holds) {
+                    let rom = compile(&alloc::format!(
+                        ": main v0 := {} v1 := 2 v2 := 0 if v0 {} {} then v2 := 1 loop again",
+                        lhs,
+                        op,
+                        rhs
+                    ))?;
+                    let mut vm = VM::new(XorShift::default(), |_, _| Ok(()));
+                    vm.load_rom(&rom)?;
+                    for _ in 0..10 {
+                        vm.step()?;
+                    }
+
+                    assert_eq!(
+                        vm.register(VRegister::V2),
+                        u8::from(expected),
+                        "v0 {} {} with v0 = {}",
+                        op,
+                        rhs,
+                        lhs
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn compile_if_begin_else_end() -> crate::errors::Result<()> {
+        let rom = compile(
+            ": main\n\
+               if v0 != 0 begin\n\
+                 v1 := 1\n\
+               else\n\
+                 v1 := 2\n\
+               end",
+        )?;
+
+        assert_eq!(
+            rom,
+            [
+                0x40, 0x00, 0x12, 0x08, // if v0 != 0 begin
+                0x61, 0x01, 0x12, 0x0A, // else
+                0x61, 0x02, // end
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn compile_loop_while() -> crate::errors::Result<()> {
+        let rom = compile(
+            ": main\n\
+               loop\n\
+                 v0 += 1\n\
+                 while v0 != 10\n\
+               again",
+        )?;
+
+        assert_eq!(rom, [0x70, 0x01, 0x40, 0x0A, 0x12, 0x08, 0x12, 0x00]);
+        Ok(())
+    }
+
+    #[test]
+    fn compile_macro_calc() -> crate::errors::Result<()> {
+        let rom = compile(
+            ":macro set reg value { reg := value }\n\
+             :calc TWELVE { 2 * 4 + 2 }\n\
+             : main\n\
+               set v4 TWELVE\n\
+               :byte { ( 1 << 4 ) - 1 }",
+        )?;
+
+        // right to left without precedence, i.e. 2 * ( 4 + 2 )
+        assert_eq!(rom, [0x64, 0x0C, 0x0F]);
+        Ok(())
+    }
+
+    #[test]
+    fn compile_xo_chip() -> crate::errors::Result<()> {
+        let source = ": main\n  plane 3\n  i := long data\n  save v1 - v2\n: data 1";
+
+        assert_eq!(
+            compile(source),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 3,
+                kind: ParseErrorKind::RequiresXoChip
+            })
+        );
+
+        let config = Config {
+            xo_chip: true,
+            ..Config::default()
+        };
+        let rom = compile_with_config(source, config)?;
+        assert_eq!(rom, [0xF3, 0x01, 0xF0, 0x00, 0x02, 0x08, 0x51, 0x22, 0x01]);
+        Ok(())
+    }
+
+    #[test]
+    fn compile_errors() {
+        assert_eq!(
+            compile("clear"),
+            Err(Chip8Error::Parse {
+                line: 1,
+                column: 1,
+                kind: ParseErrorKind::UndefinedSymbol("main".into())
+            })
+        );
+        assert_eq!(
+            compile(": main\n  jump nowhere"),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 8,
+                kind: ParseErrorKind::UndefinedSymbol("nowhere".into())
+            })
+        );
+        assert_eq!(
+            compile(": main\n  v0 := 256"),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 9,
+                kind: ParseErrorKind::ValueOutOfRange(256)
+            })
+        );
+        assert_eq!(
+            compile(": main\n  loop"),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 3,
+                kind: ParseErrorKind::UnbalancedBlock
+            })
+        );
+        assert_eq!(
+            compile(": main\n  v0 :="),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 6,
+                kind: ParseErrorKind::UnexpectedEnd
+            })
+        );
+        assert!(matches!(
+            compile(": main\n:org 0xFFFE 1 2 3"),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 15,
+                kind: ParseErrorKind::ValueOutOfRange(_)
+            })
+        ));
+        assert_eq!(
+            compile(": main\n:org 0xFFFF :next x"),
+            Err(Chip8Error::Parse {
+                line: 2,
+                column: 19,
+                kind: ParseErrorKind::ValueOutOfRange(0x10000)
+            })
+        );
+    }
+}
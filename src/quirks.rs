@@ -0,0 +1,100 @@
+//! Behaviour of ambiguous instructions
+//!
+//! Several CHIP-8 instructions behave differently on the various platforms and interpreters.
+//! A [`Quirks`] profile selects one interpretation for each of them.
+
+use crate::display::EdgeMode;
+
+/// When `LD Vx, K` (`Fx0A`) stops waiting for a key
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyWait {
+    /// Stop waiting as soon as a key is pressed
+    #[default]
+    Press,
+    /// Stop waiting once a key is pressed and released again, like the original COSMAC VIP
+    Release,
+}
+
+/// How `LD [I], Vx` (`Fx55`) and `LD Vx, [I]` (`Fx65`) change `I`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemoryIncrement {
+    /// `I` is set to `I + x + 1`, like the original COSMAC VIP
+    #[default]
+    XPlusOne,
+    /// `I` is set to `I + x`, like CHIP-48
+    X,
+    /// `I` is left unchanged, like SUPER-CHIP
+    Unchanged,
+}
+
+/// Profile of platform specific instruction behaviour
+///
+/// The [`Default`] is the same as [`Quirks::XO_CHIP`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// `SHR Vx {, Vy}` (`8xy6`) and `SHL Vx {, Vy}` (`8xyE`) shift `Vx` in place instead of `Vy`
+    pub shift_vx: bool,
+
+    /// How `LD [I], Vx` and `LD Vx, [I]` change `I`
+    pub memory_increment: MemoryIncrement,
+
+    /// `JP V0, addr` (`Bnnn`) jumps to `nnn + Vx` instead of `nnn + V0`
+    pub jump_vx: bool,
+
+    /// `OR`, `AND` and `XOR` (`8xy1`, `8xy2`, `8xy3`) reset `VF` to `0`
+    pub logic_resets_vf: bool,
+
+    /// When waiting for a key with `LD Vx, K` ends
+    pub key_wait: KeyWait,
+
+    /// Whether sprites are clipped or wrapped at the edge of the display
+    pub edge_mode: EdgeMode,
+}
+
+impl Quirks {
+    /// Original CHIP-8 interpreter on the COSMAC VIP
+    pub const COSMAC_VIP: Self = Self {
+        shift_vx: false,
+        memory_increment: MemoryIncrement::XPlusOne,
+        jump_vx: false,
+        logic_resets_vf: true,
+        key_wait: KeyWait::Release,
+        edge_mode: EdgeMode::Clip,
+    };
+
+    /// CHIP-48 interpreter on the HP-48 calculators
+    pub const CHIP_48: Self = Self {
+        shift_vx: true,
+        memory_increment: MemoryIncrement::X,
+        jump_vx: true,
+        logic_resets_vf: false,
+        key_wait: KeyWait::Press,
+        edge_mode: EdgeMode::Clip,
+    };
+
+    /// SUPER-CHIP 1.1 interpreter on the HP-48 calculators
+    pub const SUPER_CHIP_1_1: Self = Self {
+        shift_vx: true,
+        memory_increment: MemoryIncrement::Unchanged,
+        jump_vx: true,
+        logic_resets_vf: false,
+        key_wait: KeyWait::Press,
+        edge_mode: EdgeMode::Clip,
+    };
+
+    /// XO-CHIP as implemented by Octo
+    pub const XO_CHIP: Self = Self {
+        shift_vx: false,
+        memory_increment: MemoryIncrement::XPlusOne,
+        jump_vx: false,
+        logic_resets_vf: false,
+        key_wait: KeyWait::Press,
+        edge_mode: EdgeMode::Wrap,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::XO_CHIP
+    }
+}
@@ -0,0 +1,90 @@
+//! Random number generation
+//!
+//! The [`VM`](crate::vm::VM) draws the random bytes of `RND Vx, byte` (`Cxkk`) from a [`RandomSource`].
+//! [`XorShift`] is a small deterministic generator that needs neither `std` nor `rand`.
+//!
+//! # Features
+//!
+//! Feature `rand` implements `RandomSource` for every [`rand::RngCore`](https://docs.rs/rand/0.8/rand/trait.RngCore.html),
+//! e.g. `rand::rngs::ThreadRng` or `rand::rngs::StdRng`.
+
+/// Source of random bytes for a [`VM`](crate::vm::VM)
+pub trait RandomSource {
+    /// Returns the next random byte
+    fn random_byte(&mut self) -> u8;
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+#[cfg(feature = "rand")]
+impl<R: rand::RngCore> RandomSource for R {
+    fn random_byte(&mut self) -> u8 {
+        rand::Rng::gen(self)
+    }
+}
+
+/// Seedable xorshift generator, see [Marsaglia, "Xorshift RNGs"](https://doi.org/10.18637/jss.v008.i14)
+///
+/// Not suitable for anything but games, but the same seed always yields the same bytes on every platform.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    /// Seed of [`XorShift::default`]
+    pub const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+    /// Creates a new instance from `seed`, a seed of `0` is replaced by [`XorShift::DEFAULT_SEED`]
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        let state = if seed == 0 { Self::DEFAULT_SEED } else { seed };
+        Self { state }
+    }
+
+    /// Returns the next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+impl Default for XorShift {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SEED)
+    }
+}
+
+impl RandomSource for XorShift {
+    /// Returns the most significant byte, which is the most random one
+    fn random_byte(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xorshift_deterministic() {
+        let mut first = XorShift::new(42);
+        let mut second = XorShift::new(42);
+        let bytes: alloc::vec::Vec<u8> = (0..16).map(|_| first.random_byte()).collect();
+        for byte in &bytes {
+            assert_eq!(second.random_byte(), *byte);
+        }
+        assert!(bytes.iter().any(|byte| *byte != bytes[0]));
+
+        assert_eq!(XorShift::new(0), XorShift::default());
+        assert_ne!(XorShift::default().next_u64(), 0);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn rand_adapter() {
+        let mut rng = rand::rngs::mock::StepRng::new(4, 0);
+        assert_eq!(rng.random_byte(), 4);
+    }
+}
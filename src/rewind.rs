@@ -0,0 +1,331 @@
+//! Rewind
+//!
+//! A [`Rewind`] buffer records a [`Snapshot`] per frame and hands them back in reverse.
+//! Every [`Config::keyframe_interval`] frames a full keyframe is stored,
+//! the frames in between only store the bytes that differ from their keyframe.
+//!
+//! ```
+//! use chip_8::rewind::Rewind;
+//! use chip_8::random::XorShift;
+//! use chip_8::vm::VM;
+//!
+//! let mut vm = VM::new(XorShift::default(), |_, _| Ok(()));
+//! let mut rewind = Rewind::new();
+//! for _ in 0..3 {
+//!     // run a frame, then record it
+//!     vm.tick_timers();
+//!     rewind.push(&vm.snapshot());
+//! }
+//!
+//! if let Some(snapshot) = rewind.step_back() {
+//!     vm.restore(&snapshot)?;
+//! }
+//! # Ok::<(), chip_8::errors::Chip8Error>(())
+//! ```
+
+use crate::snapshot::Snapshot;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// Configuration of a [`Rewind`] buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Number of frames that can be rewound at least
+    ///
+    /// Up to [`keyframe_interval`](Self::keyframe_interval) more frames are kept,
+    /// since the oldest frames are dropped together with their keyframe.
+    pub capacity: usize,
+
+    /// Number of frames from one keyframe to the next
+    ///
+    /// Larger values need less memory, but make each frame more expensive to record and restore.
+    pub keyframe_interval: usize,
+}
+
+impl Config {
+    /// Default for [`capacity`](Self::capacity), 10 seconds at 60 frames per second
+    pub const DEFAULT_CAPACITY: usize = 600;
+
+    /// Default for [`keyframe_interval`](Self::keyframe_interval), 1 second at 60 frames per second
+    pub const DEFAULT_KEYFRAME_INTERVAL: usize = 60;
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            capacity: Self::DEFAULT_CAPACITY,
+            keyframe_interval: Self::DEFAULT_KEYFRAME_INTERVAL,
+        }
+    }
+}
+
+/// Bytes of a serialized [`Snapshot`] that differ from its keyframe
+#[derive(Debug)]
+struct Delta {
+    /// Length of the serialized snapshot
+    len: usize,
+    /// Offsets and bytes of the changed ranges
+    runs: Vec<(usize, Vec<u8>)>,
+}
+
+impl Delta {
+    fn new(keyframe: &[u8], bytes: &[u8]) -> Self {
+        let mut runs: Vec<(usize, Vec<u8>)> = Vec::new();
+        for (offset, byte) in bytes.iter().enumerate() {
+            if keyframe.get(offset) == Some(byte) {
+                continue;
+            }
+            match runs.last_mut() {
+                Some((start, run)) if *start + run.len() == offset => run.push(*byte),
+                _ => runs.push((offset, alloc::vec![*byte])),
+            }
+        }
+        Self {
+            len: bytes.len(),
+            runs,
+        }
+    }
+
+    fn apply(&self, keyframe: &[u8]) -> Vec<u8> {
+        let mut bytes = keyframe.to_vec();
+        bytes.resize(self.len, 0);
+        for (offset, run) in &self.runs {
+            bytes[*offset..*offset + run.len()].copy_from_slice(run);
+        }
+        bytes
+    }
+
+    /// Returns the number of stored bytes, ignoring bookkeeping
+    fn size(&self) -> usize {
+        self.runs.iter().map(|(_, run)| run.len()).sum()
+    }
+}
+
+#[derive(Debug)]
+enum Frame {
+    Keyframe(Vec<u8>),
+    Delta(Delta),
+}
+
+/// Ring buffer of the most recent frames
+#[derive(Debug)]
+pub struct Rewind {
+    config: Config,
+    frames: VecDeque<Frame>,
+    /// Index of the most recent keyframe in `frames`
+    keyframe: usize,
+}
+
+impl Rewind {
+    /// Creates a new empty instance with default [`Config`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Creates a new empty instance with the given `config`
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`Config::keyframe_interval`] is `0`.
+    #[must_use]
+    pub fn with_config(config: Config) -> Self {
+        assert!(config.keyframe_interval > 0, "keyframe interval is 0");
+        Self {
+            config,
+            frames: VecDeque::new(),
+            keyframe: 0,
+        }
+    }
+
+    /// Returns the number of recorded frames
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns whether no frames are recorded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the number of bytes used by the recorded frames, ignoring bookkeeping
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.frames
+            .iter()
+            .map(|frame| match frame {
+                Frame::Keyframe(bytes) => bytes.len(),
+                Frame::Delta(delta) => delta.size(),
+            })
+            .sum()
+    }
+
+    /// Removes all recorded frames
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.keyframe = 0;
+    }
+
+    /// Records the `snapshot` as the most recent frame
+    ///
+    /// Drops the oldest frames if more than [`Config::capacity`] are recorded.
+    pub fn push(&mut self, snapshot: &Snapshot) {
+        let bytes = snapshot.to_bytes();
+        if self.is_empty() || self.frames.len() - self.keyframe >= self.config.keyframe_interval {
+            self.keyframe = self.frames.len();
+            self.frames.push_back(Frame::Keyframe(bytes));
+        } else {
+            let delta = match &self.frames[self.keyframe] {
+                Frame::Keyframe(keyframe) => Delta::new(keyframe, &bytes),
+                Frame::Delta(_) => unreachable!("index of the most recent keyframe is a delta"),
+            };
+            self.frames.push_back(Frame::Delta(delta));
+        }
+
+        // the oldest frames can only be dropped up to the next keyframe
+        while let Some(next) = self.next_keyframe() {
+            if self.frames.len() - next < self.config.capacity {
+                break;
+            }
+            self.frames.drain(..next);
+            self.keyframe -= next;
+        }
+    }
+
+    /// Returns the index of the first keyframe after the oldest frame
+    fn next_keyframe(&self) -> Option<usize> {
+        self.frames
+            .iter()
+            .skip(1)
+            .position(|frame| matches!(frame, Frame::Keyframe(_)))
+            .map(|idx| idx + 1)
+    }
+
+    /// Returns the [`Snapshot`] of the most recent frame
+    #[must_use]
+    pub fn latest(&self) -> Option<Snapshot> {
+        self.frames.len().checked_sub(1).map(|idx| self.get(idx))
+    }
+
+    /// Drops the most recent frame and returns the [`Snapshot`] of the frame before it
+    ///
+    /// Returns `None` if there is no frame before it, without dropping anything.
+    pub fn step_back(&mut self) -> Option<Snapshot> {
+        self.jump_back(1)
+    }
+
+    /// Drops the `frames` most recent frames and returns the [`Snapshot`] of the frame before them
+    ///
+    /// That frame becomes the most recent frame, so recording continues from there.
+    /// Returns `None` if fewer frames are recorded, without dropping anything.
+    pub fn jump_back(&mut self, frames: usize) -> Option<Snapshot> {
+        let idx = self.frames.len().checked_sub(frames)?.checked_sub(1)?;
+        self.frames.truncate(idx + 1);
+        self.keyframe = self.keyframe.min(idx);
+        while !matches!(self.frames[self.keyframe], Frame::Keyframe(_)) {
+            self.keyframe -= 1;
+        }
+        Some(self.get(idx))
+    }
+
+    /// Returns the [`Snapshot`] of the frame at `idx`, which must exist
+    fn get(&self, idx: usize) -> Snapshot {
+        let bytes = match &self.frames[idx] {
+            Frame::Keyframe(bytes) => bytes.clone(),
+            Frame::Delta(delta) => {
+                let keyframe = self
+                    .frames
+                    .iter()
+                    .take(idx)
+                    .rev()
+                    .find_map(|frame| match frame {
+                        Frame::Keyframe(bytes) => Some(bytes),
+                        Frame::Delta(_) => None,
+                    })
+                    .expect("oldest frame is a keyframe");
+                delta.apply(keyframe)
+            }
+        };
+        Snapshot::from_bytes(&bytes).expect("recorded snapshot is valid")
+    }
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::XorShift;
+    use crate::vm::VM;
+
+    fn test_snapshots(count: u8) -> Vec<Snapshot> {
+        let rng = XorShift::default();
+        let mut vm = VM::new(rng, |_, _| Ok(()));
+        // ADD V0, 1; JP 0x200
+        vm.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        (0..count)
+            .map(|_| {
+                vm.step().unwrap();
+                vm.snapshot()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rewind_step_back() {
+        let snapshots = test_snapshots(5);
+        let mut rewind = Rewind::with_config(Config {
+            capacity: 10,
+            keyframe_interval: 2,
+        });
+        for snapshot in &snapshots {
+            rewind.push(snapshot);
+        }
+
+        assert_eq!(rewind.latest().as_ref(), Some(&snapshots[4]));
+        assert_eq!(rewind.step_back().as_ref(), Some(&snapshots[3]));
+        assert_eq!(rewind.jump_back(2).as_ref(), Some(&snapshots[1]));
+        assert_eq!(rewind.len(), 2);
+        assert_eq!(rewind.jump_back(2), None);
+        assert_eq!(rewind.len(), 2);
+
+        rewind.push(&snapshots[4]);
+        assert_eq!(rewind.latest().as_ref(), Some(&snapshots[4]));
+        assert_eq!(rewind.step_back().as_ref(), Some(&snapshots[1]));
+    }
+
+    #[test]
+    fn rewind_capacity() {
+        let snapshots = test_snapshots(20);
+        let mut rewind = Rewind::with_config(Config {
+            capacity: 5,
+            keyframe_interval: 3,
+        });
+        for snapshot in &snapshots {
+            rewind.push(snapshot);
+            assert!(rewind.len() < 5 + 3);
+        }
+
+        assert!(rewind.len() >= 5);
+        assert_eq!(rewind.jump_back(4).as_ref(), Some(&snapshots[15]));
+    }
+
+    #[test]
+    fn rewind_deltas_are_small() {
+        let snapshots = test_snapshots(2);
+        let mut rewind = Rewind::new();
+
+        rewind.push(&snapshots[0]);
+        let keyframe = rewind.size();
+        rewind.push(&snapshots[1]);
+
+        // only V0 and the program counter change
+        assert!(rewind.size() - keyframe <= 4);
+    }
+}
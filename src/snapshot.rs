@@ -0,0 +1,348 @@
+//! Save states
+//!
+//! A [`Snapshot`] holds the complete state of a [`VM`](crate::vm::VM), see
+//! [`VM::snapshot`](crate::vm::VM::snapshot) and [`VM::restore`](crate::vm::VM::restore).
+//! The random number generator, the [`Config`](crate::vm::Config) and the
+//! [`Beeper`](crate::beeper::Beeper) are not part of it.
+//!
+//! # Format
+//!
+//! [`Snapshot::to_bytes`] writes version [`Snapshot::VERSION`] of this binary format.
+//! All numbers are little endian.
+//!
+//! | Size         | Content                                                                      |
+//! |--------------|------------------------------------------------------------------------------|
+//! | 4            | Magic [`Snapshot::MAGIC`]                                                    |
+//! | 2            | Format version                                                               |
+//! | 16           | Registers `V0`..`VF`                                                         |
+//! | 2            | Register `I`                                                                 |
+//! | 2            | Program counter                                                              |
+//! | 2            | Number `n` of return addresses on the stack                                  |
+//! | 2 * `n`      | Return addresses, bottom to top                                              |
+//! | 1            | Delay timer                                                                  |
+//! | 1            | Sound timer                                                                  |
+//! | 8            | Number of timer ticks                                                        |
+//! | 2            | Keypad, bit `k` is set if key `k` is pressed                                 |
+//! | 1            | Register waiting for a key press `0x0`..`0xF`, `0xFF` if not waiting         |
+//! | 1            | Key pressed while waiting for its release `0x0`..`0xF`, `0xFF` if none       |
+//! | 1            | `1` if halted, `0` otherwise                                                 |
+//! | 16           | RPL user flags                                                               |
+//! | 16           | XO-CHIP audio pattern buffer                                                 |
+//! | 1            | XO-CHIP pitch                                                                |
+//! | 4            | Size `m` of the memory in bytes                                              |
+//! | `m`          | Memory                                                                       |
+//! | 1            | Resolution, `0` for low, `1` for high                                        |
+//! | 1            | Selected bitplanes, bit `0` for the first, bit `1` for the second            |
+//! | 1            | Edge mode, `0` for clip, `1` for wrap                                        |
+//! | 2 * 1024     | Bitplanes in high resolution, row by row, 16 bytes per row, leftmost pixel in the most significant bit |
+
+use crate::display::{Display, EdgeMode, Planes, Resolution};
+use crate::errors::Chip8Error;
+use crate::instructions::VRegister;
+use crate::keypad::Key;
+use crate::memory::Memory;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Marker for "none" of an optional register or key
+const NONE: u8 = 0xFF;
+
+/// Size of the packed bitplanes in bytes
+pub(crate) const DISPLAY_BITS_SIZE: usize =
+    Display::PLANES * Display::HIGH_RES_WIDTH * Display::HIGH_RES_HEIGHT / 8;
+
+/// State of the [`Display`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DisplayState {
+    pub(crate) resolution: Resolution,
+    pub(crate) selected: Planes,
+    pub(crate) edge_mode: EdgeMode,
+    /// Bitplanes in the packed format described in the module documentation
+    pub(crate) bits: Vec<u8>,
+}
+
+/// Complete state of a [`VM`](crate::vm::VM)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub(crate) vregisters: [u8; 16],
+    pub(crate) i: u16,
+    pub(crate) pc: u16,
+    pub(crate) stack: Vec<u16>,
+    pub(crate) delay: u8,
+    pub(crate) sound: u8,
+    pub(crate) ticks: u64,
+    pub(crate) keys: u16,
+    pub(crate) waiting_on_any_keypress: Option<VRegister>,
+    pub(crate) pressed_while_waiting: Option<Key>,
+    pub(crate) halted: bool,
+    pub(crate) rpl_flags: [u8; 16],
+    pub(crate) audio_pattern: [u8; 16],
+    pub(crate) pitch: u8,
+    pub(crate) memory: Vec<u8>,
+    pub(crate) display: DisplayState,
+}
+
+impl Snapshot {
+    /// Magic bytes at the start of the binary format
+    pub const MAGIC: [u8; 4] = *b"C8SS";
+
+    /// Version of the binary format written by [`to_bytes`](Self::to_bytes)
+    pub const VERSION: u16 = 1;
+
+    /// Serializes into the binary format described in the [module documentation](self)
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u16::MAX` return addresses on the stack.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(128 + self.memory.len() + DISPLAY_BITS_SIZE);
+        bytes.extend_from_slice(&Self::MAGIC);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.vregisters);
+        bytes.extend_from_slice(&self.i.to_le_bytes());
+        bytes.extend_from_slice(&self.pc.to_le_bytes());
+        let stack = u16::try_from(self.stack.len()).expect("at most u16::MAX return addresses");
+        bytes.extend_from_slice(&stack.to_le_bytes());
+        for addr in &self.stack {
+            bytes.extend_from_slice(&addr.to_le_bytes());
+        }
+        bytes.push(self.delay);
+        bytes.push(self.sound);
+        bytes.extend_from_slice(&self.ticks.to_le_bytes());
+        bytes.extend_from_slice(&self.keys.to_le_bytes());
+        bytes.push(self.waiting_on_any_keypress.map_or(NONE, |vx| vx as u8));
+        bytes.push(self.pressed_while_waiting.map_or(NONE, |key| key as u8));
+        bytes.push(self.halted.into());
+        bytes.extend_from_slice(&self.rpl_flags);
+        bytes.extend_from_slice(&self.audio_pattern);
+        bytes.push(self.pitch);
+        bytes.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.memory);
+        bytes.push(match self.display.resolution {
+            Resolution::Low => 0,
+            Resolution::High => 1,
+        });
+        bytes.push(self.display.selected.bits());
+        bytes.push(match self.display.edge_mode {
+            EdgeMode::Clip => 0,
+            EdgeMode::Wrap => 1,
+        });
+        bytes.extend_from_slice(&self.display.bits);
+        bytes
+    }
+
+    /// Deserializes from the binary format described in the [module documentation](self)
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::UnsupportedSnapshotVersion`] if `bytes` are of another format version,
+    /// or [`Chip8Error::InvalidSnapshot`] if they are malformed otherwise.
+    pub fn from_bytes(bytes: &[u8]) -> crate::errors::Result<Self> {
+        let mut reader = Reader::new(bytes, || Chip8Error::InvalidSnapshot);
+        if reader.take(Self::MAGIC.len())? != Self::MAGIC {
+            return Err(Chip8Error::InvalidSnapshot);
+        }
+        let version = reader.u16()?;
+        if version != Self::VERSION {
+            return Err(Chip8Error::UnsupportedSnapshotVersion(version));
+        }
+
+        let vregisters = reader.array()?;
+        let i = reader.u16()?;
+        let pc = reader.u16()?;
+        let stack_len = reader.u16()?;
+        let stack = (0..stack_len)
+            .map(|_| reader.u16())
+            .collect::<crate::errors::Result<_>>()?;
+        let delay = reader.u8()?;
+        let sound = reader.u8()?;
+        let ticks = reader.u64()?;
+        let keys = reader.u16()?;
+        let waiting_on_any_keypress = match reader.u8()? {
+            NONE => None,
+            vx => Some(VRegister::try_from(vx).map_err(|_| Chip8Error::InvalidSnapshot)?),
+        };
+        let pressed_while_waiting = match reader.u8()? {
+            NONE => None,
+            key => Some(Key::try_from(key).map_err(|_| Chip8Error::InvalidSnapshot)?),
+        };
+        let halted = match reader.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(Chip8Error::InvalidSnapshot),
+        };
+        let rpl_flags = reader.array()?;
+        let audio_pattern = reader.array()?;
+        let pitch = reader.u8()?;
+        let memory_size = reader.u32()? as usize;
+        if memory_size > Memory::XO_CHIP_SIZE {
+            return Err(Chip8Error::InvalidSnapshot);
+        }
+        let memory = reader.take(memory_size)?.to_vec();
+        let resolution = match reader.u8()? {
+            0 => Resolution::Low,
+            1 => Resolution::High,
+            _ => return Err(Chip8Error::InvalidSnapshot),
+        };
+        let selected = match reader.u8()? {
+            bits @ 0..=0b11 => Planes::new(bits),
+            _ => return Err(Chip8Error::InvalidSnapshot),
+        };
+        let edge_mode = match reader.u8()? {
+            0 => EdgeMode::Clip,
+            1 => EdgeMode::Wrap,
+            _ => return Err(Chip8Error::InvalidSnapshot),
+        };
+        let bits = reader.take(DISPLAY_BITS_SIZE)?.to_vec();
+        reader.finish()?;
+
+        Ok(Self {
+            vregisters,
+            i,
+            pc,
+            stack,
+            delay,
+            sound,
+            ticks,
+            keys,
+            waiting_on_any_keypress,
+            pressed_while_waiting,
+            halted,
+            rpl_flags,
+            audio_pattern,
+            pitch,
+            memory,
+            display: DisplayState {
+                resolution,
+                selected,
+                edge_mode,
+                bits,
+            },
+        })
+    }
+}
+
+/// Reads little endian numbers from the front of `bytes`
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    invalid: fn() -> Chip8Error,
+}
+
+impl<'a> Reader<'a> {
+    /// Creates a new instance that returns `invalid` if `bytes` are too short or too long
+    pub(crate) fn new(bytes: &'a [u8], invalid: fn() -> Chip8Error) -> Self {
+        Self { bytes, invalid }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> crate::errors::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err((self.invalid)());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> crate::errors::Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self) -> crate::errors::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> crate::errors::Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> crate::errors::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> crate::errors::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Returns `true` if all bytes have been read
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns an error unless all bytes have been read
+    pub(crate) fn finish(self) -> crate::errors::Result<()> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err((self.invalid)())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn test_snapshot() -> Snapshot {
+        let mut bits = vec![0; DISPLAY_BITS_SIZE];
+        bits[0] = 0b1000_0000;
+        Snapshot {
+            vregisters: [0x11; 16],
+            i: 0x0ABC,
+            pc: 0x0202,
+            stack: vec![0x0204, 0x0300],
+            delay: 3,
+            sound: 4,
+            ticks: 5,
+            keys: 0b1000_0000_0000_0001,
+            waiting_on_any_keypress: Some(VRegister::V7),
+            pressed_while_waiting: Some(Key::KeyA),
+            halted: false,
+            rpl_flags: [0x22; 16],
+            audio_pattern: [0x33; 16],
+            pitch: 64,
+            memory: vec![0x44; Memory::SIZE],
+            display: DisplayState {
+                resolution: Resolution::High,
+                selected: Planes::BOTH,
+                edge_mode: EdgeMode::Clip,
+                bits,
+            },
+        }
+    }
+
+    #[test]
+    fn snapshot_bytes_roundtrip() -> crate::errors::Result<()> {
+        let snapshot = test_snapshot();
+
+        let bytes = snapshot.to_bytes();
+
+        assert_eq!(bytes[..6], [b'C', b'8', b'S', b'S', 0x01, 0x00]);
+        assert_eq!(Snapshot::from_bytes(&bytes)?, snapshot);
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_from_bytes_invalid() {
+        let bytes = test_snapshot().to_bytes();
+
+        assert_eq!(
+            Snapshot::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Chip8Error::InvalidSnapshot)
+        );
+        assert_eq!(
+            Snapshot::from_bytes(b"ROM!\x01\x00"),
+            Err(Chip8Error::InvalidSnapshot)
+        );
+
+        let mut newer = bytes;
+        newer[4] = 0x02;
+        assert_eq!(
+            Snapshot::from_bytes(&newer),
+            Err(Chip8Error::UnsupportedSnapshotVersion(2))
+        );
+    }
+}
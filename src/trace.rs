@@ -0,0 +1,853 @@
+//! Execution tracing
+//!
+//! A [`Tracer`] steps a [`VM`] and records a [`TraceEntry`] for every executed instruction
+//! with its address, raw opcode, the V registers before and after, `I` and the memory written.
+//! Entries are encoded in a [`Format`] into an output buffer, which the host drains
+//! with [`Tracer::take_output`], e.g. to append it to a file.
+//!
+//! Filters restrict tracing to ranges of the program counter or kinds of instructions.
+//!
+//! A [`Comparator`] replays a [`Movie`] into a `VM` and reports the first [`Divergence`]
+//! from a reference trace, e.g. of an older version or another emulator.
+//!
+//! ```
+//! use chip_8::trace::{Format, Tracer};
+//! use chip_8::random::XorShift;
+//! use chip_8::vm::VM;
+//!
+//! let mut vm = VM::new(XorShift::default(), |_, _| Ok(()));
+//! // LD V0, 0x12; LD I, 0x300
+//! vm.load_rom(&[0x60, 0x12, 0xA3, 0x00])?;
+//!
+//! let mut tracer = Tracer::new(Format::Text);
+//! tracer.step(&mut vm)?;
+//!
+//! let output = String::from_utf8(tracer.take_output()).unwrap();
+//! assert!(output.starts_with("0200 6012 LD V0, 0x12"));
+//! # Ok::<(), chip_8::errors::Chip8Error>(())
+//! ```
+//!
+//! # Formats
+//!
+//! [`Format::Text`] writes one line per entry with hexadecimal numbers:
+//! the address, the opcode, the instruction, the V registers before and after `->`,
+//! `I`, the stack after `S=` and any memory writes as `address:value`.
+//!
+//! [`Format::JsonLines`] writes one JSON object per line with the keys
+//! `pc`, `opcode`, `instruction`, `before`, `after`, `i`, `stack` and `writes`,
+//! where `writes` is an array of `[address, value]` pairs.
+//!
+//! [`Format::Binary`] starts with a header and is read by [`read_binary`].
+//! All numbers are little endian.
+//!
+//! | Size    | Content                                             |
+//! |---------|-----------------------------------------------------|
+//! | 4       | Magic [`MAGIC`]                                     |
+//! | 2       | Format version [`VERSION`]                          |
+//! | `n` * … | Entries until the end                               |
+//!
+//! Each entry consists of:
+//!
+//! | Size    | Content                                             |
+//! |---------|-----------------------------------------------------|
+//! | 2       | Program counter                                     |
+//! | 4       | Opcode                                              |
+//! | 16      | V registers before                                  |
+//! | 16      | V registers after                                   |
+//! | 2       | `I` after                                           |
+//! | 2       | Number `s` of return addresses on the stack after   |
+//! | `s` * 2 | Return addresses, the innermost last                |
+//! | 2       | Number `w` of memory writes                         |
+//! | `w` * 3 | Memory writes, each address and value               |
+
+use crate::beeper::Beeper;
+use crate::debug::{memory_access, Access};
+use crate::display::Screen;
+use crate::errors::Chip8Error;
+use crate::instructions::{Instruction, VRegister};
+use crate::memory::Bus;
+use crate::movie::{apply_events, Movie};
+use crate::quirks::Quirks;
+use crate::snapshot::Reader;
+use crate::vm::{StepResult, VM};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::Write;
+use core::ops::RangeInclusive;
+
+/// Magic bytes at the start of [`Format::Binary`]
+pub const MAGIC: [u8; 4] = *b"C8TR";
+
+/// Current version of [`Format::Binary`]
+pub const VERSION: u16 = 1;
+
+/// Encoding of the trace output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable lines
+    Text,
+    /// Compact binary, see [`read_binary`]
+    Binary,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Single byte written to memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryWrite {
+    /// Address of the byte
+    pub addr: u16,
+    /// Value written
+    pub value: u8,
+}
+
+/// Record of a single executed instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Address of the instruction
+    pub pc: u16,
+    /// Raw opcode, with the first word in the upper half for four byte instructions
+    pub opcode: u32,
+    /// Decoded instruction
+    pub instruction: Instruction,
+    /// V registers before execution
+    pub registers_before: [u8; 16],
+    /// V registers after execution
+    pub registers_after: [u8; 16],
+    /// Register `I` after execution
+    pub i: u16,
+    /// Return addresses on the stack after execution, the innermost last
+    pub stack: Vec<u16>,
+    /// Memory written by the instruction
+    pub writes: Vec<MemoryWrite>,
+}
+
+impl TraceEntry {
+    /// Appends the entry encoded in `format` to `output`
+    ///
+    /// [`Format::Binary`] requires the header of [`Tracer::new`] in front of the first entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics in [`Format::Binary`] if there are more than `u16::MAX` return addresses on the stack.
+    pub fn encode(&self, format: Format, output: &mut Vec<u8>) {
+        match format {
+            Format::Text => output.extend_from_slice(self.to_text().as_bytes()),
+            Format::Binary => self.encode_binary(output),
+            Format::JsonLines => output.extend_from_slice(self.to_json().as_bytes()),
+        }
+    }
+
+    fn opcode_hex(&self) -> String {
+        if self.opcode > 0xFFFF {
+            alloc::format!("{:08X}", self.opcode)
+        } else {
+            alloc::format!("{:04X}", self.opcode)
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut line = alloc::format!(
+            "{:04X} {} {:<20} ",
+            self.pc,
+            self.opcode_hex(),
+            self.instruction.to_string()
+        );
+        for (registers, separator) in [
+            (&self.registers_before, " -> "),
+            (&self.registers_after, ""),
+        ] {
+            for value in registers {
+                let _ = write!(line, "{:02X}", value);
+            }
+            line.push_str(separator);
+        }
+        let _ = write!(line, " I={:04X} S=", self.i);
+        for (idx, addr) in self.stack.iter().enumerate() {
+            let separator = if idx > 0 { "," } else { "" };
+            let _ = write!(line, "{}{:04X}", separator, addr);
+        }
+        for write in &self.writes {
+            let _ = write!(line, " {:04X}:{:02X}", write.addr, write.value);
+        }
+        line.push('\n');
+        line
+    }
+
+    fn to_json(&self) -> String {
+        let mut line = alloc::format!(
+            "{{\"pc\":{},\"opcode\":{},\"instruction\":\"{}\",\"before\":{:?},\"after\":{:?},\"i\":{},\"stack\":{:?},\"writes\":[",
+            self.pc,
+            self.opcode,
+            self.instruction.to_string().replace('\\', "\\\\").replace('"', "\\\""),
+            self.registers_before,
+            self.registers_after,
+            self.i,
+            self.stack
+        );
+        for (idx, write) in self.writes.iter().enumerate() {
+            if idx > 0 {
+                line.push(',');
+            }
+            let _ = write!(line, "[{},{}]", write.addr, write.value);
+        }
+        line.push_str("]}\n");
+        line
+    }
+
+    fn encode_binary(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.pc.to_le_bytes());
+        output.extend_from_slice(&self.opcode.to_le_bytes());
+        output.extend_from_slice(&self.registers_before);
+        output.extend_from_slice(&self.registers_after);
+        output.extend_from_slice(&self.i.to_le_bytes());
+        let stack = u16::try_from(self.stack.len()).expect("at most u16::MAX return addresses");
+        output.extend_from_slice(&stack.to_le_bytes());
+        for addr in &self.stack {
+            output.extend_from_slice(&addr.to_le_bytes());
+        }
+        output.extend_from_slice(&(self.writes.len() as u16).to_le_bytes());
+        for write in &self.writes {
+            output.extend_from_slice(&write.addr.to_le_bytes());
+            output.push(write.value);
+        }
+    }
+
+    fn decode_binary(reader: &mut Reader) -> crate::errors::Result<Self> {
+        let pc = reader.u16()?;
+        let opcode = reader.u32()?;
+        let instruction = if opcode > 0xFFFF {
+            Instruction::decode_long((opcode >> 16) as u16, opcode as u16)
+        } else {
+            Instruction::decode(opcode as u16)
+        }
+        .map_err(|_| Chip8Error::InvalidTrace)?;
+        let registers_before = reader.array()?;
+        let registers_after = reader.array()?;
+        let i = reader.u16()?;
+        let stack = (0..reader.u16()?)
+            .map(|_| reader.u16())
+            .collect::<crate::errors::Result<_>>()?;
+        let writes = (0..reader.u16()?)
+            .map(|_| {
+                Ok(MemoryWrite {
+                    addr: reader.u16()?,
+                    value: reader.u8()?,
+                })
+            })
+            .collect::<crate::errors::Result<_>>()?;
+        Ok(Self {
+            pc,
+            opcode,
+            instruction,
+            registers_before,
+            registers_after,
+            i,
+            stack,
+            writes,
+        })
+    }
+}
+
+/// Reads all entries of a trace in [`Format::Binary`]
+///
+/// # Errors
+///
+/// Will return [`Chip8Error::InvalidTrace`] if `bytes` are malformed
+/// or [`Chip8Error::UnsupportedTraceVersion`] for other format versions.
+pub fn read_binary(bytes: &[u8]) -> crate::errors::Result<Vec<TraceEntry>> {
+    let mut reader = Reader::new(bytes, || Chip8Error::InvalidTrace);
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(Chip8Error::InvalidTrace);
+    }
+    let version = reader.u16()?;
+    if version != VERSION {
+        return Err(Chip8Error::UnsupportedTraceVersion(version));
+    }
+    let mut entries = Vec::new();
+    while !reader.is_empty() {
+        entries.push(TraceEntry::decode_binary(&mut reader)?);
+    }
+    Ok(entries)
+}
+
+/// Steps a [`VM`] and records the executed instructions
+#[derive(Clone, Debug)]
+pub struct Tracer {
+    format: Format,
+    output: Vec<u8>,
+    pc_ranges: Vec<RangeInclusive<u16>>,
+    instruction_filters: Vec<fn(&Instruction) -> bool>,
+}
+
+impl Tracer {
+    /// Creates a new instance writing entries in `format`, which traces everything until filters are added
+    #[must_use]
+    pub fn new(format: Format) -> Self {
+        let mut output = Vec::new();
+        if format == Format::Binary {
+            output.extend_from_slice(&MAGIC);
+            output.extend_from_slice(&VERSION.to_le_bytes());
+        }
+        Self {
+            format,
+            output,
+            pc_ranges: Vec::new(),
+            instruction_filters: Vec::new(),
+        }
+    }
+
+    /// Returns the format of the output
+    #[must_use]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the output which has not been taken yet
+    #[must_use]
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Returns and clears the output, so that all taken outputs concatenated form the whole trace
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.output)
+    }
+
+    /// Traces instructions at addresses in `range`, in addition to other ranges
+    pub fn trace_pc_range(&mut self, range: RangeInclusive<u16>) {
+        self.pc_ranges.push(range);
+    }
+
+    /// Traces instructions for which `filter` returns `true`, in addition to other filters
+    pub fn trace_instructions(&mut self, filter: fn(&Instruction) -> bool) {
+        self.instruction_filters.push(filter);
+    }
+
+    /// Removes all filters, so that everything is traced
+    pub fn clear_filters(&mut self) {
+        self.pc_ranges.clear();
+        self.instruction_filters.clear();
+    }
+
+    /// Returns `true` if an instruction passes both the PC ranges and the instruction filters
+    fn is_traced(&self, pc: u16, instruction: &Instruction) -> bool {
+        (self.pc_ranges.is_empty() || self.pc_ranges.iter().any(|range| range.contains(&pc)))
+            && (self.instruction_filters.is_empty()
+                || self
+                    .instruction_filters
+                    .iter()
+                    .any(|filter| filter(instruction)))
+    }
+
+    /// Executes a single step of `vm` and records the executed instruction if it passes the filters
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`], nothing is recorded then.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`TraceEntry::encode`].
+    pub fn step<R, S, B, M>(&mut self, vm: &mut VM<R, S, B, M>) -> crate::errors::Result<StepResult>
+    where
+        R: crate::random::RandomSource,
+        S: Screen,
+        B: Beeper,
+        M: Bus,
+    {
+        let (result, entry) = step_traced(vm)?;
+        if let Some(entry) = entry.filter(|entry| self.is_traced(entry.pc, &entry.instruction)) {
+            entry.encode(self.format, &mut self.output);
+        }
+        Ok(result)
+    }
+}
+
+/// First instruction where a [`Comparator`] found the [`VM`] differing from the reference trace
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the entry in the reference trace
+    pub index: usize,
+    /// Entry of the reference trace
+    pub expected: TraceEntry,
+    /// Entry of the `VM`, `None` if it stopped executing before
+    pub actual: Option<TraceEntry>,
+}
+
+impl core::fmt::Display for Divergence {
+    /// Formats as table with the reference and the VM side by side, differences marked with `*`
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        fn row(
+            f: &mut core::fmt::Formatter,
+            name: &str,
+            expected: String,
+            actual: Option<String>,
+        ) -> core::fmt::Result {
+            let marker = if actual.as_ref() == Some(&expected) {
+                ""
+            } else {
+                " *"
+            };
+            let actual = actual.unwrap_or_else(|| "-".into());
+            writeln!(f, "{:<8} {:<24} {:<24}{}", name, expected, actual, marker)
+        }
+
+        fn stack(entry: &TraceEntry) -> String {
+            let addrs: Vec<String> = entry
+                .stack
+                .iter()
+                .map(|addr| alloc::format!("{:04X}", addr))
+                .collect();
+            addrs.join(",")
+        }
+
+        fn writes(entry: &TraceEntry) -> String {
+            let writes: Vec<String> = entry
+                .writes
+                .iter()
+                .map(|write| alloc::format!("{:04X}:{:02X}", write.addr, write.value))
+                .collect();
+            writes.join(" ")
+        }
+
+        let expected = &self.expected;
+        let actual = self.actual.as_ref();
+        writeln!(f, "diverged at entry {}", self.index)?;
+        writeln!(f, "{:<8} {:<24} {:<24}", "", "expected", "actual")?;
+        row(
+            f,
+            "PC",
+            alloc::format!("{:04X}", expected.pc),
+            actual.map(|actual| alloc::format!("{:04X}", actual.pc)),
+        )?;
+        row(
+            f,
+            "opcode",
+            alloc::format!("{} {}", expected.opcode_hex(), expected.instruction),
+            actual.map(|actual| alloc::format!("{} {}", actual.opcode_hex(), actual.instruction)),
+        )?;
+        for (idx, vx) in VRegister::iter_to(VRegister::VF).enumerate() {
+            row(
+                f,
+                &vx.to_string(),
+                alloc::format!("{:02X}", expected.registers_after[idx]),
+                actual.map(|actual| alloc::format!("{:02X}", actual.registers_after[idx])),
+            )?;
+        }
+        row(
+            f,
+            "I",
+            alloc::format!("{:04X}", expected.i),
+            actual.map(|actual| alloc::format!("{:04X}", actual.i)),
+        )?;
+        row(f, "stack", stack(expected), actual.map(stack))?;
+        row(f, "memory", writes(expected), actual.map(writes))
+    }
+}
+
+/// Runs a [`VM`] against a reference trace and finds the first diverging instruction
+///
+/// The reference trace must contain every instruction, i.e. it was recorded without filters.
+#[derive(Clone, Debug)]
+pub struct Comparator<'a> {
+    reference: &'a [TraceEntry],
+    quirks: Option<Quirks>,
+    tolerated: usize,
+}
+
+impl<'a> Comparator<'a> {
+    /// Creates a new instance comparing with the `reference` trace
+    #[must_use]
+    pub fn new(reference: &'a [TraceEntry]) -> Self {
+        Self {
+            reference,
+            quirks: None,
+            tolerated: 0,
+        }
+    }
+
+    /// Creates a new instance comparing with the `reference` trace of an emulator with the `quirks` profile
+    ///
+    /// Differences of instructions that behave differently with `quirks` are tolerated:
+    /// The `VM` takes over the registers, `I`, memory writes and jump target of the reference and continues.
+    /// [`Quirks::key_wait`] is not tolerated, since it changes the number of executed instructions.
+    #[must_use]
+    pub fn with_quirks(reference: &'a [TraceEntry], quirks: Quirks) -> Self {
+        Self {
+            quirks: Some(quirks),
+            ..Self::new(reference)
+        }
+    }
+
+    /// Returns the number of differences tolerated by the quirks profile so far
+    #[must_use]
+    pub fn tolerated(&self) -> usize {
+        self.tolerated
+    }
+
+    /// Replays the `movie` into the `vm` and returns the first divergence from the reference trace
+    ///
+    /// The `vm` must be set up like for a [`Player`](crate::movie::Player), with the ROM loaded,
+    /// an RNG from [`Movie::rng`] and the [`Movie::quirks`] profile.
+    /// Returns `None` if the `vm` executed every instruction of the reference trace.
+    ///
+    /// # Errors
+    ///
+    /// Will return any error from [`VM::step`].
+    pub fn compare<R, S, B, M>(
+        &mut self,
+        vm: &mut VM<R, S, B, M>,
+        movie: &Movie,
+    ) -> crate::errors::Result<Option<Divergence>>
+    where
+        R: crate::random::RandomSource,
+        S: Screen,
+        B: Beeper,
+        M: Bus,
+    {
+        let mut reference = self.reference.iter().enumerate().peekable();
+        for events in movie.frames() {
+            apply_events(vm, events);
+            for _ in 0..movie.steps_per_frame() {
+                let actual = match step_traced(vm)? {
+                    (_, Some(actual)) => actual,
+                    (_, None) => continue,
+                };
+                let (index, expected) = match reference.next() {
+                    Some(next) => next,
+                    None => return Ok(None),
+                };
+                if *expected == actual {
+                    continue;
+                }
+                if !self.is_tolerated(movie.quirks(), expected, &actual) {
+                    return Ok(Some(Divergence {
+                        index,
+                        expected: expected.clone(),
+                        actual: Some(actual),
+                    }));
+                }
+                self.tolerated += 1;
+                for (vx, value) in VRegister::iter_to(VRegister::VF).zip(expected.registers_after) {
+                    vm.set_register(vx, value);
+                }
+                vm.set_i(expected.i);
+                for write in &expected.writes {
+                    let addr = vm.memory().checked_addr(write.addr)?;
+                    vm.memory_mut().load(addr, &[write.value])?;
+                }
+                if let (Instruction::LongJump(_), Some((_, next))) =
+                    (expected.instruction, reference.peek())
+                {
+                    vm.set_pc(next.pc);
+                }
+            }
+            vm.tick_timers();
+        }
+        Ok(reference.next().map(|(index, expected)| Divergence {
+            index,
+            expected: expected.clone(),
+            actual: None,
+        }))
+    }
+
+    /// Returns `true` if the same instruction differs only because of the quirks of the reference and the `VM`
+    fn is_tolerated(&self, quirks: Quirks, expected: &TraceEntry, actual: &TraceEntry) -> bool {
+        let reference = match self.quirks {
+            Some(reference) => reference,
+            None => return false,
+        };
+        if expected.pc != actual.pc || expected.opcode != actual.opcode {
+            return false;
+        }
+        match actual.instruction {
+            Instruction::ShiftRight(_, _) | Instruction::ShiftLeft(_, _) => {
+                reference.shift_vx != quirks.shift_vx
+            }
+            Instruction::Or(_, _) | Instruction::And(_, _) | Instruction::XOr(_, _) => {
+                reference.logic_resets_vf != quirks.logic_resets_vf
+            }
+            Instruction::LoadMemoryRegisters(_) | Instruction::LoadRegistersMemory(_) => {
+                reference.memory_increment != quirks.memory_increment
+            }
+            Instruction::LongJump(_) => reference.jump_vx != quirks.jump_vx,
+            Instruction::Draw(_, _, _) => reference.edge_mode != quirks.edge_mode,
+            _ => false,
+        }
+    }
+}
+
+/// Executes a single step of `vm` and returns the entry of the executed instruction
+fn step_traced<R, S, B, M>(
+    vm: &mut VM<R, S, B, M>,
+) -> crate::errors::Result<(StepResult, Option<TraceEntry>)>
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    let pc = vm.pc();
+    // read ahead since the instruction may overwrite itself
+    let opcode_bytes: Vec<u8> = (0..4)
+        .map_while(|offset| {
+            let addr = vm.memory().checked_addr(pc.checked_add(offset)?).ok()?;
+            Some(vm.memory().read(addr))
+        })
+        .collect();
+    let registers_before = registers(vm);
+    let access = memory_access(vm);
+
+    let result = vm.step()?;
+    let instruction = match result {
+        StepResult::Executed(instruction) => instruction,
+        _ => return Ok((result, None)),
+    };
+    let opcode = opcode_bytes
+        .iter()
+        .take(usize::from(instruction.size()))
+        .fold(0, |opcode, byte| opcode << 8 | u32::from(*byte));
+    let writes = match access {
+        Some((Access::Write, start, len)) => (start..start + len)
+            .filter_map(|addr| {
+                let addr = vm.memory().checked_addr(u16::try_from(addr).ok()?).ok()?;
+                Some(MemoryWrite {
+                    addr: addr.into(),
+                    value: vm.memory().read(addr),
+                })
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let entry = TraceEntry {
+        pc,
+        opcode,
+        instruction,
+        registers_before,
+        registers_after: registers(vm),
+        i: vm.i(),
+        stack: vm.stack().to_vec(),
+        writes,
+    };
+    Ok((result, Some(entry)))
+}
+
+/// Returns all V registers of `vm`
+fn registers<R, S, B, M>(vm: &VM<R, S, B, M>) -> [u8; 16]
+where
+    R: crate::random::RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    let mut registers = [0; 16];
+    for (value, vx) in registers.iter_mut().zip(VRegister::iter_to(VRegister::VF)) {
+        *value = vm.register(vx);
+    }
+    registers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::XorShift;
+
+    fn test_vm(rom: &[u8]) -> crate::errors::Result<VM<XorShift>> {
+        let rng = XorShift::default();
+        let mut vm = VM::new(rng, |_, _| Ok(()));
+        vm.load_rom(rom)?;
+        Ok(vm)
+    }
+
+    // LD V0, 0x12; LD V1, 0x34; LD I, 0x300; LD [I], V1; LD I, long 0x1234
+    const ROM: [u8; 12] = [
+        0x60, 0x12, 0x61, 0x34, 0xA3, 0x00, 0xF1, 0x55, 0xF0, 0x00, 0x12, 0x34,
+    ];
+
+    #[test]
+    fn trace_text() -> crate::errors::Result<()> {
+        let mut vm = test_vm(&ROM)?;
+        let mut tracer = Tracer::new(Format::Text);
+        for _ in 0..5 {
+            tracer.step(&mut vm)?;
+        }
+
+        let output = String::from_utf8(tracer.take_output()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "0200 6012 LD V0, 0x12          00000000000000000000000000000000 -> 12000000000000000000000000000000 I=0000 S="
+        );
+        assert!(lines[3].starts_with("0206 F155 LD [I], V1 "));
+        assert!(lines[3].ends_with(" I=0302 S= 0300:12 0301:34"));
+        assert!(lines[4].starts_with("0208 F0001234 "));
+        assert!(tracer.output().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn trace_json_lines() -> crate::errors::Result<()> {
+        let mut vm = test_vm(&ROM)?;
+        let mut tracer = Tracer::new(Format::JsonLines);
+        tracer.trace_instructions(|instruction| {
+            matches!(instruction, Instruction::LoadMemoryRegisters(_))
+        });
+        for _ in 0..5 {
+            tracer.step(&mut vm)?;
+        }
+
+        let output = String::from_utf8(tracer.take_output()).unwrap();
+        assert_eq!(
+            output,
+            "{\"pc\":518,\"opcode\":61781,\"instruction\":\"LD [I], V1\",\
+             \"before\":[18, 52, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],\
+             \"after\":[18, 52, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],\
+             \"i\":770,\"stack\":[],\"writes\":[[768,18],[769,52]]}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn trace_binary_round_trip() -> crate::errors::Result<()> {
+        let mut vm = test_vm(&ROM)?;
+        let mut tracer = Tracer::new(Format::Binary);
+        tracer.trace_pc_range(0x202..=0x206);
+        let mut output = Vec::new();
+        for _ in 0..5 {
+            tracer.step(&mut vm)?;
+            output.extend(tracer.take_output());
+        }
+
+        let entries = read_binary(&output)?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].pc, 0x202);
+        assert_eq!(entries[0].registers_after[1], 0x34);
+        assert_eq!(entries[1].instruction, Instruction::LoadI(0x300.into()));
+        assert_eq!(
+            entries[2].writes,
+            [
+                MemoryWrite {
+                    addr: 0x300,
+                    value: 0x12
+                },
+                MemoryWrite {
+                    addr: 0x301,
+                    value: 0x34
+                }
+            ]
+        );
+
+        assert_eq!(
+            read_binary(&output[..output.len() - 1]),
+            Err(Chip8Error::InvalidTrace)
+        );
+        output[4] = 2;
+        assert_eq!(
+            read_binary(&output),
+            Err(Chip8Error::UnsupportedTraceVersion(2))
+        );
+        Ok(())
+    }
+
+    // LD V0, 0x12; LD V1, 0x03; SHR V0, V1; LD I, 0x300; LD [I], V1; JP 0x20A
+    const QUIRKS_ROM: [u8; 12] = [
+        0x60, 0x12, 0x61, 0x03, 0x80, 0x16, 0xA3, 0x00, 0xF1, 0x55, 0x12, 0x0A,
+    ];
+
+    fn quirks_vm(quirks: Quirks) -> crate::errors::Result<VM<XorShift>> {
+        let rng = XorShift::default();
+        let config = crate::vm::Config {
+            quirks,
+            ..crate::vm::Config::default()
+        };
+        let mut vm = VM::with_config(rng, |_, _| Ok(()), config);
+        vm.load_rom(&QUIRKS_ROM)?;
+        Ok(vm)
+    }
+
+    fn test_movie(frames: usize) -> crate::errors::Result<Movie> {
+        let mut recorder = crate::movie::Recorder::new(0, Quirks::XO_CHIP, 4);
+        let mut vm = quirks_vm(Quirks::XO_CHIP)?;
+        for _ in 0..frames {
+            recorder.frame(&mut vm, &crate::keypad::Keypad::new())?;
+        }
+        Ok(recorder.finish())
+    }
+
+    fn record_trace(quirks: Quirks, movie: &Movie) -> crate::errors::Result<Vec<TraceEntry>> {
+        let mut vm = quirks_vm(quirks)?;
+        let mut tracer = Tracer::new(Format::Binary);
+        for _ in movie.frames() {
+            for _ in 0..movie.steps_per_frame() {
+                tracer.step(&mut vm)?;
+            }
+            vm.tick_timers();
+        }
+        read_binary(tracer.output())
+    }
+
+    #[test]
+    fn trace_compare_same() -> crate::errors::Result<()> {
+        let movie = test_movie(2)?;
+        let reference = record_trace(Quirks::XO_CHIP, &movie)?;
+        assert_eq!(reference.len(), 8);
+
+        let mut comparator = Comparator::new(&reference);
+        assert_eq!(
+            comparator.compare(&mut quirks_vm(Quirks::XO_CHIP)?, &movie)?,
+            None
+        );
+
+        let longer = record_trace(Quirks::XO_CHIP, &test_movie(3)?)?;
+        let mut comparator = Comparator::new(&longer);
+        let divergence = comparator
+            .compare(&mut quirks_vm(Quirks::XO_CHIP)?, &movie)?
+            .expect("divergence");
+        assert_eq!(divergence.index, 8);
+        assert_eq!(divergence.actual, None);
+        Ok(())
+    }
+
+    #[test]
+    fn trace_compare_divergence() -> crate::errors::Result<()> {
+        let movie = test_movie(2)?;
+        let reference = record_trace(Quirks::CHIP_48, &movie)?;
+
+        let mut comparator = Comparator::new(&reference);
+        let divergence = comparator
+            .compare(&mut quirks_vm(Quirks::XO_CHIP)?, &movie)?
+            .expect("divergence");
+        assert_eq!(divergence.index, 2);
+        assert_eq!(divergence.expected.registers_after[0], 0x09);
+        assert_eq!(
+            divergence
+                .actual
+                .as_ref()
+                .map(|actual| actual.registers_after[0]),
+            Some(0x01)
+        );
+
+        let table = divergence.to_string();
+        assert!(table.starts_with("diverged at entry 2\n"));
+        assert!(table.contains("\nV0       09                       01                       *\n"));
+        assert!(table.contains("\nV1       03                       03                      \n"));
+        Ok(())
+    }
+
+    #[test]
+    fn trace_compare_tolerated_quirks() -> crate::errors::Result<()> {
+        let movie = test_movie(2)?;
+        let reference = record_trace(Quirks::CHIP_48, &movie)?;
+
+        let mut comparator = Comparator::with_quirks(&reference, Quirks::CHIP_48);
+        let mut vm = quirks_vm(Quirks::XO_CHIP)?;
+        assert_eq!(comparator.compare(&mut vm, &movie)?, None);
+        // SHR V0, V1 and LD [I], V1
+        assert_eq!(comparator.tolerated(), 2);
+        assert_eq!(vm.i(), 0x301);
+        Ok(())
+    }
+}
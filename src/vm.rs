@@ -1,13 +1,18 @@
 //! Virtual machine
 
-use crate::display::{Display, DrawResult, XCoordinate, YCoordinate};
-use crate::instructions::{Addr, Instruction, VRegister};
+use crate::beeper::{Beeper, NoBeeper};
+use crate::display::{
+    Display, DrawResult, Planes, Resolution, Screen, Sprite, XCoordinate, YCoordinate,
+};
+use crate::instructions::{Addr, Instruction, Nibble, VRegister};
 use crate::keypad::{Key, KeyState};
-use crate::memory::Memory;
+use crate::memory::{Bus, Memory};
+use crate::quirks::{KeyWait, MemoryIncrement, Quirks};
+use crate::random::RandomSource;
+use crate::snapshot::Snapshot;
 use alloc::vec::Vec;
 use core::convert::TryFrom;
 use core::ops::{Index, IndexMut};
-use rand::Rng;
 
 /// Type of a general purpose register in the VM
 type VRegisterValue = u8;
@@ -18,8 +23,11 @@ type IRegisterValue = u16;
 /// Type of the program counter register
 type PCRegisterValue = u16;
 
-/// Memory address for programm (ROM) start
-const PROGRAM_START: PCRegisterValue = 0x200;
+/// Type of the delay and sound timers
+type TimerValue = u8;
+
+/// Default memory address for programm (ROM) start
+pub(crate) const PROGRAM_START: PCRegisterValue = 0x200;
 
 /// CPU registers
 ///
@@ -63,25 +71,151 @@ impl IndexMut<VRegister> for Registers {
     }
 }
 
+/// Delay and sound timers
+///
+/// Both count down to `0` at 60 Hz.
+#[derive(Debug, Default)]
+struct Timers {
+    delay: TimerValue,
+    sound: TimerValue,
+    ticks: u64,
+}
+
+impl Timers {
+    /// Decrements both timers unless they already reached `0`
+    fn tick(&mut self) {
+        self.ticks += 1;
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+}
+
+/// Call stack of return addresses
+#[derive(Debug)]
+struct Stack {
+    entries: Vec<PCRegisterValue>,
+    depth: usize,
+}
+
+impl Stack {
+    /// Creates a new empty instance which can hold up to `depth` entries
+    #[must_use]
+    fn new(depth: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(depth),
+            depth,
+        }
+    }
+
+    /// Pushes a return address
+    fn push(&mut self, pc: PCRegisterValue) -> crate::errors::Result<()> {
+        if self.entries.len() >= self.depth {
+            return Err(crate::errors::Chip8Error::StackOverflow(self.depth));
+        }
+        self.entries.push(pc);
+        Ok(())
+    }
+
+    /// Pops the most recent return address
+    fn pop(&mut self) -> crate::errors::Result<PCRegisterValue> {
+        self.entries
+            .pop()
+            .ok_or(crate::errors::Chip8Error::StackUnderflow)
+    }
+}
+
+/// Configuration of the [`VM`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Maximum number of nested subroutine calls
+    ///
+    /// The original CHIP-8 supports 16 levels, some variants like SUPER-CHIP support more.
+    pub stack_depth: usize,
+
+    /// Memory address where [`VM::load_rom`] puts the program and where execution starts
+    ///
+    /// The original CHIP-8 uses `0x200`, some variants like ETI 660 use `0x600`.
+    pub program_start: Addr,
+
+    /// Behaviour of ambiguous instructions
+    pub quirks: Quirks,
+
+    /// Size of the memory in bytes
+    ///
+    /// The original CHIP-8 has [`Memory::SIZE`], XO-CHIP has [`Memory::XO_CHIP_SIZE`].
+    pub memory_size: usize,
+}
+
+impl Config {
+    /// Default for [`stack_depth`](Self::stack_depth)
+    pub const DEFAULT_STACK_DEPTH: usize = 16;
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            stack_depth: Self::DEFAULT_STACK_DEPTH,
+            program_start: PROGRAM_START.into(),
+            quirks: Quirks::default(),
+            memory_size: Memory::SIZE,
+        }
+    }
+}
+
 const FONT_RAW_ADDR: u16 = 0x0;
 
+/// Memory address of the SUPER-CHIP big font, right after the regular font
+const BIG_FONT_RAW_ADDR: u16 = 0x50;
+
+/// Number of RPL user flags for `LD R, Vx` and `LD Vx, R`
+const RPL_FLAGS: usize = 16;
+
+/// Size of the XO-CHIP audio pattern buffer in bytes
+const AUDIO_PATTERN_SIZE: usize = 16;
+
+/// XO-CHIP pitch for a playback rate of 4000 Hz
+const DEFAULT_PITCH: u8 = 64;
+
+/// Result from a single [`VM::step`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepResult {
+    /// The `Instruction` was fetched, decoded and executed
+    Executed(Instruction),
+    /// The VM is waiting for a key press, nothing was executed
+    WaitingForKey,
+    /// The VM is halted, nothing was executed
+    Halted,
+}
+
 /// Virtual machine
+///
+/// Draws on a [`Screen`], which is the built-in [`Display`] by default,
+/// controls a [`Beeper`], which does nothing by default,
+/// and accesses memory through a [`Bus`], which is plain [`Memory`] by default.
 //#[derive(Debug)]
-pub struct VM<R: Rng> {
+pub struct VM<R: RandomSource, S: Screen = Display, B: Beeper = NoBeeper, M: Bus = Memory> {
+    config: Config,
     registers: Registers,
+    stack: Stack,
+    timers: Timers,
     rng: R,
     sys_fn: fn(&mut Self, crate::instructions::Addr) -> crate::errors::Result<()>,
     keypad: crate::keypad::Keypad,
     waiting_on_any_keypress: Option<VRegister>,
-    memory: Memory,
-    display: Display,
+    pressed_while_waiting: Option<Key>,
+    halted: bool,
+    rpl_flags: [VRegisterValue; RPL_FLAGS],
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    pitch: u8,
+    memory: M,
+    display: S,
+    beeper: B,
 }
 
-#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
-#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "rand"))))]
+#[cfg(all(feature = "std", feature = "rand"))]
 impl Default for VM<rand::rngs::ThreadRng> {
     /// Creates a new instance with thread-local random number generator
-    #[must_use]
     fn default() -> Self {
         Self::new(rand::thread_rng(), |_, addr| {
             Err(crate::errors::Chip8Error::UnimplementedInstruction(
@@ -91,55 +225,429 @@ impl Default for VM<rand::rngs::ThreadRng> {
     }
 }
 
-impl<R> VM<R>
+impl<R> VM<R, Display>
 where
-    R: rand::Rng,
+    R: RandomSource,
 {
-    /// Creates a new instance with the given RNG
+    /// Creates a new instance with the given RNG and default [`Config`]
     #[must_use]
     pub fn new(
         rng: R,
         sys_fn: fn(&mut Self, crate::instructions::Addr) -> crate::errors::Result<()>,
+    ) -> Self {
+        Self::with_config(rng, sys_fn, Config::default())
+    }
+
+    /// Creates a new instance with the given RNG and `config`
+    #[must_use]
+    pub fn with_config(
+        rng: R,
+        sys_fn: fn(&mut Self, crate::instructions::Addr) -> crate::errors::Result<()>,
+        config: Config,
+    ) -> Self {
+        let display = Display::new(config.quirks.edge_mode);
+        Self::with_screen(rng, sys_fn, config, display)
+    }
+}
+
+impl<R, S> VM<R, S>
+where
+    R: RandomSource,
+    S: Screen,
+{
+    /// Creates a new instance with the given RNG, `config` and `screen`
+    ///
+    /// The `screen` is responsible for [`Quirks::edge_mode`] itself.
+    #[must_use]
+    pub fn with_screen(
+        rng: R,
+        sys_fn: fn(&mut Self, crate::instructions::Addr) -> crate::errors::Result<()>,
+        config: Config,
+        screen: S,
+    ) -> Self {
+        Self::with_hardware(rng, sys_fn, config, screen, NoBeeper)
+    }
+}
+
+impl<R, S, B> VM<R, S, B>
+where
+    R: RandomSource,
+    S: Screen,
+    B: Beeper,
+{
+    /// Creates a new instance with the given RNG, `config`, `screen` and `beeper`
+    ///
+    /// The `screen` is responsible for [`Quirks::edge_mode`] itself.
+    #[must_use]
+    pub fn with_hardware(
+        rng: R,
+        sys_fn: fn(&mut Self, crate::instructions::Addr) -> crate::errors::Result<()>,
+        config: Config,
+        screen: S,
+        beeper: B,
+    ) -> Self {
+        let memory = Memory::with_size(config.memory_size);
+        Self::with_bus(rng, sys_fn, config, screen, beeper, memory)
+    }
+}
+
+impl<R, S, B, M> VM<R, S, B, M>
+where
+    R: RandomSource,
+    S: Screen,
+    B: Beeper,
+    M: Bus,
+{
+    /// Creates a new instance with the given RNG, `config`, `screen`, `beeper` and memory `bus`
+    ///
+    /// The fonts are loaded into the `bus` regardless of any protection,
+    /// [`Config::memory_size`] is ignored in favour of [`Bus::size`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fonts do not fit into the `bus`.
+    #[must_use]
+    pub fn with_bus(
+        rng: R,
+        sys_fn: fn(&mut Self, crate::instructions::Addr) -> crate::errors::Result<()>,
+        config: Config,
+        screen: S,
+        beeper: B,
+        bus: M,
     ) -> Self {
         let mut vm = Self {
+            config,
             registers: Registers::new(),
+            stack: Stack::new(config.stack_depth),
+            timers: Timers::default(),
             rng,
             sys_fn,
             keypad: crate::keypad::Keypad::default(),
             waiting_on_any_keypress: None,
-            memory: Memory::default(),
-            display: Display::default(),
+            pressed_while_waiting: None,
+            halted: false,
+            rpl_flags: [0; RPL_FLAGS],
+            audio_pattern: [0; AUDIO_PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            memory: bus,
+            display: screen,
+            beeper,
         };
 
-        for (offs, font_byte) in crate::font::font_as_bytes_iter().enumerate() {
-            let addr = Addr::new(FONT_RAW_ADDR + offs as u16).expect("built-in font fits into RAM");
-            vm.memory.write(addr, *font_byte);
-        }
+        let font: Vec<u8> = crate::font::font_as_bytes_iter().copied().collect();
+        vm.memory
+            .load(Addr::long(FONT_RAW_ADDR), &font)
+            .expect("built-in font fits into RAM");
+        let big_font: Vec<u8> = crate::font::big_font_as_bytes_iter().copied().collect();
+        vm.memory
+            .load(Addr::long(BIG_FONT_RAW_ADDR), &big_font)
+            .expect("built-in big font fits into RAM");
+        vm.registers.pc = config.program_start.into();
 
         vm
     }
 
+    /// Returns the screen the VM draws on
+    #[must_use]
+    pub fn display(&self) -> &S {
+        &self.display
+    }
+
+    /// Returns the beeper the VM controls
+    #[must_use]
+    pub fn beeper(&self) -> &B {
+        &self.beeper
+    }
+
+    /// Returns the XO-CHIP audio pattern buffer, one bit per sample
+    #[must_use]
+    pub fn audio_pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE] {
+        &self.audio_pattern
+    }
+
+    /// Returns the XO-CHIP pitch of the audio pattern
+    ///
+    /// The playback rate is `4000 * 2 ^ ((pitch - 64) / 48)` bits per second.
+    #[must_use]
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// Returns the value of register `vx`
+    #[must_use]
+    pub fn register(&self, vx: VRegister) -> u8 {
+        self.registers[vx]
+    }
+
+    /// Sets register `vx` to `value`
+    pub fn set_register(&mut self, vx: VRegister, value: u8) {
+        self.registers[vx] = value;
+    }
+
+    /// Returns the value of the address register `I`
+    #[must_use]
+    pub fn i(&self) -> u16 {
+        self.registers.i
+    }
+
+    /// Sets the address register `I` to `value`
+    pub fn set_i(&mut self, value: u16) {
+        self.registers.i = value;
+    }
+
+    /// Returns the program counter, i.e. the address of the next instruction
+    #[must_use]
+    pub fn pc(&self) -> u16 {
+        self.registers.pc
+    }
+
+    /// Sets the program counter to `value`
+    pub fn set_pc(&mut self, value: u16) {
+        self.registers.pc = value;
+    }
+
+    /// Returns the return addresses on the call stack, bottom to top
+    #[must_use]
+    pub fn stack(&self) -> &[u16] {
+        &self.stack.entries
+    }
+
+    /// Returns the memory
+    #[must_use]
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    /// Returns the memory for modification, e.g. by a debugger
+    #[must_use]
+    pub fn memory_mut(&mut self) -> &mut M {
+        &mut self.memory
+    }
+
+    /// Returns whether the VM is halted, see [`halt`](Self::halt)
     #[must_use]
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Copies the `rom` into memory at the configured [`Config::program_start`]
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::RomTooLarge`](crate::errors::Chip8Error::RomTooLarge)
+    /// if the `rom` does not fit into memory.
+    pub fn load_rom(&mut self, rom: &[u8]) -> crate::errors::Result<()> {
+        let start: usize = self.config.program_start.into();
+        let available = self.memory.size().saturating_sub(start);
+        if rom.len() > available {
+            return Err(crate::errors::Chip8Error::RomTooLarge {
+                size: rom.len(),
+                available,
+            });
+        }
+
+        self.memory.load(self.config.program_start, rom)
+    }
+
+    /// Halts the VM, i.e. subsequent calls to [`step`](Self::step) won't execute anything
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    /// Decrements the delay and sound timers
+    ///
+    /// This should be called by the host at 60 Hz, independent of [`step`](Self::step).
+    /// Stops the [`Beeper`] if the sound timer runs out.
+    pub fn tick_timers(&mut self) {
+        let was_sound_active = self.is_sound_active();
+        self.timers.tick();
+        if was_sound_active && !self.is_sound_active() {
+            self.beeper.stop_beeping(self.timers.ticks);
+        }
+    }
+
+    /// Sets the sound timer and starts or stops the [`Beeper`] accordingly
+    fn set_sound_timer(&mut self, value: TimerValue) {
+        let was_sound_active = self.is_sound_active();
+        self.timers.sound = value;
+        match (was_sound_active, self.is_sound_active()) {
+            (false, true) => self.beeper.start_beeping(self.timers.ticks),
+            (true, false) => self.beeper.stop_beeping(self.timers.ticks),
+            _ => {}
+        }
+    }
+
+    /// Returns the current value of the delay timer
+    #[must_use]
+    pub fn delay_timer(&self) -> u8 {
+        self.timers.delay
+    }
+
+    /// Returns the current value of the sound timer
+    #[must_use]
+    pub fn sound_timer(&self) -> u8 {
+        self.timers.sound
+    }
+
+    /// Returns whether the sound timer is active, i.e. whether the host should beep
+    #[must_use]
+    pub fn is_sound_active(&self) -> bool {
+        self.timers.sound > 0
+    }
+
+    /// Marks the `key` as pressed
+    ///
+    /// This finishes a pending `LD Vx, K` unless [`Quirks::key_wait`] is [`KeyWait::Release`].
+    pub fn key_down(&mut self, key: Key) {
+        self.keypad[key] = KeyState::Pressed;
+
+        if let Some(vx) = self.waiting_on_any_keypress {
+            match self.config.quirks.key_wait {
+                KeyWait::Press => self.finish_waiting_on_key(vx, key),
+                KeyWait::Release => self.pressed_while_waiting = Some(key),
+            }
+        }
+    }
+
+    /// Marks the `key` as not pressed
+    ///
+    /// This finishes a pending `LD Vx, K` if [`Quirks::key_wait`] is [`KeyWait::Release`]
+    /// and the `key` has been pressed while waiting.
+    pub fn key_up(&mut self, key: Key) {
+        self.keypad[key] = KeyState::NotPressed;
+
+        if let Some(vx) = self.waiting_on_any_keypress {
+            if self.pressed_while_waiting == Some(key) {
+                self.finish_waiting_on_key(vx, key);
+            }
+        }
+    }
+
+    fn finish_waiting_on_key(&mut self, vx: VRegister, key: Key) {
+        self.registers[vx] = key as VRegisterValue;
+        self.waiting_on_any_keypress = None;
+        self.pressed_while_waiting = None;
+    }
+
+    /// Fetches the instruction at the program counter, decodes and executes it
+    ///
+    /// The program counter is advanced to the next instruction before the
+    /// current one is executed, so jumps and skips start from there.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::OutOfRange`](crate::errors::Chip8Error::OutOfRange)
+    /// if the program counter is outside of memory,
+    /// [`Chip8Error::UnknownInstruction`](crate::errors::Chip8Error::UnknownInstruction)
+    /// if the fetched bits can not be decoded, or any error from executing the instruction.
+    pub fn step(&mut self) -> crate::errors::Result<StepResult> {
+        if self.halted {
+            return Ok(StepResult::Halted);
+        }
+        if self.waiting_on_any_keypress.is_some() {
+            return Ok(StepResult::WaitingForKey);
+        }
+
+        let instruction = self.next_instruction()?;
+        self.registers.pc = self.registers.pc.wrapping_add(instruction.size());
+
+        self.execute_instruction(&instruction)?;
+
+        Ok(StepResult::Executed(instruction))
+    }
+
+    /// Fetches and decodes the instruction at the program counter without executing it
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::OutOfRange`](crate::errors::Chip8Error::OutOfRange)
+    /// if the program counter is outside of memory or
+    /// [`Chip8Error::UnknownInstruction`](crate::errors::Chip8Error::UnknownInstruction)
+    /// if the fetched bits can not be decoded.
+    pub fn next_instruction(&self) -> crate::errors::Result<Instruction> {
+        let bits = self.fetch(self.registers.pc)?;
+        if Instruction::is_long(bits) {
+            let next = self.fetch(self.registers.pc.wrapping_add(2))?;
+            Instruction::decode_long(bits, next)
+        } else {
+            Instruction::decode(bits)
+        }
+    }
+
+    /// Reads the two bytes at `pc`
+    fn fetch(&self, pc: PCRegisterValue) -> crate::errors::Result<u16> {
+        let high = self.memory.read(self.memory.checked_addr(pc)?);
+        let low = self
+            .memory
+            .read(self.memory.checked_addr(pc.wrapping_add(1))?);
+
+        Ok(u16::from_be_bytes([high, low]))
+    }
+
+    /// Skips the next instruction, which is four bytes long for XO-CHIP `LD I, long addr`
+    fn skip(&mut self) {
+        let len = match self.fetch(self.registers.pc) {
+            Ok(bits) if Instruction::is_long(bits) => 4,
+            _ => 2,
+        };
+        self.registers.pc = self.registers.pc.wrapping_add(len);
+    }
+
+    /// Returns the address `offs` bytes after `I`
+    fn i_addr(&self, offs: u16) -> crate::errors::Result<Addr> {
+        let addr = self
+            .registers
+            .i
+            .checked_add(offs)
+            .ok_or(crate::errors::Chip8Error::OutOfRange(self.registers.i))?;
+        self.memory.checked_addr(addr)
+    }
+
+    /// Reads the sprite for `DRW` with `nibble` rows starting `offs` bytes after `I`
+    ///
+    /// Returns the sprite and its size in bytes.
+    fn read_sprite(&self, offs: u16, nibble: Nibble) -> crate::errors::Result<(Sprite, u16)> {
+        if usize::from(nibble) == 0 {
+            // SUPER-CHIP 16 * 16 sprite, two bytes per row
+            let mut sprite_data = Vec::with_capacity(16);
+            for row in 0..16 {
+                let high = self.memory.read(self.i_addr(offs + row * 2)?);
+                let low = self.memory.read(self.i_addr(offs + row * 2 + 1)?);
+                sprite_data.push(u16::from_be_bytes([high, low]));
+            }
+            Ok((Sprite::from_wide(&sprite_data), 32))
+        } else {
+            let mut sprite_data = Vec::with_capacity(nibble.into());
+            for row in 0..nibble.into() {
+                sprite_data.push(self.memory.read(self.i_addr(offs + row as u16)?));
+            }
+            Ok((sprite_data[..].into(), sprite_data.len() as u16))
+        }
+    }
+
     fn execute_instruction(&mut self, instruction: &Instruction) -> crate::errors::Result<()> {
         match *instruction {
             Instruction::Sys(addr) => return (self.sys_fn)(self, addr),
             Instruction::Clear => self.display.clear(),
-            // Return
+            Instruction::Return => self.registers.pc = self.stack.pop()?,
             Instruction::Jump(addr) => self.registers.pc = addr.into(),
-            // Call(Addr)
+            Instruction::Call(addr) => {
+                self.stack.push(self.registers.pc)?;
+                self.registers.pc = addr.into();
+            }
             Instruction::SkipEqualOperand(vx, byte) => {
                 if self.registers[vx] == byte {
-                    self.registers.pc += 2;
+                    self.skip();
                 }
             }
             Instruction::SkipNotEqualOperand(vx, byte) => {
                 if self.registers[vx] != byte {
-                    self.registers.pc += 2;
+                    self.skip();
                 }
             }
             Instruction::SkipEqual(vx, vy) => {
                 if self.registers[vx] == self.registers[vy] {
-                    self.registers.pc += 2
+                    self.skip()
                 }
             }
             Instruction::LoadOperand(vx, byte) => self.registers[vx] = byte,
@@ -147,79 +655,100 @@ where
                 self.registers[vx] = self.registers[vx].wrapping_add(byte)
             }
             Instruction::Load(vx, vy) => self.registers[vx] = self.registers[vy],
-            Instruction::Or(vx, vy) => self.registers[vx] |= self.registers[vy],
-            Instruction::And(vx, vy) => self.registers[vx] &= self.registers[vy],
-            Instruction::XOr(vx, vy) => self.registers[vx] ^= self.registers[vy],
+            Instruction::Or(vx, vy) => {
+                self.registers[vx] |= self.registers[vy];
+                self.reset_vf_after_logic();
+            }
+            Instruction::And(vx, vy) => {
+                self.registers[vx] &= self.registers[vy];
+                self.reset_vf_after_logic();
+            }
+            Instruction::XOr(vx, vy) => {
+                self.registers[vx] ^= self.registers[vy];
+                self.reset_vf_after_logic();
+            }
             Instruction::Add(vx, vy) => {
                 let x = self.registers[vx] as u16;
                 let y = self.registers[vy] as u16;
 
                 let res = x + y;
 
-                // VF is carryover
+                self.registers[vx] = res as VRegisterValue;
+                // VF is carryover, written last so that it wins over a result in VF
                 self.registers[VRegister::VF] =
                     (res > VRegisterValue::MAX as u16) as VRegisterValue;
-
-                self.registers[vx] = res as VRegisterValue;
             }
             Instruction::Sub(vx, vy) => {
                 let x = self.registers[vx];
                 let y = self.registers[vy];
 
-                // VF is Not Borrow i.e. x > y
-                self.registers[VRegister::VF] = (x > y) as VRegisterValue;
-
                 self.registers[vx] = x.wrapping_sub(y);
+                // VF is Not Borrow i.e. x >= y
+                self.registers[VRegister::VF] = (x >= y) as VRegisterValue;
             }
             Instruction::ShiftRight(vx, vy) => {
-                let y = self.registers[vy];
+                let y = self.registers[self.shift_source(vx, vy)];
 
+                self.registers[vx] = y >> 1;
                 // VF is LSB before shift
                 self.registers[VRegister::VF] = y & 0x1;
-
-                self.registers[vx] = y >> 1;
             }
             Instruction::SubNegated(vx, vy) => {
                 let x = self.registers[vx];
                 let y = self.registers[vy];
 
-                // VF is not borrow i.e. y > x
-                self.registers[VRegister::VF] = (y > x) as VRegisterValue;
-
                 self.registers[vx] = y.wrapping_sub(x);
+                // VF is not borrow i.e. y >= x
+                self.registers[VRegister::VF] = (y >= x) as VRegisterValue;
             }
             Instruction::ShiftLeft(vx, vy) => {
-                let y = self.registers[vy];
+                let y = self.registers[self.shift_source(vx, vy)];
 
+                self.registers[vx] = y << 1;
                 // VF is MSB before shift
                 self.registers[VRegister::VF] = y >> 7;
-
-                self.registers[vx] = y << 1;
             }
             Instruction::SkipNotEqual(vx, vy) => {
                 if self.registers[vx] != self.registers[vy] {
-                    self.registers.pc += 2
+                    self.skip()
                 }
             }
             Instruction::LoadI(addr) => self.registers.i = addr.into(),
             Instruction::LongJump(addr) => {
                 let addr: PCRegisterValue = addr.into();
-                self.registers.pc = self.registers[VRegister::V0] as PCRegisterValue + addr;
-            }
-            Instruction::Random(vx, byte) => {
-                self.registers[vx] = self.rng.gen::<VRegisterValue>() & byte
+                let offset_register = if self.config.quirks.jump_vx {
+                    // highest nibble of the address doubles as register
+                    VRegister::try_from((addr >> 8) as u8)?
+                } else {
+                    VRegister::V0
+                };
+                self.registers.pc = self.registers[offset_register] as PCRegisterValue + addr;
             }
+            Instruction::Random(vx, byte) => self.registers[vx] = self.rng.random_byte() & byte,
             Instruction::Draw(vx, vy, nibble) => {
-                let x = XCoordinate::new(vx as usize);
-                let y = YCoordinate::new(vy as usize);
+                let resolution = self.display.resolution();
+                let x = XCoordinate::with_resolution(self.registers[vx] as usize, resolution);
+                let y = YCoordinate::with_resolution(self.registers[vy] as usize, resolution);
+
+                // XO-CHIP reads the sprites of all selected planes one after another
+                let planes = self.display.planes();
+                let mut sprites = Vec::new();
+                let mut offs = 0;
+                for plane in planes.iter() {
+                    let (sprite, size) = self.read_sprite(offs, nibble)?;
+                    sprites.push((plane, sprite));
+                    offs += size;
+                }
 
-                let mut sprite_data = Vec::with_capacity(nibble.into());
-                for offs in 0..nibble.into() {
-                    let addr = Addr::new(self.registers.i + offs as u16)?;
-                    sprite_data.push(self.memory.read(addr));
+                let mut draw_result = DrawResult::Drawn;
+                for (plane, sprite) in sprites {
+                    self.display.select_planes(plane);
+                    if self.display.draw(&sprite, x, y) == DrawResult::Overdrawn {
+                        draw_result = DrawResult::Overdrawn;
+                    }
                 }
-                let sprite = &sprite_data[..].into();
-                let draw_result = self.display.draw(sprite, x, y);
+                self.display.select_planes(planes);
+
                 self.registers[VRegister::VF] = match draw_result {
                     DrawResult::Drawn => 0,
                     DrawResult::Overdrawn => 1,
@@ -229,21 +758,26 @@ where
                 let key_idx = self.registers[vx];
                 let key = Key::try_from(key_idx)?;
                 if self.keypad[key] == KeyState::Pressed {
-                    self.registers.pc += 2;
+                    self.skip();
                 }
             }
             Instruction::SkipKeyNotPressed(vx) => {
                 let key_idx = self.registers[vx];
                 let key = Key::try_from(key_idx)?;
                 if self.keypad[key] == KeyState::NotPressed {
-                    self.registers.pc += 2;
+                    self.skip();
                 }
             }
-            // LoadRegisterDelayTimer(Vx)
+            Instruction::LoadRegisterDelayTimer(vx) => self.registers[vx] = self.timers.delay,
             Instruction::LoadKey(vx) => self.waiting_on_any_keypress = Some(vx),
-            // LoadDelayTimerRegister(Vx)
-            // LoadSoundTimerRegister(Vx)
-            Instruction::AddI(vx) => self.registers.i += self.registers[vx] as IRegisterValue,
+            Instruction::LoadDelayTimerRegister(vx) => self.timers.delay = self.registers[vx],
+            Instruction::LoadSoundTimerRegister(vx) => self.set_sound_timer(self.registers[vx]),
+            Instruction::AddI(vx) => {
+                self.registers.i = self
+                    .registers
+                    .i
+                    .wrapping_add(self.registers[vx] as IRegisterValue)
+            }
             Instruction::LoadSprite(vx) => {
                 let x = self.registers[vx] as u16;
                 self.registers.i =
@@ -252,46 +786,367 @@ where
             Instruction::LoadBinaryCodedDecimal(vx) => {
                 let mut num = self.registers[vx];
 
-                for (i, place) in vec![100, 10, 1].iter().enumerate() {
+                for (i, place) in [100, 10, 1].iter().enumerate() {
                     let bcd = num / place;
-                    self.memory
-                        .write(Addr::new(self.registers.i + i as u16)?, bcd);
+                    self.memory.write(self.i_addr(i as u16)?, bcd)?;
                     num -= bcd * place;
                 }
             }
             Instruction::LoadMemoryRegisters(vx) => {
                 for (offs, reg) in VRegister::iter_to(vx).enumerate() {
-                    let addr = Addr::new(self.registers.i + offs as u16)?;
-                    self.memory.write(addr, self.registers[reg]);
+                    let addr = self.i_addr(offs as u16)?;
+                    self.memory.write(addr, self.registers[reg])?;
                 }
 
-                self.registers.i = Addr::new(self.registers.i + vx as u16 + 1)?.into();
+                self.increment_i_after_memory(vx)?;
             }
             Instruction::LoadRegistersMemory(vx) => {
                 for (offs, reg) in VRegister::iter_to(vx).enumerate() {
-                    let addr = Addr::new(self.registers.i + offs as u16)?;
+                    let addr = self.i_addr(offs as u16)?;
                     self.registers[reg] = self.memory.read(addr);
                 }
 
-                self.registers.i = Addr::new(self.registers.i + vx as u16 + 1)?.into();
+                self.increment_i_after_memory(vx)?;
+            }
+            Instruction::ScrollDown(nibble) => self.display.scroll_down(nibble.into()),
+            Instruction::ScrollRight => self.display.scroll_right(),
+            Instruction::ScrollLeft => self.display.scroll_left(),
+            Instruction::Exit => self.halted = true,
+            Instruction::LowResolution => self.display.set_resolution(Resolution::Low),
+            Instruction::HighResolution => self.display.set_resolution(Resolution::High),
+            Instruction::LoadBigSprite(vx) => {
+                let x = self.registers[vx] as u16;
+                self.registers.i =
+                    Addr::new(BIG_FONT_RAW_ADDR + x * crate::font::BIG_FONT_SPRITE_ROWS as u16)?
+                        .into();
+            }
+            Instruction::StoreFlags(vx) => {
+                for reg in VRegister::iter_to(vx) {
+                    self.rpl_flags[reg as usize] = self.registers[reg];
+                }
+            }
+            Instruction::LoadFlags(vx) => {
+                for reg in VRegister::iter_to(vx) {
+                    self.registers[reg] = self.rpl_flags[reg as usize];
+                }
+            }
+            Instruction::SaveRange(vx, vy) => {
+                for (offs, reg) in VRegister::iter_range(vx, vy).enumerate() {
+                    let addr = self.i_addr(offs as u16)?;
+                    self.memory.write(addr, self.registers[reg])?;
+                }
+            }
+            Instruction::LoadRange(vx, vy) => {
+                for (offs, reg) in VRegister::iter_range(vx, vy).enumerate() {
+                    let addr = self.i_addr(offs as u16)?;
+                    self.registers[reg] = self.memory.read(addr);
+                }
+            }
+            Instruction::LoadILong(addr) => self.registers.i = addr.into(),
+            Instruction::SelectPlanes(nibble) => {
+                self.display
+                    .select_planes(Planes::new(usize::from(nibble) as u8));
+            }
+            Instruction::LoadAudio => {
+                for offs in 0..AUDIO_PATTERN_SIZE {
+                    self.audio_pattern[offs] = self.memory.read(self.i_addr(offs as u16)?);
+                }
             }
-            other => return Err(crate::errors::Chip8Error::UnimplementedInstruction(other)),
+            Instruction::Pitch(vx) => self.pitch = self.registers[vx],
         }
         Ok(())
     }
+
+    /// Returns the register shifted by `SHR` and `SHL` according to [`Quirks::shift_vx`]
+    fn shift_source(&self, vx: VRegister, vy: VRegister) -> VRegister {
+        if self.config.quirks.shift_vx {
+            vx
+        } else {
+            vy
+        }
+    }
+
+    /// Resets `VF` after `OR`, `AND` and `XOR` according to [`Quirks::logic_resets_vf`]
+    fn reset_vf_after_logic(&mut self) {
+        if self.config.quirks.logic_resets_vf {
+            self.registers[VRegister::VF] = 0;
+        }
+    }
+
+    /// Changes `I` after `LD [I], Vx` and `LD Vx, [I]` according to [`Quirks::memory_increment`]
+    fn increment_i_after_memory(&mut self, vx: VRegister) -> crate::errors::Result<()> {
+        let increment = match self.config.quirks.memory_increment {
+            MemoryIncrement::XPlusOne => vx as u16 + 1,
+            MemoryIncrement::X => vx as u16,
+            MemoryIncrement::Unchanged => 0,
+        };
+        self.registers.i = self.i_addr(increment)?.into();
+        Ok(())
+    }
+}
+
+impl<R, B, M> VM<R, Display, B, M>
+where
+    R: RandomSource,
+    B: Beeper,
+    M: Bus,
+{
+    /// Copies out the complete state, e.g. to save a game
+    ///
+    /// See [`Snapshot`] for what is not included.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        let keys = (0..16u8)
+            .filter_map(|key| Key::try_from(key).ok())
+            .filter(|key| self.keypad[*key] == KeyState::Pressed)
+            .fold(0, |keys, key| keys | 1 << key as u16);
+        Snapshot {
+            vregisters: self.registers.vregisters,
+            i: self.registers.i,
+            pc: self.registers.pc,
+            stack: self.stack.entries.clone(),
+            delay: self.timers.delay,
+            sound: self.timers.sound,
+            ticks: self.timers.ticks,
+            keys,
+            waiting_on_any_keypress: self.waiting_on_any_keypress,
+            pressed_while_waiting: self.pressed_while_waiting,
+            halted: self.halted,
+            rpl_flags: self.rpl_flags,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            memory: (0..self.memory.size())
+                .map(|addr| self.memory.read(Addr::long(addr as u16)))
+                .collect(),
+            display: self.display.save_state(),
+        }
+    }
+
+    /// Puts back the state of a [`snapshot`](Self::snapshot), e.g. to load a game
+    ///
+    /// The memory is written with [`Bus::load`], i.e. regardless of any write protection.
+    /// Starts or stops the [`Beeper`] if the sound timer changes between active and inactive.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Chip8Error::StackOverflow`](crate::errors::Chip8Error::StackOverflow)
+    /// if the stack of the `snapshot` exceeds [`Config::stack_depth`],
+    /// or [`Chip8Error::InvalidSnapshot`](crate::errors::Chip8Error::InvalidSnapshot)
+    /// if its memory differs in size from the [`Bus`]. The VM is not modified in these cases.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> crate::errors::Result<()> {
+        if snapshot.stack.len() > self.stack.depth {
+            return Err(crate::errors::Chip8Error::StackOverflow(self.stack.depth));
+        }
+        if snapshot.memory.len() != self.memory.size() {
+            return Err(crate::errors::Chip8Error::InvalidSnapshot);
+        }
+
+        self.registers.vregisters = snapshot.vregisters;
+        self.registers.i = snapshot.i;
+        self.registers.pc = snapshot.pc;
+        self.stack.entries.clone_from(&snapshot.stack);
+        self.timers.delay = snapshot.delay;
+        self.timers.ticks = snapshot.ticks;
+        self.set_sound_timer(snapshot.sound);
+        for key in (0..16u8).filter_map(|key| Key::try_from(key).ok()) {
+            self.keypad[key] = if snapshot.keys & 1 << key as u16 != 0 {
+                KeyState::Pressed
+            } else {
+                KeyState::NotPressed
+            };
+        }
+        self.waiting_on_any_keypress = snapshot.waiting_on_any_keypress;
+        self.pressed_while_waiting = snapshot.pressed_while_waiting;
+        self.halted = snapshot.halted;
+        self.rpl_flags = snapshot.rpl_flags;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+        self.memory.load(Addr::long(0), &snapshot.memory)?;
+        self.display.restore_state(&snapshot.display);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
+// offsets like `0x0111 + 0x0` are kept for symmetry with the following lines
+#[allow(clippy::identity_op)]
 mod tests {
     use super::*;
     use crate::instructions::{Instruction::*, VRegister::*};
     use crate::keypad::{Key::*, KeyState::*};
+    use crate::memory::{MappedBus, WriteProtection};
+    use crate::random::XorShift;
 
-    fn test_vm_default() -> VM<rand::rngs::mock::StepRng> {
-        let rng = rand::rngs::mock::StepRng::new(4, 0);
+    fn test_vm_default() -> VM<XorShift> {
+        let rng = XorShift::default();
         VM::new(rng, |_, _| Ok(()))
     }
 
+    fn test_vm_quirks(quirks: Quirks) -> VM<XorShift> {
+        let rng = XorShift::default();
+        let config = Config {
+            quirks,
+            ..Config::default()
+        };
+        VM::with_config(rng, |_, _| Ok(()), config)
+    }
+
+    #[test]
+    fn vm_load_rom() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+
+        vm.load_rom(&[0x62, 0xFF])?;
+
+        assert_eq!(vm.step()?, StepResult::Executed(LoadOperand(V2, 0xFF)));
+        Ok(())
+    }
+
+    #[test]
+    fn vm_with_bus_write_protected() -> crate::errors::Result<()> {
+        let mut bus = crate::memory::MappedBus::new(Memory::new());
+        bus.protect(0x000..=0x1FF, crate::memory::WriteProtection::Error);
+        let rng = XorShift::default();
+        let mut vm = VM::with_bus(
+            rng,
+            |_, _| Ok(()),
+            Config::default(),
+            Display::new(crate::display::EdgeMode::Wrap),
+            NoBeeper,
+            bus,
+        );
+        // LD I, 0x1FF; LD [I], V0
+        vm.load_rom(&[0xA1, 0xFF, 0xF0, 0x55])?;
+
+        vm.step()?;
+        assert_eq!(
+            vm.step(),
+            Err(crate::errors::Chip8Error::WriteProtected(0x1FF))
+        );
+        // fonts are loaded regardless of protection
+        assert_eq!(vm.memory().read(Addr::long(0x000)), 0xF0);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_load_rom_program_start() -> crate::errors::Result<()> {
+        let rng = XorShift::default();
+        let config = Config {
+            program_start: 0x0600.into(),
+            ..Config::default()
+        };
+        let mut vm = VM::with_config(rng, |_, _| Ok(()), config);
+
+        vm.load_rom(&[0x62, 0xFF])?;
+
+        assert_eq!(vm.registers.pc, 0x0600);
+        assert_eq!(vm.memory.read(0x0600.into()), 0x62);
+        assert_eq!(vm.memory.read(0x0601.into()), 0xFF);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_load_rom_too_large() {
+        let mut vm = test_vm_default();
+        let rom = [0; Memory::SIZE - PROGRAM_START as usize + 1];
+
+        let res = vm.load_rom(&rom);
+
+        assert_eq!(
+            res,
+            Err(crate::errors::Chip8Error::RomTooLarge {
+                size: 3585,
+                available: 3584
+            })
+        );
+    }
+
+    #[test]
+    fn vm_step_executed() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.memory.write(0x0200.into(), 0x62);
+        vm.memory.write(0x0201.into(), 0xFF);
+
+        let res = vm.step()?;
+
+        assert_eq!(res, StepResult::Executed(LoadOperand(V2, 0xFF)));
+        assert_eq!(vm.registers[V2], 0xFF);
+        assert_eq!(vm.registers.pc, 0x0202);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_step_jump() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.memory.write(0x0200.into(), 0x12);
+        vm.memory.write(0x0201.into(), 0x00);
+
+        vm.step()?;
+        vm.step()?;
+
+        assert_eq!(vm.registers.pc, 0x0200);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_step_skip() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.memory.write(0x0200.into(), 0x30);
+        vm.memory.write(0x0201.into(), 0x00);
+
+        vm.step()?;
+
+        assert_eq!(vm.registers.pc, 0x0204);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_step_unknown_instruction() {
+        let mut vm = test_vm_default();
+        vm.memory.write(0x0200.into(), 0x5F);
+        vm.memory.write(0x0201.into(), 0xF1);
+
+        let res = vm.step();
+
+        assert_eq!(
+            res,
+            Err(crate::errors::Chip8Error::UnknownInstruction(0x5FF1))
+        );
+        assert_eq!(vm.registers.pc, 0x0200);
+    }
+
+    #[test]
+    fn vm_step_pc_out_of_range() {
+        let mut vm = test_vm_default();
+        vm.registers.pc = 0x0FFF;
+
+        let res = vm.step();
+
+        assert_eq!(res, Err(crate::errors::Chip8Error::OutOfRange(0x1000)));
+    }
+
+    #[test]
+    fn vm_step_waiting_for_key() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.memory.write(0x0200.into(), 0xF3);
+        vm.memory.write(0x0201.into(), 0x0A);
+
+        assert_eq!(vm.step()?, StepResult::Executed(LoadKey(V3)));
+        assert_eq!(vm.step()?, StepResult::WaitingForKey);
+        assert_eq!(vm.registers.pc, 0x0202);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_step_halted() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+
+        vm.halt();
+
+        assert_eq!(vm.step()?, StepResult::Halted);
+        assert_eq!(vm.registers.pc, PROGRAM_START);
+        Ok(())
+    }
+
     #[test]
     fn vregisters_set_get() {
         let mut registers = Registers::new();
@@ -345,7 +1200,7 @@ mod tests {
         };
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(all(feature = "std", feature = "rand"))]
     #[test]
     fn vm_execute_instruction_sys_default() -> crate::errors::Result<()> {
         let mut vm = VM::default();
@@ -363,7 +1218,7 @@ mod tests {
 
     #[test]
     fn vm_execute_instruction_sys_sysfn() -> crate::errors::Result<()> {
-        let mut vm = VM::new(rand::thread_rng(), |_, addr| {
+        let mut vm = VM::new(XorShift::default(), |_, addr| {
             assert_eq!(addr, 0x0FFF.into());
             Ok(())
         });
@@ -385,6 +1240,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn vm_execute_instruction_call_return() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.registers.pc = 0x0202;
+
+        vm.execute_instruction(&Instruction::Call(0x0400.into()))?;
+        assert_eq!(vm.registers.pc, 0x0400);
+
+        vm.execute_instruction(&Instruction::Return)?;
+        assert_eq!(vm.registers.pc, 0x0202);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_call_overflow() -> crate::errors::Result<()> {
+        let rng = XorShift::default();
+        let config = Config {
+            stack_depth: 2,
+            ..Config::default()
+        };
+        let mut vm = VM::with_config(rng, |_, _| Ok(()), config);
+
+        vm.execute_instruction(&Instruction::Call(0x0400.into()))?;
+        vm.execute_instruction(&Instruction::Call(0x0400.into()))?;
+        let res = vm.execute_instruction(&Instruction::Call(0x0400.into()));
+
+        assert_eq!(res, Err(crate::errors::Chip8Error::StackOverflow(2)));
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_return_underflow() {
+        let mut vm = test_vm_default();
+
+        let res = vm.execute_instruction(&Instruction::Return);
+
+        assert_eq!(res, Err(crate::errors::Chip8Error::StackUnderflow));
+    }
+
     #[test]
     fn vm_execute_instruction_skipequaloperand() -> crate::errors::Result<()> {
         let mut vm = test_vm_default();
@@ -512,6 +1406,24 @@ mod tests {
         }
     );
 
+    registers_test!(
+        vm_execute_instruction_sub_equal {
+            instruction: Sub(V2, V3),
+            registers_before: {V2 => 0x3, V3 => 0x3},
+            registers_after: {V2 => 0x0, V3 => 0x3},
+            register_overflow: 1,
+        }
+    );
+
+    registers_test!(
+        vm_execute_instruction_sub_vf {
+            instruction: Sub(VF, V3),
+            registers_before: {VF => 0x3, V3 => 0x4},
+            registers_after: {V3 => 0x4},
+            register_overflow: 0,
+        }
+    );
+
     registers_test!(
         vm_execute_instruction_sub_borrow {
             instruction: Sub(V2, V3),
@@ -557,6 +1469,15 @@ mod tests {
         }
     );
 
+    registers_test!(
+        vm_execute_instruction_subnegated_equal {
+            instruction: SubNegated(V2, V3),
+            registers_before: {V2 => 0x3, V3 => 0x3},
+            registers_after: {V2 => 0x0, V3 => 0x3},
+            register_overflow: 1,
+        }
+    );
+
     registers_test!(
         vm_execute_instruction_subnegated_borrow {
             instruction: SubNegated(V2, V3),
@@ -584,14 +1505,55 @@ mod tests {
         }
     );
 
-    registers_test!(
-        vm_execute_instruction_shiftleft_inplace_overflow {
-            instruction: ShiftLeft(V2, V2),
-            registers_before: {V2 => 0b1111_0111},
-            registers_after: {V2 => 0b1110_1110},
-            register_overflow: 1,
-        }
-    );
+    registers_test!(
+        vm_execute_instruction_shiftleft_inplace_overflow {
+            instruction: ShiftLeft(V2, V2),
+            registers_before: {V2 => 0b1111_0111},
+            registers_after: {V2 => 0b1110_1110},
+            register_overflow: 1,
+        }
+    );
+
+    #[test]
+    fn vm_execute_instruction_shiftright_quirk_shift_vx() -> crate::errors::Result<()> {
+        let mut vm = test_vm_quirks(Quirks::SUPER_CHIP_1_1);
+        vm.registers[V2] = 0b0000_0011;
+        vm.registers[V3] = 0b1000_0000;
+
+        vm.execute_instruction(&ShiftRight(V2, V3))?;
+
+        assert_eq!(vm.registers[V2], 0b0000_0001);
+        assert_eq!(vm.registers[V3], 0b1000_0000);
+        assert_eq!(vm.registers[VF], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_shiftleft_quirk_shift_vx() -> crate::errors::Result<()> {
+        let mut vm = test_vm_quirks(Quirks::CHIP_48);
+        vm.registers[V2] = 0b1100_0000;
+        vm.registers[V3] = 0b0000_0001;
+
+        vm.execute_instruction(&ShiftLeft(V2, V3))?;
+
+        assert_eq!(vm.registers[V2], 0b1000_0000);
+        assert_eq!(vm.registers[VF], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_or_quirk_logic_resets_vf() -> crate::errors::Result<()> {
+        let mut vm = test_vm_quirks(Quirks::COSMAC_VIP);
+        vm.registers[V2] = 0x01;
+        vm.registers[V3] = 0x10;
+        vm.registers[VF] = 0xFF;
+
+        vm.execute_instruction(&Or(V2, V3))?;
+
+        assert_eq!(vm.registers[V2], 0x11);
+        assert_eq!(vm.registers[VF], 0);
+        Ok(())
+    }
 
     #[test]
     fn vm_execute_instruction_skipnotequal() -> crate::errors::Result<()> {
@@ -629,6 +1591,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn vm_execute_instruction_longjump_quirk_jump_vx() -> crate::errors::Result<()> {
+        let mut vm = test_vm_quirks(Quirks::SUPER_CHIP_1_1);
+        vm.registers[V0] = 0x11;
+        vm.registers[V3] = 0x22;
+
+        vm.execute_instruction(&LongJump(0x0345.into()))?;
+
+        assert_eq!(vm.registers.pc, 0x0367);
+        Ok(())
+    }
+
     #[test]
     fn vm_execute_instruction_skipkeypressed() -> crate::errors::Result<()> {
         let mut vm = test_vm_default();
@@ -666,6 +1640,157 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn vm_execute_instruction_loadregisterdelaytimer() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.timers.delay = 0x42;
+
+        vm.execute_instruction(&LoadRegisterDelayTimer(V3))?;
+
+        assert_eq!(vm.registers[V3], 0x42);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_loaddelaytimerregister() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.registers[V3] = 0x42;
+
+        vm.execute_instruction(&LoadDelayTimerRegister(V3))?;
+
+        assert_eq!(vm.delay_timer(), 0x42);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_loadsoundtimerregister() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.registers[V3] = 0x42;
+
+        vm.execute_instruction(&LoadSoundTimerRegister(V3))?;
+
+        assert_eq!(vm.sound_timer(), 0x42);
+        assert!(vm.is_sound_active());
+        Ok(())
+    }
+
+    #[test]
+    fn vm_tick_timers() {
+        let mut vm = test_vm_default();
+        vm.timers.delay = 2;
+        vm.timers.sound = 1;
+
+        vm.tick_timers();
+        assert_eq!(vm.delay_timer(), 1);
+        assert_eq!(vm.sound_timer(), 0);
+        assert!(!vm.is_sound_active());
+
+        vm.tick_timers();
+        vm.tick_timers();
+        assert_eq!(vm.delay_timer(), 0);
+        assert_eq!(vm.sound_timer(), 0);
+    }
+
+    #[test]
+    fn vm_key_down_press() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.execute_instruction(&LoadKey(V6))?;
+
+        vm.key_down(Key7);
+
+        assert_eq!(vm.waiting_on_any_keypress, None);
+        assert_eq!(vm.registers[V6], 0x7);
+        assert_eq!(vm.keypad[Key7], Pressed);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_key_down_not_waiting() {
+        let mut vm = test_vm_default();
+
+        vm.key_down(Key7);
+
+        assert_eq!(vm.registers[V0], 0x0);
+        assert_eq!(vm.keypad[Key7], Pressed);
+    }
+
+    #[test]
+    fn vm_key_up_release() -> crate::errors::Result<()> {
+        let rng = XorShift::default();
+        let config = Config {
+            quirks: Quirks {
+                key_wait: KeyWait::Release,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        };
+        let mut vm = VM::with_config(rng, |_, _| Ok(()), config);
+        vm.key_down(Key1);
+        vm.execute_instruction(&LoadKey(V6))?;
+
+        // key was already pressed before waiting
+        vm.key_up(Key1);
+        assert_eq!(vm.waiting_on_any_keypress, Some(V6));
+
+        vm.key_down(Key7);
+        assert_eq!(vm.waiting_on_any_keypress, Some(V6));
+
+        vm.key_up(Key7);
+        assert_eq!(vm.waiting_on_any_keypress, None);
+        assert_eq!(vm.registers[V6], 0x7);
+        assert_eq!(vm.keypad[Key7], NotPressed);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_step_resumes_after_key() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.load_rom(&[0xF3, 0x0A, 0x62, 0xFF])?;
+
+        assert_eq!(vm.step()?, StepResult::Executed(LoadKey(V3)));
+        assert_eq!(vm.step()?, StepResult::WaitingForKey);
+        vm.key_down(KeyA);
+
+        assert_eq!(vm.step()?, StepResult::Executed(LoadOperand(V2, 0xFF)));
+        assert_eq!(vm.registers[V3], 0xA);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_beeper() -> crate::errors::Result<()> {
+        use crate::beeper::{BeepEvent, RecordingBeeper};
+
+        let rng = XorShift::default();
+        let mut vm = VM::with_hardware(
+            rng,
+            |_, _| Ok(()),
+            Config::default(),
+            Display::default(),
+            RecordingBeeper::new(),
+        );
+        vm.registers[V0] = 2;
+        vm.registers[V1] = 0;
+
+        vm.tick_timers();
+        vm.execute_instruction(&LoadSoundTimerRegister(V0))?;
+        vm.tick_timers();
+        vm.tick_timers();
+        vm.tick_timers();
+        vm.execute_instruction(&LoadSoundTimerRegister(V0))?;
+        vm.execute_instruction(&LoadSoundTimerRegister(V1))?;
+
+        assert_eq!(
+            vm.beeper().events(),
+            [
+                BeepEvent::Start(1),
+                BeepEvent::Stop(3),
+                BeepEvent::Start(4),
+                BeepEvent::Stop(4)
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn vm_execute_instruction_addi() -> crate::errors::Result<()> {
         let mut vm = test_vm_default();
@@ -680,13 +1805,15 @@ mod tests {
 
     #[test]
     fn vm_execute_instruction_random() -> crate::errors::Result<()> {
-        let rng = rand::rngs::mock::StepRng::new(0b1000_0000, 0);
-        let mut vm = VM::new(rng, |_, _| Ok(()));
+        let mut vm = VM::new(XorShift::new(42), |_, _| Ok(()));
         vm.registers[V0] = 0x00;
 
         vm.execute_instruction(&Instruction::Random(V0, 0b1100_0000))?;
 
-        assert_eq!(vm.registers[V0], 0b1000_0000);
+        assert_eq!(
+            vm.registers[V0],
+            XorShift::new(42).random_byte() & 0b1100_0000
+        );
         Ok(())
     }
 
@@ -709,6 +1836,168 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn vm_execute_instruction_draw_register_values() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.registers[V0] = 0x10;
+        vm.registers[V1] = 0x08;
+        vm.registers.i = 0x0111;
+        vm.memory.write(0x0111.into(), 0b1000_0000);
+
+        vm.execute_instruction(&Instruction::Draw(V0, V1, 1.into()))?;
+
+        let display = vm.display();
+        assert_eq!(
+            display.pixel(XCoordinate::new(0x10), YCoordinate::new(0x08)),
+            crate::display::Pixel::On
+        );
+        assert_eq!(
+            display.pixel(XCoordinate::new(0x00), YCoordinate::new(0x00)),
+            crate::display::Pixel::Off
+        );
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct CountingScreen {
+        clears: usize,
+        draws: usize,
+    }
+
+    impl Screen for CountingScreen {
+        fn clear(&mut self) {
+            self.clears += 1;
+        }
+
+        fn draw(
+            &mut self,
+            _sprite: &crate::display::Sprite,
+            _x: XCoordinate,
+            _y: YCoordinate,
+        ) -> DrawResult {
+            self.draws += 1;
+            DrawResult::Overdrawn
+        }
+
+        fn resolution(&self) -> Resolution {
+            Resolution::Low
+        }
+
+        fn set_resolution(&mut self, _resolution: Resolution) {}
+
+        fn scroll_down(&mut self, _rows: usize) {}
+
+        fn scroll_left(&mut self) {}
+
+        fn scroll_right(&mut self) {}
+    }
+
+    #[test]
+    fn vm_with_screen() -> crate::errors::Result<()> {
+        let rng = XorShift::default();
+        let mut vm = VM::with_screen(
+            rng,
+            |_, _| Ok(()),
+            Config::default(),
+            CountingScreen::default(),
+        );
+
+        vm.execute_instruction(&Instruction::Clear)?;
+        vm.execute_instruction(&Instruction::Draw(V0, V1, 1.into()))?;
+
+        assert_eq!(vm.display.clears, 1);
+        assert_eq!(vm.display.draws, 1);
+        assert_eq!(vm.registers[VF], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_draw_high_resolution_big_sprite() -> crate::errors::Result<()> {
+        use crate::display::Pixel;
+
+        let mut vm = test_vm_default();
+        vm.execute_instruction(&Instruction::HighResolution)?;
+        vm.registers[V0] = 100;
+        vm.registers[V1] = 40;
+        vm.registers.i = 0x0300;
+        vm.memory.write(0x0300.into(), 0b1000_0000);
+        vm.memory.write(0x0301.into(), 0b0000_0001);
+        vm.memory.write(0x031F.into(), 0b0000_0001);
+
+        vm.execute_instruction(&Instruction::Draw(V0, V1, 0.into()))?;
+
+        let display = vm.display();
+        assert_eq!(display.width(), Display::HIGH_RES_WIDTH);
+        assert_eq!(
+            display.pixel(
+                XCoordinate::with_resolution(100, Resolution::High),
+                YCoordinate::with_resolution(40, Resolution::High)
+            ),
+            Pixel::On
+        );
+        assert_eq!(
+            display.pixel(
+                XCoordinate::with_resolution(115, Resolution::High),
+                YCoordinate::with_resolution(40, Resolution::High)
+            ),
+            Pixel::On
+        );
+        assert_eq!(
+            display.pixel(
+                XCoordinate::with_resolution(115, Resolution::High),
+                YCoordinate::with_resolution(55, Resolution::High)
+            ),
+            Pixel::On
+        );
+        assert_eq!(vm.registers[VF], 0);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_exit() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.load_rom(&[0x00, 0xFD])?;
+
+        assert_eq!(vm.step()?, StepResult::Executed(Exit));
+        assert_eq!(vm.step()?, StepResult::Halted);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_loadbigsprite() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.registers[V0] = 0x8;
+
+        vm.execute_instruction(&Instruction::LoadBigSprite(V0))?;
+
+        let mut sprite_data = vec![];
+        for offs in 0..crate::font::BIG_FONT_SPRITE_ROWS {
+            let addr = Addr::new(vm.registers.i + offs as u16)?;
+            sprite_data.push(vm.memory.read(addr));
+        }
+        assert_eq!(sprite_data[..], crate::font::BIG_SPRITE_DATA_8);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_storeflags_loadflags() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.registers[V0] = 0xAA;
+        vm.registers[V1] = 0xBB;
+        vm.registers[V2] = 0xCC;
+
+        vm.execute_instruction(&StoreFlags(V1))?;
+        vm.registers[V0] = 0x00;
+        vm.registers[V1] = 0x00;
+        vm.registers[V2] = 0x00;
+        vm.execute_instruction(&LoadFlags(V2))?;
+
+        assert_eq!(vm.registers[V0], 0xAA);
+        assert_eq!(vm.registers[V1], 0xBB);
+        assert_eq!(vm.registers[V2], 0x00);
+        Ok(())
+    }
+
     #[test]
     fn vm_execute_instruction_loadsprite() -> crate::errors::Result<()> {
         let mut vm = test_vm_default();
@@ -803,6 +2092,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn vm_execute_instruction_loadmemoryregisters_quirk_memory_increment_x(
+    ) -> crate::errors::Result<()> {
+        let mut vm = test_vm_quirks(Quirks::CHIP_48);
+        vm.registers.i = 0x0111;
+
+        vm.execute_instruction(&LoadMemoryRegisters(V3))?;
+
+        assert_eq!(vm.registers.i, 0x0111 + 3);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_loadregistersmemory_quirk_memory_increment_unchanged(
+    ) -> crate::errors::Result<()> {
+        let mut vm = test_vm_quirks(Quirks::SUPER_CHIP_1_1);
+        vm.registers.i = 0x0111;
+        vm.memory.write(0x0111.into(), 0xAA);
+
+        vm.execute_instruction(&LoadRegistersMemory(V3))?;
+
+        assert_eq!(vm.registers[V0], 0xAA);
+        assert_eq!(vm.registers.i, 0x0111);
+        Ok(())
+    }
+
     #[test]
     fn vm_execute_instruction_loadregistersmemory_all() -> crate::errors::Result<()> {
         let mut vm = test_vm_default();
@@ -860,4 +2175,201 @@ mod tests {
         assert_eq!(vm.registers.i, 0x0111 + 1);
         Ok(())
     }
+
+    fn test_vm_xo_chip() -> VM<XorShift> {
+        let rng = XorShift::default();
+        let config = Config {
+            quirks: Quirks::XO_CHIP,
+            memory_size: Memory::XO_CHIP_SIZE,
+            ..Config::default()
+        };
+        VM::with_config(rng, |_, _| Ok(()), config)
+    }
+
+    #[test]
+    fn vm_step_loadilong() -> crate::errors::Result<()> {
+        let mut vm = test_vm_xo_chip();
+        vm.load_rom(&[0xF0, 0x00, 0xAB, 0xCD, 0xF1, 0x02])?;
+
+        assert_eq!(
+            vm.step()?,
+            StepResult::Executed(LoadILong(Addr::long(0xABCD)))
+        );
+        assert_eq!(vm.registers.i, 0xABCD);
+        assert_eq!(vm.registers.pc, 0x0204);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_skip_loadilong() -> crate::errors::Result<()> {
+        let mut vm = test_vm_xo_chip();
+        vm.load_rom(&[0x30, 0x00, 0xF0, 0x00, 0xAB, 0xCD])?;
+
+        vm.step()?;
+
+        assert_eq!(vm.registers.pc, 0x0206);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_xo_chip_memory() -> crate::errors::Result<()> {
+        let mut vm = test_vm_xo_chip();
+        vm.registers.i = 0xFFF0;
+        vm.registers[V0] = 0xAA;
+
+        vm.execute_instruction(&LoadMemoryRegisters(V0))?;
+
+        assert_eq!(vm.memory.read(Addr::long(0xFFF0)), 0xAA);
+        assert_eq!(
+            vm.load_rom(&[0; Memory::XO_CHIP_SIZE]),
+            Err(crate::errors::Chip8Error::RomTooLarge {
+                size: Memory::XO_CHIP_SIZE,
+                available: Memory::XO_CHIP_SIZE - PROGRAM_START as usize
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_saverange_loadrange() -> crate::errors::Result<()> {
+        let mut vm = test_vm_xo_chip();
+        vm.registers[V1] = 0xAA;
+        vm.registers[V2] = 0xBB;
+        vm.registers.i = 0x0111;
+
+        vm.execute_instruction(&SaveRange(V2, V1))?;
+
+        assert_eq!(vm.memory.read(0x0111.into()), 0xBB);
+        assert_eq!(vm.memory.read(0x0112.into()), 0xAA);
+        assert_eq!(vm.registers.i, 0x0111);
+
+        vm.execute_instruction(&LoadRange(V3, V4))?;
+
+        assert_eq!(vm.registers[V3], 0xBB);
+        assert_eq!(vm.registers[V4], 0xAA);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_draw_both_planes() -> crate::errors::Result<()> {
+        let mut vm = test_vm_xo_chip();
+        vm.registers.i = 0x0111;
+        vm.memory.write(0x0111.into(), 0b1000_0000);
+        vm.memory.write(0x0112.into(), 0b1100_0000);
+
+        vm.execute_instruction(&SelectPlanes(0x3.into()))?;
+        vm.execute_instruction(&Draw(V0, V0, 1.into()))?;
+
+        assert_eq!(vm.display.planes(), Planes::BOTH);
+        assert_eq!(
+            vm.display.color(XCoordinate::new(0), YCoordinate::new(0)),
+            3
+        );
+        assert_eq!(
+            vm.display.color(XCoordinate::new(1), YCoordinate::new(0)),
+            2
+        );
+        assert_eq!(vm.registers[VF], 0);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_execute_instruction_loadaudio_pitch() -> crate::errors::Result<()> {
+        let mut vm = test_vm_xo_chip();
+        vm.registers.i = 0x0111;
+        vm.memory.write(0x0111.into(), 0xAA);
+        vm.memory.write((0x0111 + 15).into(), 0xBB);
+        vm.registers[V0] = 112;
+
+        vm.execute_instruction(&LoadAudio)?;
+        vm.execute_instruction(&Pitch(V0))?;
+
+        assert_eq!(vm.audio_pattern()[0], 0xAA);
+        assert_eq!(vm.audio_pattern()[15], 0xBB);
+        assert_eq!(vm.pitch(), 112);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_snapshot_restore() -> crate::errors::Result<()> {
+        let mut vm = test_vm_default();
+        vm.load_rom(&[
+            0x60, 0x05, 0xA2, 0x0A, 0xD0, 0x05, 0x22, 0x0C, 0x00, 0x00, 0xF0,
+        ])?;
+        vm.step()?;
+        vm.step()?;
+        vm.step()?;
+        vm.step()?;
+        vm.key_down(Key5);
+        vm.set_sound_timer(3);
+        let snapshot = vm.snapshot();
+
+        let mut other = test_vm_default();
+        other.restore(&Snapshot::from_bytes(&snapshot.to_bytes())?)?;
+
+        assert_eq!(other.registers.vregisters, vm.registers.vregisters);
+        assert_eq!(other.registers.i, 0x20A);
+        assert_eq!(other.registers.pc, 0x20C);
+        assert_eq!(other.stack.entries, [0x208]);
+        assert_eq!(other.sound_timer(), 3);
+        assert_eq!(other.keypad[Key5], Pressed);
+        assert_eq!(other.snapshot().memory, vm.snapshot().memory);
+        assert_eq!(other.display().to_bitplane(), vm.display().to_bitplane());
+        assert_eq!(other.snapshot(), snapshot);
+        Ok(())
+    }
+
+    #[test]
+    fn vm_restore_stack_overflow() {
+        let mut vm = test_vm_default();
+        let mut snapshot = vm.snapshot();
+        snapshot.stack = alloc::vec![0x200; Config::DEFAULT_STACK_DEPTH + 1];
+
+        assert_eq!(
+            vm.restore(&snapshot),
+            Err(crate::errors::Chip8Error::StackOverflow(
+                Config::DEFAULT_STACK_DEPTH
+            ))
+        );
+        assert!(vm.stack.entries.is_empty());
+    }
+
+    #[test]
+    fn vm_restore_memory_size() {
+        let mut vm = test_vm_default();
+        let mut snapshot = vm.snapshot();
+        snapshot.memory = alloc::vec![0x44; Memory::XO_CHIP_SIZE];
+
+        assert_eq!(
+            vm.restore(&snapshot),
+            Err(crate::errors::Chip8Error::InvalidSnapshot)
+        );
+        assert_eq!(vm.memory.size(), Memory::SIZE);
+    }
+
+    #[test]
+    fn vm_snapshot_restore_mapped_bus() -> crate::errors::Result<()> {
+        let mut bus = MappedBus::new(Memory::new());
+        bus.protect(0x200..=0x2FF, WriteProtection::Error);
+        let mut vm = VM::with_bus(
+            XorShift::default(),
+            |_, _| Ok(()),
+            Config::default(),
+            Display::new(crate::display::EdgeMode::Wrap),
+            NoBeeper,
+            bus,
+        );
+        vm.load_rom(&[0x60, 0x05, 0xA3, 0x00, 0xF0, 0x55])?;
+        vm.step()?;
+        let snapshot = vm.snapshot();
+        vm.step()?;
+        vm.step()?;
+
+        vm.restore(&snapshot)?;
+
+        assert_eq!(vm.pc(), 0x202);
+        assert_eq!(vm.memory().read(Addr::long(0x300)), 0x00);
+        assert_eq!(vm.snapshot(), snapshot);
+        Ok(())
+    }
 }